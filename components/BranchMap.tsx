@@ -35,6 +35,17 @@ function smoothScrollTo(el: HTMLElement, targetLeft: number, durationMs: number)
   requestAnimationFrame(step);
 }
 
+// What stands between an open PR and landing, most blocking first
+function prReadiness(pr: OpenPR): { label: string; color: string } {
+  if (pr.draft) return { label: 'draft', color: '#78716c' };
+  if (pr.mergeable === 'conflicting') return { label: 'conflicts', color: '#dc2626' };
+  if (pr.checks === 'failure' || pr.checks === 'error') return { label: 'checks failing', color: '#dc2626' };
+  if (pr.reviewDecision === 'changes-requested') return { label: 'changes requested', color: '#d97706' };
+  if (pr.checks === 'pending' || pr.checks === 'expected') return { label: 'checks running', color: '#78716c' };
+  if (pr.reviewDecision === 'review-required') return { label: 'awaiting review', color: '#78716c' };
+  return { label: 'ready', color: '#16a34a' };
+}
+
 function fmtRelativeDate(dateStr: string): string {
   const diffDays = Math.floor((Date.now() - new Date(dateStr).getTime()) / 86400000);
   if (diffDays === 0) return 'today';
@@ -135,6 +146,7 @@ export default function BranchMap({
   // Inactive error branches render grey (no status colors)
   const inactiveErrorSet = new Set(inactiveErrorBranches.map(b => b.name));
   const openPRBranchNames = new Set(openPRs.map(p => p.branchName));
+  const openPRByBranch = new Map(openPRs.map(p => [p.branchName, p]));
 
   // Trigger draw-in animations when merge nodes first arrive (not on mount),
   // so the timeline always animates in even when the map view is shown before data loads.
@@ -739,6 +751,8 @@ export default function BranchMap({
               const hasSelection = selectedBranch != null;
               const isInactiveError = inactiveErrorSet.has(b.name);
               const hasOpenPR = openPRBranchNames.has(b.name);
+              const openPR = openPRByBranch.get(b.name);
+              const readiness = openPR ? prReadiness(openPR) : null;
               const daysSinceCommit = (Date.now() - new Date(b.lastCommitDate).getTime()) / 86400000;
               const showClockIcon = hasOpenPR && daysSinceCommit >= 60;
 
@@ -908,6 +922,19 @@ export default function BranchMap({
                       fill="#78716c"
                     >
                       +{b.commitsAhead}
+                      {openPR && readiness && (
+                        <tspan dx={8} fill={readiness.color}>
+                          #{openPR.number} {readiness.label}
+                          <title>
+                            {[
+                              openPR.title,
+                              `@${openPR.authorLogin}`,
+                              openPR.requestedReviewers.length > 0 && `Waiting on ${openPR.requestedReviewers.join(', ')}`,
+                              openPR.labels.length > 0 && openPR.labels.join(', '),
+                            ].filter(Boolean).join('\n')}
+                          </title>
+                        </tspan>
+                      )}
                     </text>
 
                     {/* Clock icon for open PR + 60+ day stale commit */}
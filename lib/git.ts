@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import type { Branch, MergeNode } from '../types';
+import type { Branch, MergedPR, MergeNode } from '../types';
 
 export interface RepoInfo {
   name: string;
@@ -19,9 +19,10 @@ export async function getMergeNodes(
   repoPath: string,
   branch: string,
   page: number,
-  perPage: number
+  perPage: number,
+  mergedPrs?: MergedPR[]
 ): Promise<MergeNodesResponse> {
-  return invoke('get_merge_nodes', { repoPath, branch, page, perPage });
+  return invoke('get_merge_nodes', { repoPath, branch, page, perPage, mergedPrs });
 }
 
 export async function getDefaultBranch(repoPath: string): Promise<string> {
@@ -0,0 +1,25 @@
+//! Date arguments shared by the git and GitHub commands.
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+/// Parse a cutoff date: an RFC 3339 timestamp, or a `YYYY-MM-DD` day.
+pub(crate) fn parse_since(since: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(since) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(since, "%Y-%m-%d")
+        .map(|day| day.and_time(NaiveTime::MIN).and_utc())
+        .map_err(|_| format!("Invalid date: {since}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("2024-05-01").unwrap().to_rfc3339(), "2024-05-01T00:00:00+00:00");
+        assert_eq!(parse_since("2024-05-01T12:00:00+02:00").unwrap().to_rfc3339(), "2024-05-01T10:00:00+00:00");
+        assert_eq!(parse_since("last week").unwrap_err(), "Invalid date: last week");
+    }
+}
@@ -0,0 +1,775 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use super::cli::{CliBackend, GitError};
+use super::conventional::ConventionalHeader;
+use super::libgit2::Libgit2Backend;
+use super::trailers::Trailers;
+
+/// A ref as returned by `list_refs`: full ref name and the object it points
+/// at, with annotated tags peeled to their target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefEntry {
+    pub name: String,
+    pub sha: String,
+}
+
+/// A tag pointing (directly or through a tag object) at a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagEntry {
+    /// Short name, e.g. `v1.2.0`
+    pub name: String,
+    /// Commit the tag points at
+    pub target_sha: String,
+    pub annotated: bool,
+    pub tagger_name: Option<String>,
+    pub tagger_email: Option<String>,
+    /// Tagger date of annotated tags, committer date of the target otherwise
+    pub date: String,
+    /// Message of annotated tags, without any signature
+    pub message: Option<String>,
+}
+
+/// What a repository root is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepoKind {
+    /// A work tree with its own `.git` directory
+    Normal,
+    /// A git directory without a work tree, e.g. a mirror
+    Bare,
+    /// A linked work tree (`git worktree add`)
+    Worktree,
+    /// A work tree checked out as a submodule of the repository around it
+    Submodule,
+}
+
+/// A working tree attached to the repository, as in `git worktree list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorktreeEntry {
+    pub path: String,
+    /// `None` for a bare repository or an unborn branch
+    pub head_sha: Option<String>,
+    /// Full ref name of the checked-out branch; `None` when detached
+    pub branch: Option<String>,
+    pub bare: bool,
+}
+
+/// Where a repository keeps its metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitDirs {
+    /// The git directory of this working tree (`.git`, or
+    /// `.git/worktrees/<name>` for a linked worktree)
+    pub git_dir: PathBuf,
+    /// The directory shared by all worktrees, holding refs and objects
+    pub common_dir: PathBuf,
+}
+
+/// A submodule declared in `.gitmodules`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmoduleEntry {
+    pub name: String,
+    /// Relative to the superproject's top level
+    pub path: String,
+    pub url: Option<String>,
+    /// `submodule.<name>.branch`, if set
+    pub branch: Option<String>,
+    /// Commit the superproject's HEAD records for it; `None` if the
+    /// submodule hasn't been committed yet
+    pub pinned_sha: Option<String>,
+}
+
+/// Resolve symlinks (git reports `/private/tmp` for `/tmp`), keeping the path
+/// as given if that fails.
+pub(super) fn canonical(path: &Path) -> std::path::PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Cut the signature block off a tag message.
+pub(super) fn strip_tag_signature(message: &str) -> String {
+    let end = ["-----BEGIN PGP SIGNATURE-----", "-----BEGIN SSH SIGNATURE-----"]
+        .iter()
+        .filter_map(|marker| message.find(marker))
+        .min()
+        .unwrap_or(message.len());
+    message[..end].trim_end().to_string()
+}
+
+/// Which commits `log` should return based on their parent count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeFilter {
+    #[default]
+    All,
+    /// Only commits with at most one parent (`--no-merges`)
+    Exclude,
+}
+
+/// Parameters for a `git log` style history walk.
+#[derive(Debug, Clone, Default)]
+pub struct LogQuery<'a> {
+    /// Revisions to start walking from
+    pub include: &'a [&'a str],
+    /// Revision whose history is excluded (the `^rev` in `rev..include`)
+    pub exclude: Option<&'a str>,
+    pub merges: MergeFilter,
+    pub skip: u32,
+    pub max_count: Option<u32>,
+    /// Only commits made at or after this Unix time (committer date, `--since`)
+    pub since: Option<i64>,
+    /// Never show a commit before all of its children (`--date-order`)
+    pub children_first: bool,
+    /// Follow only the first parent of merges (`--first-parent`)
+    pub first_parent: bool,
+    /// Check commit signatures and fill in `Commit::signature`. This runs
+    /// gpg or ssh-keygen per signed commit, so it is off by default.
+    pub verify_signatures: bool,
+}
+
+/// Result of verifying a commit signature, as reported by `%G?`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureStatus {
+    /// `G`: good signature from a trusted key
+    Good,
+    /// `B`: the signature doesn't match the commit
+    Bad,
+    /// `U`: good signature, key of unknown validity
+    UnknownValidity,
+    /// `X`: good signature that has expired
+    ExpiredSignature,
+    /// `Y`: good signature made by a key that has since expired
+    ExpiredKey,
+    /// `R`: good signature made by a revoked key
+    RevokedKey,
+    /// `E`: signed, but it can't be checked (e.g. the key is missing)
+    CannotCheck,
+    /// `N`: no signature
+    Unsigned,
+}
+
+impl SignatureStatus {
+    pub fn from_code(code: &str) -> Self {
+        match code {
+            "G" => SignatureStatus::Good,
+            "B" => SignatureStatus::Bad,
+            "U" => SignatureStatus::UnknownValidity,
+            "X" => SignatureStatus::ExpiredSignature,
+            "Y" => SignatureStatus::ExpiredKey,
+            "R" => SignatureStatus::RevokedKey,
+            "E" => SignatureStatus::CannotCheck,
+            _ => SignatureStatus::Unsigned,
+        }
+    }
+}
+
+/// GPG or SSH signature on a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Signature {
+    pub status: SignatureStatus,
+    /// Signer name (GPG user id, or SSH principal from the allowed signers file)
+    pub signer: Option<String>,
+    /// Key id or fingerprint
+    pub key: Option<String>,
+}
+
+/// One commit from a history walk. This is what every command that lists
+/// commits returns; the renamed fields keep the JSON the frontend already
+/// reads (`sha`, `message`, `author`, `date`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Commit {
+    pub full_sha: String,
+    #[serde(rename = "sha")]
+    pub short_sha: String,
+    /// First line of the message
+    #[serde(rename = "message")]
+    pub subject: String,
+    /// Rest of the message after the subject, without trailing newlines
+    pub body: String,
+    #[serde(rename = "author")]
+    pub author_name: String,
+    pub author_email: String,
+    /// Author date, strict ISO 8601 (`%aI`)
+    #[serde(rename = "date")]
+    pub author_date: String,
+    #[serde(rename = "committer")]
+    pub committer_name: String,
+    pub committer_email: String,
+    /// Committer date, strict ISO 8601 (`%cI`)
+    pub committer_date: String,
+    /// Parent SHAs, first parent first
+    pub parents: Vec<String>,
+    pub tree: String,
+    /// Conventional Commit header parsed from the subject
+    pub conventional: Option<ConventionalHeader>,
+    /// Trailers closing the body (co-authors, sign-offs, fixed issues)
+    pub trailers: Trailers,
+    /// Only set when the query asked for `verify_signatures`
+    pub signature: Option<Signature>,
+}
+
+/// Everything the branch map needs about one ref, gathered in bulk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchSummary {
+    /// Full ref name, e.g. `refs/heads/feature`
+    pub refname: String,
+    pub head_sha: String,
+    pub author: String,
+    /// Author date of the tip commit, strict ISO 8601
+    pub date: String,
+    /// Commits on the branch that are not in the base
+    pub ahead: i32,
+    /// Commits in the base that are not on the branch
+    pub behind: i32,
+    /// Merge base with the base branch (`None` for unrelated histories)
+    pub fork_sha: Option<String>,
+    pub fork_date: Option<String>,
+    /// Full ref name of the configured upstream, if it still exists
+    pub upstream: Option<String>,
+    /// `(ahead, behind)` against `upstream`: unpushed and unpulled commits
+    pub upstream_counts: Option<(i32, i32)>,
+}
+
+/// The two revisions to compare in a diff.
+#[derive(Debug, Clone, Copy)]
+pub enum DiffRange<'a> {
+    /// `git diff from to`
+    Between(&'a str, &'a str),
+    /// `git diff base...head`: changes on `head` since it forked from `base`
+    Since(&'a str, &'a str),
+}
+
+impl DiffRange<'_> {
+    /// Render as a single git revision range argument.
+    pub fn to_arg(self) -> String {
+        match self {
+            DiffRange::Between(from, to) => format!("{}..{}", from, to),
+            DiffRange::Since(base, head) => format!("{}...{}", base, head),
+        }
+    }
+}
+
+/// How a path conflicts in a merge, derived from which index stages it has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictKind {
+    /// Changed on both sides (text or binary)
+    Content,
+    /// Added on both sides with different contents
+    AddAdd,
+    /// Changed on one side, deleted on the other
+    ModifyDelete,
+    /// Anything else: rename/rename, file/directory, ...
+    Other,
+}
+
+impl ConflictKind {
+    pub fn from_stages(base: bool, ours: bool, theirs: bool) -> Self {
+        match (base, ours, theirs) {
+            (true, true, true) => ConflictKind::Content,
+            (false, true, true) => ConflictKind::AddAdd,
+            (true, true, false) | (true, false, true) => ConflictKind::ModifyDelete,
+            _ => ConflictKind::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    pub path: String,
+    pub kind: ConflictKind,
+}
+
+/// How a path changed from one state (HEAD, index, working tree) to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileChange {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Copied,
+    /// e.g. a file replaced by a symlink
+    TypeChanged,
+}
+
+/// A path with uncommitted changes, and how many lines they touch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangedFile {
+    pub path: String,
+    /// Where a renamed or copied file came from
+    pub old_path: Option<String>,
+    pub change: FileChange,
+    /// `None` for binary files
+    pub additions: Option<u32>,
+    pub deletions: Option<u32>,
+}
+
+/// Uncommitted changes in a working tree, each list sorted by path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkingChanges {
+    /// HEAD to index, with renames detected
+    pub staged: Vec<ChangedFile>,
+    /// Index to working tree
+    pub unstaged: Vec<ChangedFile>,
+    /// Every untracked, non-ignored file, counted as all added lines
+    pub untracked: Vec<ChangedFile>,
+    /// Unmerged paths; these are left out of the lists above
+    pub conflicted: Vec<MergeConflict>,
+}
+
+/// An entry of the stash reflog (`git stash list`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StashEntry {
+    /// `n` in `stash@{n}`
+    pub index: usize,
+    pub sha: String,
+    /// Reflog message, e.g. `WIP on main: 1a2b3c4 Add search`
+    pub message: String,
+    /// Committer date of the stash commit
+    pub date: String,
+}
+
+/// Line count of an untracked file, the way `git diff --numstat` would
+/// report it. `None` for binary (or unreadable) files.
+pub(super) fn count_lines(path: &Path) -> Option<u32> {
+    let bytes = std::fs::read(path).ok()?;
+    // git's own heuristic: a NUL in the first 8000 bytes means binary
+    if bytes.iter().take(8000).any(|&b| b == 0) {
+        return None;
+    }
+    let newlines = bytes.iter().filter(|&&b| b == b'\n').count();
+    let unterminated = !bytes.is_empty() && !bytes.ends_with(b"\n");
+    Some((newlines + usize::from(unterminated)) as u32)
+}
+
+/// The set of repository queries the app needs. Implemented once on top of
+/// the `git` CLI and once in-process via libgit2, so large repos can skip
+/// the per-query process spawn.
+pub trait GitBackend: Send + Sync {
+    /// Resolve a revision to an object SHA, or `None` if it doesn't exist.
+    fn resolve(&self, repo: &Path, rev: &str) -> Result<Option<String>, GitError>;
+
+    /// Target of a symbolic ref (e.g. `refs/remotes/origin/HEAD`), or `None`
+    /// if the ref is missing or not symbolic.
+    fn symbolic_ref(&self, repo: &Path, name: &str) -> Result<Option<String>, GitError>;
+
+    /// Absolute path of the working tree root.
+    fn toplevel(&self, repo: &Path) -> Result<String, GitError>;
+
+    /// What `dir` is the root of (a work tree's top level, or a bare
+    /// repository's git directory), following git's own discovery rules.
+    /// `None` if it isn't a repository root, including directories that are
+    /// merely inside one.
+    fn repo_kind(&self, dir: &Path) -> Result<Option<RepoKind>, GitError>;
+
+    /// Absolute git directory and common directory of `repo`.
+    fn git_dirs(&self, repo: &Path) -> Result<GitDirs, GitError>;
+
+    /// All refs under `prefix` (e.g. `refs/heads/`) that peel to a commit,
+    /// with that commit. Tags of trees and blobs are left out.
+    fn list_refs(&self, repo: &Path, prefix: &str) -> Result<Vec<RefEntry>, GitError>;
+
+    /// Tip commit, ahead/behind counts, fork point against `base` and upstream
+    /// state for every ref under any of `prefixes`, in as few git invocations
+    /// as possible.
+    fn branch_summaries(&self, repo: &Path, prefixes: &[&str], base: &str) -> Result<Vec<BranchSummary>, GitError>;
+
+    /// Every tag whose target is a commit, sorted by name.
+    fn tags(&self, repo: &Path) -> Result<Vec<TagEntry>, GitError>;
+
+    /// The main working tree (or bare repository) first, then linked
+    /// worktrees sorted by path. Worktrees whose directory is gone are left out.
+    fn worktrees(&self, repo: &Path) -> Result<Vec<WorktreeEntry>, GitError>;
+
+    /// Whether a working tree has staged, unstaged or untracked changes.
+    fn is_dirty(&self, worktree: &Path) -> Result<bool, GitError>;
+
+    /// Submodules declared in the working tree's `.gitmodules`, sorted by path.
+    fn submodules(&self, repo: &Path) -> Result<Vec<SubmoduleEntry>, GitError>;
+
+    /// Staged, unstaged, untracked and conflicted files in a working tree,
+    /// with per-file line counts.
+    fn working_changes(&self, worktree: &Path) -> Result<WorkingChanges, GitError>;
+
+    /// Stash entries, newest (`stash@{0}`) first.
+    fn stashes(&self, repo: &Path) -> Result<Vec<StashEntry>, GitError>;
+
+    /// Names of the configured remotes.
+    fn list_remotes(&self, repo: &Path) -> Result<Vec<String>, GitError>;
+
+    /// Walk history in reverse chronological order.
+    fn log(&self, repo: &Path, query: &LogQuery) -> Result<Vec<Commit>, GitError>;
+
+    /// How many commits `ours` has that `theirs` doesn't, and the reverse,
+    /// counted without loading them.
+    fn ahead_behind(&self, repo: &Path, ours: &str, theirs: &str) -> Result<(usize, usize), GitError>;
+
+    /// Best common ancestor of all of `revs`, `None` when their histories
+    /// never meet.
+    fn merge_base(&self, repo: &Path, revs: &[&str]) -> Result<Option<String>, GitError>;
+
+    /// Unified diff (3 lines of context) as patch text.
+    fn diff(&self, repo: &Path, range: DiffRange) -> Result<String, GitError>;
+
+    /// Paths changed in a diff.
+    fn diff_names(&self, repo: &Path, range: DiffRange) -> Result<Vec<String>, GitError>;
+
+    /// Recursively list file paths at `rev`, limited to `paths` when non-empty.
+    fn ls_tree(&self, repo: &Path, rev: &str, paths: &[&str]) -> Result<Vec<String>, GitError>;
+
+    /// Tar archive of the tree at `rev`.
+    fn archive(&self, repo: &Path, rev: &str) -> Result<Vec<u8>, GitError>;
+
+    /// `(commit, patch id)` for every non-merge commit in `include` but not in
+    /// `exclude`, newest first. Commits with equal patch ids make the same
+    /// change (`git patch-id --stable`).
+    fn patch_ids(&self, repo: &Path, include: &str, exclude: &str) -> Result<Vec<(String, String)>, GitError>;
+
+    /// Patch id of the whole diff between two commits, `None` if it is empty.
+    fn diff_patch_id(&self, repo: &Path, from: &str, to: &str) -> Result<Option<String>, GitError>;
+
+    /// Merge `theirs` into `ours` without touching the index or working tree.
+    /// Returns the conflicting paths sorted by path; empty means a clean merge.
+    fn merge_tree(&self, repo: &Path, ours: &str, theirs: &str) -> Result<Vec<MergeConflict>, GitError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    Cli,
+    Libgit2,
+}
+
+impl BackendKind {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "cli" | "git" => Some(BackendKind::Cli),
+            "libgit2" | "git2" => Some(BackendKind::Libgit2),
+            _ => None,
+        }
+    }
+}
+
+const UNSET: u8 = 0;
+const CLI: u8 = 1;
+const LIBGIT2: u8 = 2;
+
+static SELECTED: AtomicU8 = AtomicU8::new(UNSET);
+
+static CLI_BACKEND: CliBackend = CliBackend;
+static LIBGIT2_BACKEND: Libgit2Backend = Libgit2Backend;
+
+/// The currently selected backend kind. Defaults to `GIT_VIZ_BACKEND` from
+/// the environment, falling back to the CLI.
+pub fn backend_kind() -> BackendKind {
+    match SELECTED.load(Ordering::Relaxed) {
+        CLI => BackendKind::Cli,
+        LIBGIT2 => BackendKind::Libgit2,
+        _ => {
+            let kind = std::env::var("GIT_VIZ_BACKEND")
+                .ok()
+                .and_then(|v| BackendKind::parse(&v))
+                .unwrap_or(BackendKind::Cli);
+            set_backend(kind);
+            kind
+        }
+    }
+}
+
+/// Switch the backend used by all subsequent git queries.
+pub fn set_backend(kind: BackendKind) {
+    let value = match kind {
+        BackendKind::Cli => CLI,
+        BackendKind::Libgit2 => LIBGIT2,
+    };
+    SELECTED.store(value, Ordering::Relaxed);
+}
+
+/// The backend instance for a given kind.
+pub fn backend_for(kind: BackendKind) -> &'static dyn GitBackend {
+    match kind {
+        BackendKind::Cli => &CLI_BACKEND,
+        BackendKind::Libgit2 => &LIBGIT2_BACKEND,
+    }
+}
+
+/// The currently selected backend.
+pub fn backend() -> &'static dyn GitBackend {
+    backend_for(backend_kind())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fixture::Fixture;
+
+    fn both() -> [&'static dyn GitBackend; 2] {
+        [backend_for(BackendKind::Cli), backend_for(BackendKind::Libgit2)]
+    }
+
+    /// main: A - B - D - M        feature: A - B - C - E
+    ///                  \  /
+    ///        topic:     T
+    fn sample_repo(name: &str) -> Fixture {
+        let fx = Fixture::new(name);
+        fx.commit("README.md", "hello\n", "Initial commit");
+        fx.commit("app/page.tsx", "export default 1\n", "Add home page");
+        fx.git(&["checkout", "-q", "-b", "feature"]);
+        fx.commit("app/about/page.tsx", "about\n", "Add about | contact pages\n\nSplit into two | separated parts.\n\nRefs: #12");
+        fx.commit("lib/util.ts", "util\n", "feat(lib)!: add util\n\nCo-authored-by: Alice <alice@example.com>");
+        fx.git(&["checkout", "-q", "main"]);
+        fx.commit("README.md", "hello world\n", "Update readme");
+        fx.git(&["checkout", "-q", "-b", "topic"]);
+        fx.commit("docs/guide.md", "guide\n", "Add guide");
+        fx.git(&["checkout", "-q", "main"]);
+        fx.merge("topic", "Merge pull request #7 from someone/topic");
+        fx
+    }
+
+    #[test]
+    fn test_backends_agree_on_refs() {
+        let fx = sample_repo("backend-refs");
+        let [cli, lib] = both();
+        let path = fx.path();
+
+        assert_eq!(cli.list_refs(path, "refs/heads/").unwrap(), lib.list_refs(path, "refs/heads/").unwrap());
+        // Tags peel to their commit, through nested tags; tags of trees are left out
+        fx.git(&["tag", "-a", "v1", "-m", "v1"]);
+        fx.git(&["tag", "-a", "v1-again", "-m", "v1 again", "v1"]);
+        fx.git(&["tag", "tree", "HEAD^{tree}"]);
+        let tags = cli.list_refs(path, "refs/tags/").unwrap();
+        assert_eq!(tags, lib.list_refs(path, "refs/tags/").unwrap());
+        let head = fx.git(&["rev-parse", "HEAD"]);
+        let tags: Vec<(&str, &str)> = tags.iter().map(|t| (t.name.as_str(), t.sha.as_str())).collect();
+        assert_eq!(tags, vec![("refs/tags/v1", head.as_str()), ("refs/tags/v1-again", head.as_str())]);
+        assert_eq!(cli.resolve(path, "feature").unwrap(), lib.resolve(path, "feature").unwrap());
+        assert_eq!(cli.resolve(path, "tree^{commit}").unwrap(), None);
+        assert_eq!(lib.resolve(path, "tree^{commit}").unwrap(), None);
+        assert_eq!(lib.resolve(path, "does-not-exist").unwrap(), None);
+        assert_eq!(cli.resolve(path, "does-not-exist").unwrap(), None);
+        assert_eq!(cli.symbolic_ref(path, "HEAD").unwrap(), Some("refs/heads/main".to_string()));
+        assert_eq!(cli.symbolic_ref(path, "HEAD").unwrap(), lib.symbolic_ref(path, "HEAD").unwrap());
+        assert_eq!(cli.toplevel(path).unwrap(), lib.toplevel(path).unwrap());
+    }
+
+    #[test]
+    fn test_backends_agree_on_tags() {
+        let fx = sample_repo("backend-tags");
+        fx.git(&["tag", "light", "feature"]);
+        fx.git(&["tag", "-a", "v1.0.0", "-m", "Release 1.0.0\n\nFirst stable release.", "main~1"]);
+        let tree = fx.git(&["rev-parse", "main^{tree}"]);
+        fx.git(&["tag", "-a", "tree-tag", "-m", "Not a commit", &tree]);
+        let [cli, lib] = both();
+        let path = fx.path();
+
+        let tags = cli.tags(path).unwrap();
+        assert_eq!(tags, lib.tags(path).unwrap());
+        let names: Vec<&str> = tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["light", "v1.0.0"]);
+
+        assert!(!tags[0].annotated);
+        assert_eq!(tags[0].message, None);
+        let release = &tags[1];
+        assert_eq!(release.target_sha, fx.git(&["rev-parse", "main~1"]));
+        assert_eq!(release.tagger_email.as_deref(), Some("author@example.com"));
+        assert_eq!(release.message.as_deref(), Some("Release 1.0.0\n\nFirst stable release."));
+        assert_eq!(
+            strip_tag_signature("Release\n\n-----BEGIN SSH SIGNATURE-----\nabc\n-----END SSH SIGNATURE-----\n"),
+            "Release"
+        );
+    }
+
+    #[test]
+    fn test_backends_agree_on_branch_summaries() {
+        let fx = sample_repo("backend-summaries");
+        // A branch that has pulled main in since forking, and an unrelated one
+        fx.git(&["checkout", "-q", "-b", "synced", "feature"]);
+        fx.merge("main", "Merge branch 'main' into synced");
+        fx.commit("lib/more.ts", "more\n", "Add more");
+        fx.git(&["checkout", "-q", "--orphan", "orphan"]);
+        fx.commit("other.txt", "other\n", "Unrelated root");
+        fx.git(&["checkout", "-q", "main"]);
+
+        let [cli, lib] = both();
+        let path = fx.path();
+        let summaries = cli.branch_summaries(path, &["refs/heads/"], "main").unwrap();
+        assert_eq!(summaries, lib.branch_summaries(path, &["refs/heads/"], "main").unwrap());
+
+        let get = |name: &str| summaries.iter().find(|s| s.refname == format!("refs/heads/{name}")).unwrap();
+        let main_sha = fx.git(&["rev-parse", "main"]);
+
+        let feature = get("feature");
+        assert_eq!((feature.ahead, feature.behind), (2, 3));
+        assert_eq!(feature.fork_sha, Some(fx.git(&["rev-parse", "main~2"])));
+
+        let synced = get("synced");
+        assert_eq!((synced.ahead, synced.behind), (4, 0));
+        assert_eq!(synced.fork_sha.as_deref(), Some(main_sha.as_str()));
+
+        let topic = get("topic");
+        assert_eq!(topic.ahead, 0);
+        assert_eq!(topic.fork_sha.as_ref(), Some(&topic.head_sha));
+
+        let orphan = get("orphan");
+        assert_eq!((orphan.ahead, orphan.behind), (1, 5));
+        assert_eq!(orphan.fork_sha, None);
+    }
+
+    #[test]
+    fn test_backends_agree_on_criss_cross_fork() {
+        let fx = Fixture::new("backend-criss-cross");
+        fx.commit("README.md", "hello\n", "Initial commit");
+        fx.git(&["checkout", "-q", "-b", "cross"]);
+        let b1 = fx.commit("b.txt", "b\n", "Add b");
+        fx.git(&["checkout", "-q", "main"]);
+        let a1 = fx.commit("a.txt", "a\n", "Add a");
+        // Each side merges the other's tip, leaving two best common ancestors
+        fx.merge("cross", "Merge branch 'cross'");
+        fx.git(&["checkout", "-q", "cross"]);
+        fx.git(&["merge", "-q", "--no-ff", "-m", "Merge branch 'main' into cross", &a1]);
+        fx.commit("c.txt", "c\n", "Add c");
+        fx.git(&["checkout", "-q", "main"]);
+        let bases = fx.git(&["merge-base", "--all", "main", "cross"]);
+        assert_eq!(bases.lines().count(), 2);
+        assert!(bases.contains(&a1) && bases.contains(&b1));
+
+        let [cli, lib] = both();
+        let path = fx.path();
+        let summaries = cli.branch_summaries(path, &["refs/heads/"], "main").unwrap();
+        assert_eq!(summaries, lib.branch_summaries(path, &["refs/heads/"], "main").unwrap());
+        let cross = summaries.iter().find(|s| s.refname == "refs/heads/cross").unwrap();
+        // The newer of the two: `Add a` was committed after `Add b`
+        assert_eq!(cross.fork_sha.as_deref(), Some(a1.as_str()));
+    }
+
+    #[test]
+    fn test_backends_agree_on_log() {
+        let fx = sample_repo("backend-log");
+        let [cli, lib] = both();
+        let path = fx.path();
+
+        let queries = [
+            LogQuery { include: &["main"], ..Default::default() },
+            LogQuery { include: &["main"], merges: MergeFilter::Exclude, skip: 1, max_count: Some(2), ..Default::default() },
+            LogQuery { include: &["feature"], exclude: Some("main"), ..Default::default() },
+            LogQuery { include: &["main", "feature"], children_first: true, ..Default::default() },
+            LogQuery { include: &["main"], first_parent: true, ..Default::default() },
+        ];
+        for query in &queries {
+            assert_eq!(cli.log(path, query).unwrap(), lib.log(path, query).unwrap(), "{query:?}");
+        }
+
+        let feature = cli.log(path, &queries[2]).unwrap();
+        assert_eq!(feature.len(), 2);
+        assert_eq!(feature[1].subject, "Add about | contact pages");
+        assert_eq!(feature[1].body, "Split into two | separated parts.\n\nRefs: #12");
+        assert_eq!(feature[1].author_email, "author@example.com");
+        assert_eq!(feature[0].parents, vec![feature[1].full_sha.clone()]);
+
+        assert_eq!(feature[0].conventional.as_ref().map(|c| (c.kind.as_str(), c.breaking)), Some(("feat", true)));
+        assert_eq!(feature[0].trailers.co_authors[0].email.as_deref(), Some("alice@example.com"));
+        assert_eq!(feature[1].conventional, None);
+        assert_eq!(feature[1].trailers.all[0].key, "Refs");
+
+        let counts = cli.ahead_behind(path, "feature", "main").unwrap();
+        assert_eq!(counts, lib.ahead_behind(path, "feature", "main").unwrap());
+        assert_eq!(counts.0, feature.len());
+    }
+
+    #[test]
+    fn test_backends_agree_on_diff_and_tree() {
+        let fx = sample_repo("backend-diff");
+        let [cli, lib] = both();
+        let path = fx.path();
+
+        let since = DiffRange::Since("main", "feature");
+        assert_eq!(
+            cli.diff_names(path, since).unwrap(),
+            vec!["app/about/page.tsx".to_string(), "lib/util.ts".to_string()]
+        );
+        assert_eq!(cli.diff_names(path, since).unwrap(), lib.diff_names(path, since).unwrap());
+
+        let between = DiffRange::Between("main^1", "main");
+        assert_eq!(cli.diff_names(path, between).unwrap(), lib.diff_names(path, between).unwrap());
+        assert_eq!(cli.diff(path, since).unwrap(), lib.diff(path, since).unwrap());
+
+        assert_eq!(
+            cli.ls_tree(path, "feature", &["app"]).unwrap(),
+            lib.ls_tree(path, "feature", &["app"]).unwrap()
+        );
+        assert_eq!(cli.ls_tree(path, "main", &[]).unwrap(), lib.ls_tree(path, "main", &[]).unwrap());
+    }
+
+    #[test]
+    fn test_backends_archive_same_files() {
+        let fx = sample_repo("backend-archive");
+        let [cli, lib] = both();
+        let path = fx.path();
+
+        let list = |tar: Vec<u8>| {
+            let file = fx.path().join("archive.tar");
+            std::fs::write(&file, tar).unwrap();
+            let out = std::process::Command::new("tar")
+                .args(["-tf", file.to_str().unwrap()])
+                .output()
+                .unwrap();
+            std::fs::remove_file(&file).unwrap();
+            let mut files: Vec<String> = String::from_utf8(out.stdout)
+                .unwrap()
+                .lines()
+                .filter(|l| !l.ends_with('/'))
+                .map(str::to_string)
+                .collect();
+            files.sort();
+            files
+        };
+
+        let expected = list(cli.archive(path, "feature").unwrap());
+        assert_eq!(expected, vec!["README.md", "app/about/page.tsx", "app/page.tsx", "lib/util.ts"]);
+        assert_eq!(expected, list(lib.archive(path, "feature").unwrap()));
+    }
+
+    #[test]
+    fn test_backends_agree_on_merge_tree() {
+        let fx = Fixture::new("backend-merge-tree");
+        fx.commit("edit.txt", "a\nb\n", "Initial commit");
+        fx.commit("gone.txt", "keep\n", "Add file");
+
+        fx.git(&["checkout", "-q", "-b", "topic"]);
+        fx.commit("edit.txt", "a\ntopic\n", "Edit on topic");
+        fx.git(&["rm", "-q", "gone.txt"]);
+        fx.git(&["commit", "-q", "-m", "Remove file"]);
+        fx.commit("new.txt", "topic\n", "Add new file");
+
+        fx.git(&["checkout", "-q", "main"]);
+        fx.commit("edit.txt", "a\nmain\n", "Edit on main");
+        fx.commit("gone.txt", "changed\n", "Change file");
+        fx.commit("new.txt", "main\n", "Add new file");
+
+        let [cli, lib] = both();
+        let path = fx.path();
+        let expected = vec![
+            MergeConflict { path: "edit.txt".into(), kind: ConflictKind::Content },
+            MergeConflict { path: "gone.txt".into(), kind: ConflictKind::ModifyDelete },
+            MergeConflict { path: "new.txt".into(), kind: ConflictKind::AddAdd },
+        ];
+        assert_eq!(cli.merge_tree(path, "main", "topic").unwrap(), expected);
+        assert_eq!(lib.merge_tree(path, "main", "topic").unwrap(), expected);
+        assert!(cli.merge_tree(path, "main", "main~1").unwrap().is_empty());
+        assert!(lib.merge_tree(path, "main", "main~1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_backend_kind() {
+        assert_eq!(BackendKind::parse("CLI"), Some(BackendKind::Cli));
+        assert_eq!(BackendKind::parse("libgit2"), Some(BackendKind::Libgit2));
+        assert_eq!(BackendKind::parse("svn"), None);
+    }
+}
@@ -1,5 +1,11 @@
-use super::cli::{self, GitError};
+use super::backend::{backend, canonical, BranchSummary};
+use super::cli::GitError;
+use super::discovery::work_tree;
+use super::merged::{merged_tips, MergedBy};
+use super::staleness::{BranchStatus, StalenessRules};
+use super::worktrees::{list_worktrees, BranchWorktree};
 use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 #[derive(Debug, Clone, Serialize)]
@@ -10,29 +16,45 @@ pub struct Branch {
     pub commits_behind: i32,
     pub last_commit_date: String,
     pub last_commit_author: String,
-    pub status: String,
+    pub status: BranchStatus,
     pub head_sha: String,
     pub diverged_from_sha: Option<String>,
     pub diverged_from_date: Option<String>,
+    /// True for remote-tracking branches with no local counterpart
+    pub is_remote: bool,
+    /// Remote this branch lives on (remote-only) or tracks (local)
+    pub remote: Option<String>,
+    /// Short name of the upstream branch, e.g. `origin/feature`
+    pub upstream: Option<String>,
+    /// Local commits not yet pushed to the upstream
+    pub upstream_ahead: Option<i32>,
+    /// Upstream commits not yet pulled
+    pub upstream_behind: Option<i32>,
+    /// How the branch's changes already reached the default branch, if they
+    /// did; such branches are safe to delete
+    pub merged_by: Option<MergedBy>,
+    /// Working tree the branch is checked out in, if any (local branches only)
+    pub worktree: Option<BranchWorktree>,
 }
 
 /// Get the default branch name (usually main or master)
 pub fn get_default_branch(repo: &Path) -> Result<String, GitError> {
+    let git = backend();
+
     // Try to get from origin HEAD
-    if let Ok(output) = cli::run(repo, &["symbolic-ref", "refs/remotes/origin/HEAD"]) {
-        let trimmed = output.trim();
-        if let Some(branch) = trimmed.strip_prefix("refs/remotes/origin/") {
+    if let Ok(Some(target)) = git.symbolic_ref(repo, "refs/remotes/origin/HEAD") {
+        if let Some(branch) = target.strip_prefix("refs/remotes/origin/") {
             return Ok(branch.to_string());
         }
     }
 
     // Fallback: check if main exists
-    if cli::run(repo, &["rev-parse", "--verify", "main"]).is_ok() {
+    if let Ok(Some(_)) = git.resolve(repo, "main") {
         return Ok("main".to_string());
     }
 
     // Fallback: check if master exists
-    if cli::run(repo, &["rev-parse", "--verify", "master"]).is_ok() {
+    if let Ok(Some(_)) = git.resolve(repo, "master") {
         return Ok("master".to_string());
     }
 
@@ -40,35 +62,52 @@ pub fn get_default_branch(repo: &Path) -> Result<String, GitError> {
     Ok("HEAD".to_string())
 }
 
-/// Get repository info (name and path)
+/// Get repository info (name and path). A bare repository is named after
+/// its directory without the `.git` suffix (`app.git` is `app`).
 pub fn get_repo_info(repo: &Path) -> Result<(String, String), GitError> {
-    let output = cli::run(repo, &["rev-parse", "--show-toplevel"])?;
-    let full_path = output.trim().to_string();
+    let full_path = match work_tree(repo)? {
+        Some(path) => path,
+        None => canonical(repo),
+    };
 
-    let name = Path::new(&full_path)
+    let name = full_path
         .file_name()
         .map(|s| s.to_string_lossy().to_string())
+        .map(|s| s.strip_suffix(".git").filter(|s| !s.is_empty()).map(str::to_string).unwrap_or(s))
         .unwrap_or_else(|| "unknown".to_string());
 
-    Ok((name, full_path))
+    Ok((name, full_path.to_string_lossy().trim_end_matches('/').to_string()))
 }
 
-/// List all branches with their metadata
+/// List local and remote-tracking branches with their metadata.
+/// A remote-tracking branch is folded into the local branch that tracks it
+/// (or has the same name and tip), so each line of work appears once.
+/// Invalid staleness settings fall back to the defaults rather than hiding
+/// every branch; `StalenessRules::load` reports them.
 pub fn list_branches(repo: &Path, default_branch: &str) -> Result<Vec<Branch>, GitError> {
-    // Get all local branches
-    let output = cli::run(repo, &["branch", "--format=%(refname:short)"])?;
-
-    let branch_names: Vec<&str> = output
-        .lines()
-        .filter(|s| !s.is_empty() && *s != default_branch)
+    let git = backend();
+    let rules = StalenessRules::load(repo).unwrap_or_default();
+    let base_sha = git.resolve(repo, default_branch)?;
+    let worktrees: HashMap<String, BranchWorktree> = list_worktrees(repo)?
+        .iter()
+        .filter_map(|w| Some((w.branch.clone()?, BranchWorktree::from(w))))
         .collect();
+    let listed = listed_refs(repo, default_branch)?;
+    let merged = match &base_sha {
+        Some(base_sha) => merged_tips(repo, base_sha, listed.iter().map(|l| &l.summary))?,
+        None => HashMap::new(),
+    };
 
     let mut branches = Vec::new();
 
-    for name in branch_names {
-        if let Ok(branch) = get_branch_info(repo, name, default_branch) {
-            branches.push(branch);
-        }
+    for listed in listed {
+        let merged_by = merged.get(&listed.summary.head_sha).cloned();
+        let worktree = if listed.is_remote { None } else { worktrees.get(&listed.name).cloned() };
+        let upstream = listed.summary.upstream.as_deref().map(short_ref);
+        let ListedRef { name, summary, is_remote, remote } = listed;
+        let mut branch = to_branch(name, summary, &rules, is_remote, remote, upstream, merged_by);
+        branch.worktree = worktree;
+        branches.push(branch);
     }
 
     // Sort by last commit date (most recent first)
@@ -77,86 +116,200 @@ pub fn list_branches(repo: &Path, default_branch: &str) -> Result<Vec<Branch>, G
     Ok(branches)
 }
 
-fn get_branch_info(repo: &Path, name: &str, default_branch: &str) -> Result<Branch, GitError> {
-    // Get ahead/behind counts
-    let (commits_ahead, commits_behind) = get_ahead_behind(repo, name, default_branch)?;
-
-    // Get last commit info: SHA, author, date
-    let log_output = cli::run(
-        repo,
-        &["log", "-1", "--format=%H|%an|%aI", name],
-    )?;
-    let parts: Vec<&str> = log_output.trim().split('|').collect();
-
-    let head_sha = parts.first().unwrap_or(&"").to_string();
-    let last_commit_author = parts.get(1).unwrap_or(&"Unknown").to_string();
-    let last_commit_date = parts.get(2).unwrap_or(&"").to_string();
-
-    // Get merge base (fork point)
-    let (diverged_from_sha, diverged_from_date) = get_fork_point(repo, name, default_branch)?;
-
-    // Calculate status
-    let status = calculate_status(commits_behind, &last_commit_date);
-
-    Ok(Branch {
-        name: name.to_string(),
-        commits_ahead,
-        commits_behind,
-        last_commit_date,
-        last_commit_author,
-        status,
-        head_sha,
-        diverged_from_sha,
-        diverged_from_date,
-    })
+/// A ref that shows up as a branch: a local branch other than the default,
+/// or a remote-tracking branch no local branch already represents.
+pub(super) struct ListedRef {
+    pub name: String,
+    pub summary: BranchSummary,
+    pub is_remote: bool,
+    /// Remote the branch lives on (remote-only) or tracks (local)
+    pub remote: Option<String>,
 }
 
-fn get_ahead_behind(repo: &Path, branch: &str, base: &str) -> Result<(i32, i32), GitError> {
-    let output = cli::run(
-        repo,
-        &["rev-list", "--left-right", "--count", &format!("{}...{}", base, branch)],
-    )?;
-
-    let parts: Vec<&str> = output.trim().split_whitespace().collect();
-    let behind = parts.first().and_then(|s| s.parse().ok()).unwrap_or(0);
-    let ahead = parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+/// The refs `list_branches` reports, with their summaries, and nothing
+/// more expensive than the one batched `branch_summaries` call.
+pub(super) fn listed_refs(repo: &Path, default_branch: &str) -> Result<Vec<ListedRef>, GitError> {
+    let git = backend();
+    let remotes = git.list_remotes(repo)?;
+
+    // Tip, ahead/behind, fork point and upstream for every branch in one batch
+    let summaries = git.branch_summaries(repo, &["refs/heads/", "refs/remotes/"], default_branch)?;
+    let (local, remote): (Vec<_>, Vec<_>) = summaries
+        .into_iter()
+        .partition(|s| s.refname.starts_with("refs/heads/"));
+
+    // Remote refs already represented by a local branch
+    let tracked: HashSet<String> = local.iter().filter_map(|s| s.upstream.clone()).collect();
+    let local_tips: HashSet<(String, String)> = local
+        .iter()
+        .filter_map(|s| Some((s.refname.strip_prefix("refs/heads/")?.to_string(), s.head_sha.clone())))
+        .collect();
 
-    Ok((ahead, behind))
-}
+    let mut listed = Vec::new();
 
-fn get_fork_point(repo: &Path, branch: &str, base: &str) -> Result<(Option<String>, Option<String>), GitError> {
-    let merge_base = cli::run(repo, &["merge-base", base, branch])?;
-    let sha = merge_base.trim();
+    for summary in local {
+        let Some(name) = summary.refname.strip_prefix("refs/heads/").map(str::to_string) else {
+            continue;
+        };
+        if name.is_empty() || name == default_branch {
+            continue;
+        }
+        let remote = summary
+            .upstream
+            .as_deref()
+            .and_then(|u| split_remote_ref(u, &remotes))
+            .map(|(remote, _)| remote.to_string());
+        listed.push(ListedRef { name, summary, is_remote: false, remote });
+    }
 
-    if sha.is_empty() {
-        return Ok((None, None));
+    for summary in remote {
+        let Some((remote_name, branch)) = split_remote_ref(&summary.refname, &remotes) else {
+            continue;
+        };
+        if branch == "HEAD" || branch == default_branch || tracked.contains(&summary.refname) {
+            continue;
+        }
+        if local_tips.contains(&(branch.to_string(), summary.head_sha.clone())) {
+            continue;
+        }
+        let name = format!("{}/{}", remote_name, branch);
+        let remote = Some(remote_name.to_string());
+        listed.push(ListedRef { name, summary, is_remote: true, remote });
     }
 
-    let date_output = cli::run(repo, &["log", "-1", "--format=%aI", sha])?;
-    let date = date_output.trim().to_string();
+    Ok(listed)
+}
 
-    Ok((Some(sha.to_string()), Some(date)))
+/// Branches that live on or track `remote`.
+pub fn list_remote_branches(repo: &Path, default_branch: &str, remote: &str) -> Result<Vec<Branch>, GitError> {
+    let mut branches = list_branches(repo, default_branch)?;
+    branches.retain(|b| b.remote.as_deref() == Some(remote));
+    Ok(branches)
 }
 
-fn calculate_status(commits_behind: i32, last_commit_date: &str) -> String {
-    // Parse the date and check if it's stale (more than 7 days old)
-    if commits_behind > 50 {
-        return "stale".to_string();
-    }
+/// Names of the repository's remotes.
+pub fn list_remotes(repo: &Path) -> Result<Vec<String>, GitError> {
+    backend().list_remotes(repo)
+}
 
-    if commits_behind > 10 {
-        return "conflict-risk".to_string();
+fn to_branch(
+    name: String,
+    summary: BranchSummary,
+    rules: &StalenessRules,
+    is_remote: bool,
+    remote: Option<String>,
+    upstream: Option<String>,
+    merged_by: Option<MergedBy>,
+) -> Branch {
+    let status = rules.classify(&name, summary.behind, &summary.date);
+    Branch {
+        name,
+        commits_ahead: summary.ahead,
+        commits_behind: summary.behind,
+        last_commit_date: summary.date,
+        last_commit_author: summary.author,
+        status,
+        head_sha: summary.head_sha,
+        diverged_from_sha: summary.fork_sha,
+        diverged_from_date: summary.fork_date,
+        is_remote,
+        remote,
+        upstream,
+        upstream_ahead: summary.upstream_counts.map(|(ahead, _)| ahead),
+        upstream_behind: summary.upstream_counts.map(|(_, behind)| behind),
+        merged_by,
+        worktree: None,
     }
+}
 
-    // Check date freshness
-    if let Ok(commit_date) = chrono::DateTime::parse_from_rfc3339(last_commit_date) {
-        let now = chrono::Utc::now();
-        let days_old = (now - commit_date.with_timezone(&chrono::Utc)).num_days();
+/// Split `refs/remotes/<remote>/<branch>` into its remote and branch parts.
+/// Remote names may contain slashes, so match against the known remotes.
+fn split_remote_ref<'a>(refname: &'a str, remotes: &[String]) -> Option<(&'a str, &'a str)> {
+    let rest = refname.strip_prefix("refs/remotes/")?;
+    remotes
+        .iter()
+        .filter(|r| rest.len() > r.len() && rest.starts_with(r.as_str()) && rest.as_bytes()[r.len()] == b'/')
+        .max_by_key(|r| r.len())
+        .map(|r| (&rest[..r.len()], &rest[r.len() + 1..]))
+}
 
-        if days_old > 14 {
-            return "stale".to_string();
-        }
+/// `refs/remotes/origin/x` → `origin/x`, `refs/heads/x` → `x`
+fn short_ref(refname: &str) -> String {
+    refname
+        .strip_prefix("refs/remotes/")
+        .or_else(|| refname.strip_prefix("refs/heads/"))
+        .unwrap_or(refname)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::backend::{backend_for, BackendKind};
+    use crate::git::fixture::Fixture;
+
+    #[test]
+    fn test_split_remote_ref() {
+        let remotes = vec!["origin".to_string(), "team/upstream".to_string()];
+        assert_eq!(split_remote_ref("refs/remotes/origin/feat/x", &remotes), Some(("origin", "feat/x")));
+        assert_eq!(split_remote_ref("refs/remotes/team/upstream/y", &remotes), Some(("team/upstream", "y")));
+        assert_eq!(split_remote_ref("refs/remotes/other/y", &remotes), None);
+        assert_eq!(split_remote_ref("refs/heads/y", &remotes), None);
     }
 
-    "fresh".to_string()
+    #[test]
+    fn test_remote_branches_merged_with_local() {
+        let origin = Fixture::new_bare("branches-origin");
+        let fx = Fixture::new("branches-remote");
+        fx.git(&["remote", "add", "origin", origin.path().to_str().unwrap()]);
+        fx.commit("README.md", "hello\n", "Initial commit");
+        fx.git(&["push", "-q", "-u", "origin", "main"]);
+
+        // Tracked branch with one unpushed commit
+        fx.git(&["checkout", "-q", "-b", "feature"]);
+        fx.commit("a.txt", "a\n", "Add a");
+        fx.git(&["push", "-q", "-u", "origin", "feature"]);
+        fx.commit("b.txt", "b\n", "Add b");
+
+        // Pushed without tracking, same tip: still a duplicate
+        fx.git(&["checkout", "-q", "-b", "topic", "main"]);
+        fx.commit("c.txt", "c\n", "Add c");
+        fx.git(&["push", "-q", "origin", "topic"]);
+
+        // A teammate's branch that only exists on the remote
+        fx.git(&["checkout", "-q", "-b", "teammate", "main"]);
+        fx.commit("d.txt", "d\n", "Add d");
+        fx.git(&["push", "-q", "origin", "teammate"]);
+        fx.git(&["checkout", "-q", "main"]);
+        fx.git(&["branch", "-q", "-D", "teammate"]);
+
+        let path = fx.path();
+        let prefixes = ["refs/heads/", "refs/remotes/"];
+        assert_eq!(
+            backend_for(BackendKind::Cli).branch_summaries(path, &prefixes, "main").unwrap(),
+            backend_for(BackendKind::Libgit2).branch_summaries(path, &prefixes, "main").unwrap()
+        );
+
+        let branches = list_branches(path, "main").unwrap();
+        let mut names: Vec<&str> = branches.iter().map(|b| b.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["feature", "origin/teammate", "topic"]);
+
+        let feature = branches.iter().find(|b| b.name == "feature").unwrap();
+        assert_eq!(feature.remote.as_deref(), Some("origin"));
+        assert_eq!(feature.upstream.as_deref(), Some("origin/feature"));
+        assert_eq!((feature.upstream_ahead, feature.upstream_behind), (Some(1), Some(0)));
+
+        let teammate = branches.iter().find(|b| b.name == "origin/teammate").unwrap();
+        assert!(teammate.is_remote);
+        assert_eq!(teammate.commits_ahead, 1);
+        assert_eq!(teammate.upstream, None);
+
+        let topic = branches.iter().find(|b| b.name == "topic").unwrap();
+        assert_eq!((topic.remote.as_deref(), topic.upstream_ahead), (None, None));
+
+        let on_origin = list_remote_branches(path, "main", "origin").unwrap();
+        assert_eq!(on_origin.len(), 2);
+        assert_eq!(list_remotes(path).unwrap(), vec!["origin".to_string()]);
+    }
 }
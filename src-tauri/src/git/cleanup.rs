@@ -0,0 +1,539 @@
+//! Branch cleanup: delete local and remote branches and prune remote-tracking
+//! refs whose branch is gone from the remote.
+//!
+//! Every operation can run as a dry run, refuses to touch the default branch
+//! or a protected one, and records what it deleted in an undo journal kept in
+//! the repository's git directory. Protected branches come from the
+//! `[cleanup]` table of the settings files (see `config`):
+//!
+//! ```toml
+//! [cleanup]
+//! protected = ["release/*", "staging", "production"]
+//! ```
+//!
+//! Changes go through the `git` CLI whatever the backend, so hooks,
+//! credentials and reflogs behave as they would in a terminal.
+
+use super::backend::backend;
+use super::cli::{self, GitError};
+use super::config::{self, glob_match};
+use super::worktrees::list_worktrees;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// Oldest journal entries are dropped past this many.
+const JOURNAL_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeletionKind {
+    /// `refs/heads/<branch>`
+    LocalBranch,
+    /// The branch on the remote itself (a push)
+    RemoteBranch,
+    /// `refs/remotes/<remote>/<branch>` only
+    TrackingRef,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deletion {
+    /// Journal entry to restore from; `None` on a dry run
+    pub id: Option<String>,
+    pub kind: DeletionKind,
+    /// Branch name, without the remote prefix
+    pub branch: String,
+    pub remote: Option<String>,
+    /// Where the ref pointed; restoring puts it back here
+    pub sha: String,
+    pub deleted_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Refusal {
+    pub branch: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupReport {
+    pub dry_run: bool,
+    /// Refs deleted, or that would be on a dry run
+    pub deleted: Vec<Deletion>,
+    /// Branches left alone (protected, missing, or git refused)
+    pub refused: Vec<Refusal>,
+    /// Why the deletions couldn't be journaled, if they couldn't; they
+    /// happened all the same
+    pub journal_error: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    cleanup: CleanupLayer,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct CleanupLayer {
+    protected: Option<Vec<String>>,
+}
+
+/// Branch names cleanup must never delete.
+struct Protection {
+    default_branch: String,
+    patterns: Vec<String>,
+}
+
+impl Protection {
+    fn load(repo: &Path, default_branch: &str) -> Result<Self, GitError> {
+        let mut patterns = Vec::new();
+        for file in config::layers::<ConfigFile>(repo)? {
+            if let Some(protected) = file.cleanup.protected {
+                patterns = protected;
+            }
+        }
+        Ok(Protection {
+            default_branch: default_branch.to_string(),
+            patterns,
+        })
+    }
+
+    /// Why `branch` is protected, if it is. `remote_head` is the branch the
+    /// remote's `HEAD` points at, which is its default branch.
+    fn check(&self, branch: &str, remote_head: Option<&str>) -> Option<String> {
+        if branch == self.default_branch || Some(branch) == remote_head {
+            return Some("default branch".to_string());
+        }
+        self.patterns
+            .iter()
+            .find(|pattern| glob_match(pattern, branch))
+            .map(|pattern| format!("protected by `{}`", pattern))
+    }
+}
+
+/// A cleanup in progress. Outside a dry run it holds the journal lock and
+/// the journal itself from the start, so a journal that can't be read stops
+/// the cleanup before anything is deleted.
+struct Cleanup {
+    report: CleanupReport,
+    journal: Option<(MutexGuard<'static, ()>, Vec<Deletion>)>,
+    now: chrono::DateTime<chrono::Utc>,
+}
+
+impl Cleanup {
+    fn new(repo: &Path, dry_run: bool) -> Result<Self, GitError> {
+        let journal = if dry_run {
+            None
+        } else {
+            let guard = JOURNAL_LOCK.lock().unwrap();
+            Some((guard, read_journal(repo)?))
+        };
+        Ok(Cleanup {
+            report: CleanupReport {
+                dry_run,
+                deleted: Vec::new(),
+                refused: Vec::new(),
+                journal_error: None,
+            },
+            journal,
+            now: chrono::Utc::now(),
+        })
+    }
+
+    fn refuse(&mut self, branch: &str, reason: impl Into<String>) {
+        self.report.refused.push(Refusal {
+            branch: branch.to_string(),
+            reason: reason.into(),
+        });
+    }
+
+    /// Unless this is a dry run, give `deletion` its journal id and run
+    /// `delete`; record the outcome.
+    fn delete(&mut self, mut deletion: Deletion, delete: impl FnOnce() -> Result<String, GitError>) {
+        if let Some((_, journal)) = &self.journal {
+            // The sequence number separates cleanups within a millisecond
+            // here, the SHA those of another app instance
+            let seq = NEXT_SEQ.fetch_add(1, Ordering::Relaxed);
+            let sha = &deletion.sha[..12.min(deletion.sha.len())];
+            let id = format!("{}-{}-{}", self.now.timestamp_millis(), seq, sha);
+            if journal.iter().any(|d| d.id.as_deref() == Some(id.as_str())) {
+                self.refuse(&deletion.branch, format!("undo journal already has an entry {}", id));
+                return;
+            }
+            deletion.id = Some(id);
+            deletion.deleted_at = Some(self.now.to_rfc3339());
+            if let Err(e) = delete() {
+                self.refuse(&deletion.branch, failure(e));
+                return;
+            }
+        }
+        self.report.deleted.push(deletion);
+    }
+
+    /// Journal what was deleted and hand back the report.
+    fn finish(mut self, repo: &Path) -> CleanupReport {
+        if let Some((_guard, mut journal)) = self.journal.take() {
+            if !self.report.deleted.is_empty() {
+                journal.extend(self.report.deleted.iter().cloned());
+                if let Err(e) = write_journal(repo, journal) {
+                    self.report.journal_error = Some(e.to_string());
+                }
+            }
+        }
+        self.report
+    }
+}
+
+fn deletion(kind: DeletionKind, branch: &str, remote: Option<&str>, sha: String) -> Deletion {
+    Deletion {
+        id: None,
+        kind,
+        branch: branch.to_string(),
+        remote: remote.map(str::to_string),
+        sha,
+        deleted_at: None,
+    }
+}
+
+/// git's own message for a failed command, without our prefix.
+fn failure(e: GitError) -> String {
+    match e {
+        GitError::CommandFailed(stderr) => stderr.trim().to_string(),
+        e => e.to_string(),
+    }
+}
+
+/// Branch a remote's `HEAD` points at, e.g. `main` for `origin/HEAD`.
+fn remote_head(repo: &Path, remote: &str) -> Result<Option<String>, GitError> {
+    let prefix = format!("refs/remotes/{}/", remote);
+    Ok(backend()
+        .symbolic_ref(repo, &format!("{}HEAD", prefix))?
+        .and_then(|target| target.strip_prefix(&prefix).map(str::to_string)))
+}
+
+/// Delete local branches (`git branch -D`). Branches checked out here or in
+/// another worktree are refused, as git would refuse them.
+pub fn delete_local_branches(
+    repo: &Path,
+    default_branch: &str,
+    branches: &[String],
+    dry_run: bool,
+) -> Result<CleanupReport, GitError> {
+    let git = backend();
+    let protection = Protection::load(repo, default_branch)?;
+    let current = git.symbolic_ref(repo, "HEAD")?;
+    let worktrees = list_worktrees(repo)?;
+    let mut cleanup = Cleanup::new(repo, dry_run)?;
+
+    for branch in branches {
+        let refname = format!("refs/heads/{}", branch);
+        if let Some(reason) = protection.check(branch, None) {
+            cleanup.refuse(branch, reason);
+        } else if current.as_deref() == Some(refname.as_str()) {
+            cleanup.refuse(branch, "checked out");
+        } else if let Some(worktree) = worktrees.iter().find(|w| w.branch.as_deref() == Some(branch.as_str())) {
+            cleanup.refuse(branch, format!("checked out in worktree {}", worktree.path));
+        } else if let Some(sha) = git.resolve(repo, &refname)? {
+            let deletion = deletion(DeletionKind::LocalBranch, branch, None, sha);
+            cleanup.delete(deletion, || cli::run(repo, &["branch", "-D", branch]));
+        } else {
+            cleanup.refuse(branch, "no such branch");
+        }
+    }
+
+    Ok(cleanup.finish(repo))
+}
+
+/// Delete branches on `remote` (`git push --delete`). Each branch must have
+/// been fetched; the push is leased on the fetched SHA, so a branch someone
+/// pushed to since is refused rather than lost.
+pub fn delete_remote_branches(
+    repo: &Path,
+    default_branch: &str,
+    remote: &str,
+    branches: &[String],
+    dry_run: bool,
+) -> Result<CleanupReport, GitError> {
+    let git = backend();
+    let protection = Protection::load(repo, default_branch)?;
+    let remote_head = remote_head(repo, remote)?;
+    let mut cleanup = Cleanup::new(repo, dry_run)?;
+
+    for branch in branches {
+        if let Some(reason) = protection.check(branch, remote_head.as_deref()) {
+            cleanup.refuse(branch, reason);
+        } else if let Some(sha) = git.resolve(repo, &format!("refs/remotes/{}/{}", remote, branch))? {
+            let lease = format!("--force-with-lease=refs/heads/{}:{}", branch, sha);
+            let target = format!(":refs/heads/{}", branch);
+            let deletion = deletion(DeletionKind::RemoteBranch, branch, Some(remote), sha);
+            cleanup.delete(deletion, || cli::run(repo, &["push", "--quiet", &lease, remote, &target]));
+        } else {
+            cleanup.refuse(branch, format!("not fetched from {}", remote));
+        }
+    }
+
+    Ok(cleanup.finish(repo))
+}
+
+/// Remove remote-tracking refs for branches that no longer exist on
+/// `remote`. Like `git remote prune`, but protected branches are kept and
+/// each removed ref is journaled.
+pub fn prune_remote(repo: &Path, default_branch: &str, remote: &str, dry_run: bool) -> Result<CleanupReport, GitError> {
+    let git = backend();
+    let protection = Protection::load(repo, default_branch)?;
+    let remote_head = remote_head(repo, remote)?;
+    let mut cleanup = Cleanup::new(repo, dry_run)?;
+
+    let output = cli::run(repo, &["remote", "prune", "--dry-run", remote])?;
+    for branch in parse_prune(&output, remote) {
+        let refname = format!("refs/remotes/{}/{}", remote, branch);
+        if let Some(reason) = protection.check(branch, remote_head.as_deref()) {
+            cleanup.refuse(branch, reason);
+        } else if let Some(sha) = git.resolve(repo, &refname)? {
+            let deletion = deletion(DeletionKind::TrackingRef, branch, Some(remote), sha.clone());
+            cleanup.delete(deletion, || cli::run(repo, &["update-ref", "-d", &refname, &sha]));
+        }
+    }
+
+    Ok(cleanup.finish(repo))
+}
+
+/// Branch names from `git remote prune --dry-run` lines such as
+/// ` * [would prune] origin/feature`.
+fn parse_prune<'a>(output: &'a str, remote: &str) -> Vec<&'a str> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once("[would prune] "))
+        .filter_map(|(_, name)| name.trim().strip_prefix(remote)?.strip_prefix('/'))
+        .collect()
+}
+
+/// Serializes journal read-modify-write cycles within the app.
+static JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+
+/// Next sequence number for journal entry ids.
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// `<git common dir>/git-visualizer/undo-journal.json`, shared by all
+/// worktrees of the repository.
+fn journal_path(repo: &Path) -> Result<PathBuf, GitError> {
+    let common_dir = backend().git_dirs(repo)?.common_dir;
+    Ok(common_dir.join("git-visualizer").join("undo-journal.json"))
+}
+
+fn read_journal(repo: &Path) -> Result<Vec<Deletion>, GitError> {
+    let path = journal_path(repo)?;
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(GitError::Config(format!("{}: {}", path.display(), e))),
+    };
+    serde_json::from_str(&text).map_err(|e| GitError::Config(format!("{}: {}", path.display(), e)))
+}
+
+fn write_journal(repo: &Path, mut journal: Vec<Deletion>) -> Result<(), GitError> {
+    let path = journal_path(repo)?;
+    let excess = journal.len().saturating_sub(JOURNAL_LIMIT);
+    journal.drain(..excess);
+
+    let io_error = |e: std::io::Error| GitError::Config(format!("{}: {}", path.display(), e));
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(io_error)?;
+    }
+    let json = serde_json::to_string_pretty(&journal).map_err(|e| GitError::Config(e.to_string()))?;
+    // Write aside and rename over, so a crash mid-write can't lose the history
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, json).map_err(io_error)?;
+    std::fs::rename(&temp, &path).map_err(io_error)
+}
+
+/// Deletions that can still be undone, newest first.
+pub fn undo_journal(repo: &Path) -> Result<Vec<Deletion>, GitError> {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    let mut journal = read_journal(repo)?;
+    journal.reverse();
+    Ok(journal)
+}
+
+/// Recreate the ref a journal entry deleted, at the SHA it pointed to, and
+/// drop the entry. Fails if a ref of that name exists again. Remote branches
+/// are pushed back, which needs the commit to still be in the local object
+/// store (it stays there until `git gc` prunes it).
+pub fn undo_deletion(repo: &Path, id: &str) -> Result<Deletion, GitError> {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    let mut journal = read_journal(repo)?;
+    let index = journal
+        .iter()
+        .position(|d| d.id.as_deref() == Some(id))
+        .ok_or_else(|| GitError::CommandFailed(format!("no undo journal entry {}", id)))?;
+    let entry = &journal[index];
+    let remote = entry.remote.as_deref().unwrap_or_default();
+
+    match entry.kind {
+        DeletionKind::LocalBranch => {
+            cli::run(repo, &["branch", "--", &entry.branch, &entry.sha])?;
+        }
+        DeletionKind::RemoteBranch => {
+            // An empty lease makes the push fail if the branch exists again
+            let lease = format!("--force-with-lease=refs/heads/{}:", entry.branch);
+            let refspec = format!("{}:refs/heads/{}", entry.sha, entry.branch);
+            cli::run(repo, &["push", "--quiet", &lease, remote, &refspec])?;
+        }
+        DeletionKind::TrackingRef => {
+            // An empty old value makes update-ref fail if the ref exists
+            let refname = format!("refs/remotes/{}/{}", remote, entry.branch);
+            cli::run(repo, &["update-ref", &refname, &entry.sha, ""])?;
+        }
+    }
+
+    let restored = journal.remove(index);
+    write_journal(repo, journal)?;
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fixture::Fixture;
+
+    fn names(deleted: &[Deletion]) -> Vec<&str> {
+        deleted.iter().map(|d| d.branch.as_str()).collect()
+    }
+
+    #[test]
+    fn test_delete_local_branches_and_undo() {
+        let fx = Fixture::new("cleanup-local");
+        fx.commit("README.md", "hello\n", "Initial commit");
+        fx.write(".git-visualizer.toml", "[cleanup]\nprotected = [\"release/*\"]\n");
+        fx.git(&["branch", "old"]);
+        fx.git(&["branch", "release/1.0"]);
+        fx.git(&["checkout", "-q", "-b", "wip"]);
+        let wip = fx.commit("wip.txt", "w\n", "Work in progress");
+        fx.git(&["checkout", "-q", "main"]);
+        let linked = fx.path().with_extension("linked");
+        let _ = std::fs::remove_dir_all(&linked);
+        fx.git(&["worktree", "add", "-q", "-b", "elsewhere", linked.to_str().unwrap()]);
+        let in_worktree = format!("checked out in worktree {}", linked.canonicalize().unwrap().display());
+        let path = fx.path();
+
+        let branches: Vec<String> =
+            ["old", "wip", "main", "release/1.0", "missing", "elsewhere"].iter().map(|s| s.to_string()).collect();
+        let preview = delete_local_branches(path, "main", &branches, true).unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(names(&preview.deleted), vec!["old", "wip"]);
+        assert!(preview.deleted.iter().all(|d| d.id.is_none()));
+        let refused: Vec<(&str, &str)> =
+            preview.refused.iter().map(|r| (r.branch.as_str(), r.reason.as_str())).collect();
+        assert_eq!(
+            refused,
+            vec![
+                ("main", "default branch"),
+                ("release/1.0", "protected by `release/*`"),
+                ("missing", "no such branch"),
+                ("elsewhere", in_worktree.as_str()),
+            ]
+        );
+        assert!(fx.git(&["branch", "--list", "old"]).contains("old"));
+        assert!(undo_journal(path).unwrap().is_empty());
+
+        let report = delete_local_branches(path, "main", &branches, false).unwrap();
+        assert_eq!(names(&report.deleted), vec!["old", "wip"]);
+        assert_eq!(fx.git(&["branch", "--list", "old", "wip"]), "");
+
+        let journal = undo_journal(path).unwrap();
+        assert_eq!(names(&journal), vec!["wip", "old"]);
+        assert_eq!(journal[0].sha, wip);
+
+        let restored = undo_deletion(path, journal[0].id.as_deref().unwrap()).unwrap();
+        assert_eq!(restored.kind, DeletionKind::LocalBranch);
+        assert_eq!(fx.git(&["rev-parse", "wip"]), wip);
+        assert_eq!(names(&undo_journal(path).unwrap()), vec!["old"]);
+        assert!(undo_deletion(path, "nope").is_err());
+
+        // Deleting the restored branch again, at the same SHA, journals it
+        // under a new id
+        let again = delete_local_branches(path, "main", &["wip".to_string()], false).unwrap();
+        assert_ne!(again.deleted[0].id, restored.id);
+        let journal = undo_journal(path).unwrap();
+        assert_eq!(names(&journal), vec!["wip", "old"]);
+        assert_eq!(journal[0].id, again.deleted[0].id);
+        undo_deletion(path, journal[0].id.as_deref().unwrap()).unwrap();
+        fx.git(&["worktree", "remove", "--force", linked.to_str().unwrap()]);
+
+        let current = delete_local_branches(path, "trunk", &["main".to_string()], true).unwrap();
+        assert_eq!(current.refused[0].reason, "checked out");
+    }
+
+    #[test]
+    fn test_delete_remote_branches_and_prune() {
+        let origin = Fixture::new_bare("cleanup-origin");
+        let fx = Fixture::new("cleanup-remote");
+        fx.git(&["remote", "add", "origin", origin.path().to_str().unwrap()]);
+        let root = fx.commit("README.md", "hello\n", "Initial commit");
+        fx.git(&["push", "-q", "origin", "main", "main:done", "main:gone", "main:staging"]);
+        fx.git(&["fetch", "-q", "origin"]);
+        let path = fx.path();
+
+        let branches = vec!["done".to_string(), "main".to_string(), "unknown".to_string()];
+        let report = delete_remote_branches(path, "main", "origin", &branches, false).unwrap();
+        assert_eq!(names(&report.deleted), vec!["done"]);
+        assert_eq!(report.refused.len(), 2);
+        assert_eq!(origin.git(&["branch", "--list", "done"]), "");
+        assert!(backend().resolve(path, "refs/remotes/origin/done").unwrap().is_none());
+
+        // Deleted elsewhere: only the tracking ref is left to prune
+        origin.git(&["branch", "-D", "gone", "staging"]);
+        fx.write(".git-visualizer.toml", "[cleanup]\nprotected = [\"staging\"]\n");
+        assert_eq!(parse_prune(&cli::run(path, &["remote", "prune", "--dry-run", "origin"]).unwrap(), "origin"), vec!["gone", "staging"]);
+
+        let preview = prune_remote(path, "main", "origin", true).unwrap();
+        assert_eq!(names(&preview.deleted), vec!["gone"]);
+        assert_eq!(preview.refused[0].branch, "staging");
+        let report = prune_remote(path, "main", "origin", false).unwrap();
+        assert_eq!(names(&report.deleted), vec!["gone"]);
+        assert!(backend().resolve(path, "refs/remotes/origin/gone").unwrap().is_none());
+        assert!(backend().resolve(path, "refs/remotes/origin/staging").unwrap().is_some());
+
+        for entry in undo_journal(path).unwrap() {
+            undo_deletion(path, entry.id.as_deref().unwrap()).unwrap();
+        }
+        assert_eq!(origin.git(&["rev-parse", "done"]), root);
+        assert_eq!(fx.git(&["rev-parse", "origin/done"]), root);
+        assert_eq!(fx.git(&["rev-parse", "origin/gone"]), root);
+        assert!(undo_journal(path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_undo_never_moves_a_recreated_branch() {
+        let origin = Fixture::new_bare("cleanup-recreated-origin");
+        let fx = Fixture::new("cleanup-recreated");
+        fx.git(&["remote", "add", "origin", origin.path().to_str().unwrap()]);
+        let root = fx.commit("README.md", "hello\n", "Initial commit");
+        let tip = fx.commit("feature.txt", "f\n", "Add feature");
+        fx.git(&["push", "-q", "origin", "main", "main:feature"]);
+        fx.git(&["fetch", "-q", "origin"]);
+        let path = fx.path();
+
+        let report = delete_remote_branches(path, "main", "origin", &["feature".to_string()], false).unwrap();
+        assert_eq!((names(&report.deleted), report.journal_error), (vec!["feature"], None));
+
+        // Someone pushes a new `feature` that the deleted tip fast-forwards
+        origin.git(&["branch", "feature", &root]);
+        let id = report.deleted[0].id.as_deref().unwrap();
+        assert!(undo_deletion(path, id).is_err());
+        assert_eq!(origin.git(&["rev-parse", "feature"]), root);
+        assert_eq!(undo_journal(path).unwrap()[0].sha, tip);
+
+        // A journal that can't be read stops a cleanup before it deletes
+        let journal = journal_path(path).unwrap();
+        std::fs::write(&journal, "not json").unwrap();
+        fx.git(&["branch", "spare"]);
+        assert!(delete_local_branches(path, "main", &["spare".to_string()], false).is_err());
+        assert_eq!(fx.git(&["rev-parse", "spare"]), tip);
+    }
+}
@@ -1,7 +1,18 @@
-use std::path::Path;
-use std::process::Command;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::process::{Command, Stdio};
 use thiserror::Error;
 
+use super::backend::{
+    canonical, count_lines, strip_tag_signature, BranchSummary, ChangedFile, Commit, ConflictKind, DiffRange, FileChange,
+    GitBackend, GitDirs, LogQuery, MergeConflict, MergeFilter, RefEntry, RepoKind, Signature, SignatureStatus, StashEntry,
+    SubmoduleEntry, TagEntry, WorkingChanges, WorktreeEntry,
+};
+use super::conventional::parse_header;
+use super::libgit2::Libgit2Backend;
+use super::trailers::parse_trailers;
+
 #[derive(Error, Debug)]
 pub enum GitError {
     #[error("git not found - is git installed?")]
@@ -18,18 +29,66 @@ pub enum GitError {
 
     #[error("path contains invalid UTF-8: {0}")]
     InvalidPath(String),
+
+    #[error("invalid config {0}")]
+    Config(String),
 }
 
 /// Run a git command in the specified repository and return stdout as a string
 pub fn run(repo: &Path, args: &[&str]) -> Result<String, GitError> {
+    let stdout = run_bytes(repo, args)?;
+    String::from_utf8(stdout).map_err(|_| GitError::InvalidUtf8)
+}
+
+/// Run a git command in the specified repository and return raw stdout bytes.
+/// Used for binary output such as `git archive`.
+pub fn run_bytes(repo: &Path, args: &[&str]) -> Result<Vec<u8>, GitError> {
+    let output = run_output(repo, args)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("not a git repository") {
+            return Err(GitError::NotARepo(repo.display().to_string()));
+        }
+        return Err(GitError::CommandFailed(stderr.into_owned()));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Run a git command and return its output whatever the exit status, for
+/// commands that use the status to report a result (e.g. `merge-tree`).
+fn run_output(repo: &Path, args: &[&str]) -> Result<std::process::Output, GitError> {
     let repo_str = repo
         .to_str()
         .ok_or_else(|| GitError::InvalidPath(repo.display().to_string()))?;
 
-    let output = Command::new("git")
+    Command::new("git")
         .args(["-C", repo_str])
         .args(args)
         .output()
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                GitError::GitNotFound
+            } else {
+                GitError::CommandFailed(e.to_string())
+            }
+        })
+}
+
+/// Run a git command with `input` on stdin and return stdout as a string.
+fn run_with_input(repo: &Path, args: &[&str], input: Vec<u8>) -> Result<String, GitError> {
+    let repo_str = repo
+        .to_str()
+        .ok_or_else(|| GitError::InvalidPath(repo.display().to_string()))?;
+
+    let mut child = Command::new("git")
+        .args(["-C", repo_str])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
         .map_err(|e| {
             if e.kind() == std::io::ErrorKind::NotFound {
                 GitError::GitNotFound
@@ -38,13 +97,871 @@ pub fn run(repo: &Path, args: &[&str]) -> Result<String, GitError> {
             }
         })?;
 
+    // Feed stdin from another thread so a full stdout pipe can't deadlock us
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| GitError::CommandFailed(e.to_string()))?;
+    let _ = writer.join();
+
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        if stderr.contains("not a git repository") {
-            return Err(GitError::NotARepo(repo.display().to_string()));
+        return Err(GitError::CommandFailed(String::from_utf8_lossy(&output.stderr).into_owned()));
+    }
+    String::from_utf8(output.stdout).map_err(|_| GitError::InvalidUtf8)
+}
+
+/// Like `run`, but maps a silent non-zero exit (e.g. `rev-parse --verify --quiet`
+/// on a missing ref, `merge-base` on unrelated histories) to `Ok(None)`.
+fn run_optional(repo: &Path, args: &[&str]) -> Result<Option<String>, GitError> {
+    match run(repo, args) {
+        Ok(output) => {
+            let trimmed = output.trim();
+            Ok(if trimmed.is_empty() { None } else { Some(trimmed.to_string()) })
         }
-        return Err(GitError::CommandFailed(stderr.into_owned()));
+        Err(GitError::CommandFailed(stderr)) if stderr.trim().is_empty() => Ok(None),
+        Err(e) => Err(e),
     }
+}
 
-    String::from_utf8(output.stdout).map_err(|_| GitError::InvalidUtf8)
+/// `for-each-ref` fields for a branch tip, NUL-separated.
+const TIP_FORMAT: &str =
+    "%(refname)%00%(objectname)%00%(authorname)%00%(authordate:iso-strict)%00%(upstream)%00%(upstream:track)";
+
+/// Parse `%(upstream:track)` (`[ahead 1, behind 2]`, `[gone]`, or empty when
+/// in sync) into `(ahead, behind)`. `None` when the upstream ref is gone.
+fn parse_track(track: &str) -> Option<(i32, i32)> {
+    let inner = track.trim().trim_start_matches('[').trim_end_matches(']');
+    if inner == "gone" {
+        return None;
+    }
+    let mut counts = (0, 0);
+    for part in inner.split(',') {
+        match part.trim().split_once(' ') {
+            Some(("ahead", n)) => counts.0 = n.parse().unwrap_or(0),
+            Some(("behind", n)) => counts.1 = n.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    Some(counts)
+}
+
+/// `git log` format for `Commit`, used with `-z`: every field is followed by a
+/// NUL, so records can be split without caring what the message contains
+/// (commit messages can't contain NUL).
+const LOG_FORMAT: &str = "--format=%H%x00%h%x00%P%x00%an%x00%ae%x00%aI%x00%cn%x00%ce%x00%cI%x00%s%x00%b%x00%T";
+const LOG_FIELDS: usize = 12;
+
+/// Signature fields appended to `LOG_FORMAT` when verifying signatures.
+const SIGNATURE_FORMAT: &str = "%x00%G?%x00%GS%x00%GK";
+const SIGNATURE_FIELDS: usize = 3;
+
+/// Parse `git log -z` output produced with `LOG_FORMAT`, plus
+/// `SIGNATURE_FORMAT` if `signed`.
+fn parse_log(output: &str, signed: bool) -> Vec<Commit> {
+    let fields: Vec<&str> = output.split('\0').collect();
+    let width = if signed { LOG_FIELDS + SIGNATURE_FIELDS } else { LOG_FIELDS };
+    fields
+        .chunks_exact(width)
+        .map(|f| Commit {
+            signature: signed.then(|| parse_signature(&f[LOG_FIELDS..])),
+            conventional: parse_header(f[9]),
+            trailers: parse_trailers(f[10]),
+            full_sha: f[0].to_string(),
+            short_sha: f[1].to_string(),
+            parents: f[2].split_whitespace().map(str::to_string).collect(),
+            author_name: f[3].to_string(),
+            author_email: f[4].to_string(),
+            author_date: f[5].to_string(),
+            committer_name: f[6].to_string(),
+            committer_email: f[7].to_string(),
+            committer_date: f[8].to_string(),
+            subject: f[9].to_string(),
+            body: f[10].trim_end().to_string(),
+            tree: f[11].to_string(),
+        })
+        .collect()
+}
+
+/// Parse `git worktree list --porcelain`: blank-line separated records of
+/// `worktree <path>`, `HEAD <sha>`, `branch <ref>` / `detached` / `bare`,
+/// and optional `locked` / `prunable` lines.
+fn parse_worktrees(output: &str) -> Vec<WorktreeEntry> {
+    let mut worktrees = Vec::new();
+    for record in output.split("\n\n") {
+        let mut entry = WorktreeEntry {
+            path: String::new(),
+            head_sha: None,
+            branch: None,
+            bare: false,
+        };
+        let mut prunable = false;
+        for line in record.lines() {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "worktree" => entry.path = value.to_string(),
+                "HEAD" => entry.head_sha = Some(value.to_string()).filter(|sha| sha.bytes().any(|b| b != b'0')),
+                "branch" => entry.branch = Some(value.to_string()),
+                "bare" => entry.bare = true,
+                "prunable" => prunable = true,
+                _ => {}
+            }
+        }
+        if !entry.path.is_empty() && !prunable {
+            worktrees.push(entry);
+        }
+    }
+    if worktrees.len() > 1 {
+        worktrees[1..].sort_by(|a, b| a.path.cmp(&b.path));
+    }
+    worktrees
+}
+
+/// Parse `git config -z --get-regexp ^submodule\.` output (`key\nvalue`
+/// records) read from `.gitmodules`. Names may contain dots, so the
+/// variable is whatever follows the last one. Entries without a path are
+/// dropped, as git does.
+fn parse_gitmodules(output: &str) -> Vec<SubmoduleEntry> {
+    let mut submodules: Vec<SubmoduleEntry> = Vec::new();
+    for record in output.split('\0') {
+        let (key, value) = record.split_once('\n').unwrap_or((record, ""));
+        let Some((name, var)) = key.strip_prefix("submodule.").and_then(|k| k.rsplit_once('.')) else {
+            continue;
+        };
+        let index = match submodules.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => {
+                submodules.push(SubmoduleEntry {
+                    name: name.to_string(),
+                    path: String::new(),
+                    url: None,
+                    branch: None,
+                    pinned_sha: None,
+                });
+                submodules.len() - 1
+            }
+        };
+        let entry = &mut submodules[index];
+        match var {
+            "path" => entry.path = value.to_string(),
+            "url" => entry.url = Some(value.to_string()),
+            "branch" => entry.branch = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    submodules.retain(|s| !s.path.is_empty());
+    submodules.sort_by(|a, b| a.path.cmp(&b.path));
+    submodules
+}
+
+/// Parse `git status --porcelain=v2 -z` into changed files (line counts
+/// left unset). Records are `1 XY ...` (changed), `2 XY ...` (renamed or
+/// copied, followed by the original path), `u XY ...` (unmerged) and
+/// `? path` (untracked); X is the index side, Y the working tree side.
+fn parse_status(output: &str) -> WorkingChanges {
+    let change = |c: char| match c {
+        'A' => Some(FileChange::Added),
+        'M' => Some(FileChange::Modified),
+        'D' => Some(FileChange::Deleted),
+        'R' => Some(FileChange::Renamed),
+        'C' => Some(FileChange::Copied),
+        'T' => Some(FileChange::TypeChanged),
+        _ => None,
+    };
+    let file = |path: &str, old_path: Option<&str>, change: FileChange| ChangedFile {
+        path: path.to_string(),
+        old_path: old_path.map(str::to_string),
+        change,
+        additions: None,
+        deletions: None,
+    };
+
+    let mut changes = WorkingChanges::default();
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        let (kind, rest) = record.split_once(' ').unwrap_or((record, ""));
+        let xy: Vec<char> = rest.chars().take(2).collect();
+        match kind {
+            "1" | "2" => {
+                let fields: Vec<&str> = rest.splitn(if kind == "1" { 8 } else { 9 }, ' ').collect();
+                let Some(&path) = fields.last() else { continue };
+                let old_path = if kind == "2" { records.next() } else { None };
+                if let Some(c) = xy.first().copied().and_then(change) {
+                    changes.staged.push(file(path, old_path, c));
+                }
+                if let Some(c) = xy.get(1).copied().and_then(change) {
+                    changes.unstaged.push(file(path, None, c));
+                }
+            }
+            "u" => {
+                let fields: Vec<&str> = rest.splitn(10, ' ').collect();
+                if fields.len() < 10 {
+                    continue;
+                }
+                let present = |mode: &str| mode.bytes().any(|b| b != b'0');
+                changes.conflicted.push(MergeConflict {
+                    path: fields[9].to_string(),
+                    kind: ConflictKind::from_stages(present(fields[2]), present(fields[3]), present(fields[4])),
+                });
+            }
+            "?" => changes.untracked.push(file(rest, None, FileChange::Added)),
+            _ => {}
+        }
+    }
+    for list in [&mut changes.staged, &mut changes.unstaged, &mut changes.untracked] {
+        list.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+    changes.conflicted.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+/// Parse `git diff --numstat -z`: `added\tdeleted\tpath` records, or
+/// `added\tdeleted\t` followed by the old and new paths for a rename.
+/// Binary files show `-` for both counts. Keyed by (new) path.
+fn parse_numstat(output: &str) -> HashMap<String, (Option<u32>, Option<u32>)> {
+    let mut stats = HashMap::new();
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        let mut fields = record.splitn(3, '\t');
+        let (Some(added), Some(deleted), Some(path)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let path = if path.is_empty() {
+            records.next();
+            records.next().unwrap_or_default()
+        } else {
+            path
+        };
+        stats.insert(path.to_string(), (added.parse().ok(), deleted.parse().ok()));
+    }
+    stats
+}
+
+/// Parse `git log -g --format=%H%x00%cI%x00%gs refs/stash` output.
+fn parse_stashes(output: &str) -> Vec<StashEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let f: Vec<&str> = line.splitn(3, '\0').collect();
+            (f.len() == 3).then(|| (f[0], f[1], f[2]))
+        })
+        .enumerate()
+        .map(|(index, (sha, date, message))| StashEntry {
+            index,
+            sha: sha.to_string(),
+            message: message.to_string(),
+            date: date.to_string(),
+        })
+        .collect()
+}
+
+/// `%G?`, `%GS` and `%GK` values.
+fn parse_signature(f: &[&str]) -> Signature {
+    let non_empty = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+    Signature {
+        status: SignatureStatus::from_code(f[0].trim()),
+        signer: non_empty(f[1]),
+        key: non_empty(f[2]),
+    }
+}
+
+/// Verify the signatures of `shas` with git, which libgit2 can't do.
+pub(super) fn verify_signatures(repo: &Path, shas: &[String]) -> Result<HashMap<String, Signature>, GitError> {
+    if shas.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let format = format!("--format=%H{}", SIGNATURE_FORMAT);
+    let input = shas.join("\n").into_bytes();
+    let output = run_with_input(repo, &["log", "-z", "--no-walk=unsorted", "--stdin", &format], input)?;
+    let fields: Vec<&str> = output.split('\0').collect();
+    Ok(fields
+        .chunks_exact(1 + SIGNATURE_FIELDS)
+        .map(|f| (f[0].to_string(), parse_signature(&f[1..])))
+        .collect())
+}
+
+/// `for-each-ref` format for `TagEntry`. Every field ends in a NUL like
+/// `LOG_FORMAT`; for-each-ref adds a newline after each record on top.
+/// `*` fields read the object an annotated tag points at.
+const TAG_FORMAT: &str = "--format=%(refname:strip=2)%00%(objecttype)%00%(objectname)%00%(*objecttype)%00%(*objectname)%00%(taggername)%00%(taggeremail)%00%(taggerdate:iso-strict)%00%(committerdate:iso-strict)%00%(*committerdate:iso-strict)%00%(contents)%00";
+const TAG_FIELDS: usize = 11;
+
+/// Parse `for-each-ref refs/tags` output produced with `TAG_FORMAT`, keeping
+/// only tags that point at a commit.
+fn parse_tags(output: &str) -> Vec<TagEntry> {
+    let fields: Vec<&str> = output.split('\0').collect();
+    fields
+        .chunks_exact(TAG_FIELDS)
+        .filter_map(|f| {
+            let name = f[0].trim_start_matches('\n').to_string();
+            let entry = match (f[1], f[3]) {
+                ("commit", _) => TagEntry {
+                    name,
+                    target_sha: f[2].to_string(),
+                    annotated: false,
+                    tagger_name: None,
+                    tagger_email: None,
+                    date: f[8].to_string(),
+                    message: None,
+                },
+                ("tag", "commit") => TagEntry {
+                    name,
+                    target_sha: f[4].to_string(),
+                    annotated: true,
+                    tagger_name: Some(f[5].to_string()).filter(|s| !s.is_empty()),
+                    tagger_email: Some(f[6].trim_start_matches('<').trim_end_matches('>').to_string())
+                        .filter(|s| !s.is_empty()),
+                    date: if f[7].is_empty() { f[9].to_string() } else { f[7].to_string() },
+                    message: Some(strip_tag_signature(f[10])),
+                },
+                _ => return None,
+            };
+            Some(entry)
+        })
+        .collect()
+}
+
+/// Parse `merge-tree --write-tree -z --no-messages` output: the result tree,
+/// then one `<mode> <oid> <stage>\t<path>` record per conflicted stage.
+fn parse_merge_tree(output: &[u8]) -> Vec<MergeConflict> {
+    let text = String::from_utf8_lossy(output);
+    // path -> (base, ours, theirs)
+    let mut stages: Vec<(String, [bool; 3])> = Vec::new();
+    for record in text.split('\0').skip(1) {
+        let Some((info, path)) = record.split_once('\t') else {
+            continue;
+        };
+        let stage = match info.rsplit(' ').next() {
+            Some("1") => 0,
+            Some("2") => 1,
+            Some("3") => 2,
+            _ => continue,
+        };
+        match stages.iter_mut().find(|(p, _)| p == path) {
+            Some((_, seen)) => seen[stage] = true,
+            None => {
+                let mut seen = [false; 3];
+                seen[stage] = true;
+                stages.push((path.to_string(), seen));
+            }
+        }
+    }
+
+    let mut conflicts: Vec<MergeConflict> = stages
+        .into_iter()
+        .map(|(path, [base, ours, theirs])| MergeConflict {
+            path,
+            kind: ConflictKind::from_stages(base, ours, theirs),
+        })
+        .collect();
+    conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+    conflicts
+}
+
+/// One commit from the `log --boundary` walk used to find fork points.
+struct WalkCommit {
+    /// Excluded (base) commit that is a parent of a branch-only commit
+    boundary: bool,
+    parents: Vec<String>,
+    date: String,
+    timestamp: i64,
+}
+
+/// Parse `log --boundary --format=%m%x00%H%x00%P%x00%aI%x00%ct` output.
+fn parse_walk(output: &str) -> HashMap<String, WalkCommit> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\0').collect();
+            if fields.len() < 5 {
+                return None;
+            }
+            Some((
+                fields[1].to_string(),
+                WalkCommit {
+                    boundary: fields[0] == "-",
+                    parents: fields[2].split_whitespace().map(str::to_string).collect(),
+                    date: fields[3].to_string(),
+                    timestamp: fields[4].parse().unwrap_or(0),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Walk the branch-only commits reachable from `head`. Returns the number of
+/// commits visited and the base commits the walk stopped at, or `None` if
+/// `head` is itself part of the base history.
+fn walk_branch<'g>(graph: &'g HashMap<String, WalkCommit>, head: &str) -> Option<(i32, Vec<&'g str>)> {
+    let (head, commit) = graph.get_key_value(head)?;
+    if commit.boundary {
+        return None;
+    }
+
+    let mut seen = HashSet::new();
+    let mut stack = vec![head.as_str()];
+    let mut boundaries = Vec::new();
+    let mut ahead = 0;
+
+    while let Some(sha) = stack.pop() {
+        if !seen.insert(sha) {
+            continue;
+        }
+        // Parents outside the walk only happen in shallow clones
+        let Some((sha, commit)) = graph.get_key_value(sha) else { continue };
+        if commit.boundary {
+            boundaries.push(sha.as_str());
+            continue;
+        }
+        ahead += 1;
+        stack.extend(commit.parents.iter().map(String::as_str));
+    }
+
+    Some((ahead, boundaries))
+}
+
+/// Backend that shells out to the `git` binary for every query.
+pub struct CliBackend;
+
+impl GitBackend for CliBackend {
+    fn resolve(&self, repo: &Path, rev: &str) -> Result<Option<String>, GitError> {
+        match run_optional(repo, &["rev-parse", "--verify", "--quiet", rev]) {
+            // `--quiet` doesn't cover a peel to the wrong type (`tree^{commit}`)
+            Err(GitError::CommandFailed(stderr)) if stderr.contains("but the object dereferences to") => Ok(None),
+            resolved => resolved,
+        }
+    }
+
+    fn symbolic_ref(&self, repo: &Path, name: &str) -> Result<Option<String>, GitError> {
+        match run(repo, &["symbolic-ref", "-q", name]) {
+            Ok(output) => Ok(Some(output.trim().to_string())),
+            // Missing refs and non-symbolic refs both fail here
+            Err(GitError::CommandFailed(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn toplevel(&self, repo: &Path) -> Result<String, GitError> {
+        Ok(run(repo, &["rev-parse", "--show-toplevel"])?.trim().to_string())
+    }
+
+    fn repo_kind(&self, dir: &Path) -> Result<Option<RepoKind>, GitError> {
+        let output = match run(dir, &["rev-parse", "--is-bare-repository", "--absolute-git-dir", "--git-common-dir"]) {
+            Ok(output) => output,
+            Err(GitError::NotARepo(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let lines: Vec<&str> = output.lines().collect();
+        let [bare, git_dir, common_dir] = lines[..] else {
+            return Err(GitError::CommandFailed(format!("unexpected rev-parse output: {}", output)));
+        };
+        let root = canonical(dir);
+        if bare == "true" {
+            return Ok((canonical(Path::new(git_dir)) == root).then_some(RepoKind::Bare));
+        }
+
+        // Inside a git directory there is no top level, so that fails too
+        let Ok(output) = run(dir, &["rev-parse", "--show-toplevel", "--show-superproject-working-tree"]) else {
+            return Ok(None);
+        };
+        let mut lines = output.lines();
+        if lines.next().map(|toplevel| canonical(Path::new(toplevel))) != Some(root) {
+            return Ok(None);
+        }
+        Ok(Some(if canonical(Path::new(git_dir)) != canonical(&dir.join(common_dir)) {
+            RepoKind::Worktree
+        } else if lines.next().is_some_and(|superproject| !superproject.is_empty()) {
+            RepoKind::Submodule
+        } else {
+            RepoKind::Normal
+        }))
+    }
+
+    fn git_dirs(&self, repo: &Path) -> Result<GitDirs, GitError> {
+        let output = run(repo, &["rev-parse", "--absolute-git-dir", "--git-common-dir"])?;
+        let lines: Vec<&str> = output.lines().collect();
+        let [git_dir, common_dir] = lines[..] else {
+            return Err(GitError::CommandFailed(format!("unexpected rev-parse output: {}", output)));
+        };
+        // The common dir is relative to `repo` unless it is elsewhere
+        Ok(GitDirs {
+            git_dir: PathBuf::from(git_dir),
+            common_dir: repo.join(common_dir),
+        })
+    }
+
+    fn list_refs(&self, repo: &Path, prefix: &str) -> Result<Vec<RefEntry>, GitError> {
+        let output = run(
+            repo,
+            &[
+                "for-each-ref",
+                "--format=%(objecttype) %(*objecttype) %(objectname) %(*objectname) %(refname)",
+                prefix,
+            ],
+        )?;
+
+        let mut refs = Vec::new();
+        for line in output.lines() {
+            let fields: Vec<&str> = line.splitn(5, ' ').collect();
+            let [kind, peeled_kind, sha, peeled, name] = fields[..] else { continue };
+            let sha = match (kind, peeled_kind) {
+                ("commit", _) => sha.to_string(),
+                (_, "commit") => peeled.to_string(),
+                // A tag of a tag: peel the rest of the way
+                (_, "tag") => match self.resolve(repo, &format!("{}^{{commit}}", name))? {
+                    Some(sha) => sha,
+                    None => continue,
+                },
+                // Tags of trees and blobs
+                _ => continue,
+            };
+            refs.push(RefEntry { name: name.to_string(), sha });
+        }
+        Ok(refs)
+    }
+
+    fn branch_summaries(&self, repo: &Path, prefixes: &[&str], base: &str) -> Result<Vec<BranchSummary>, GitError> {
+        // git >= 2.41 reports ahead/behind for every ref in the same pass;
+        // older versions reject the atom and we count from the walk below.
+        let fast_format = format!("--format={}%00%(ahead-behind:{})", TIP_FORMAT, base);
+        let mut args = vec!["for-each-ref", fast_format.as_str()];
+        args.extend_from_slice(prefixes);
+        let (output, has_counts) = match run(repo, &args) {
+            Ok(output) => (output, true),
+            Err(GitError::CommandFailed(_)) => {
+                let format = format!("--format={}", TIP_FORMAT);
+                let mut args = vec!["for-each-ref", format.as_str()];
+                args.extend_from_slice(prefixes);
+                (run(repo, &args)?, false)
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut summaries: Vec<BranchSummary> = output
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split('\0').collect();
+                if fields.len() < 6 {
+                    return None;
+                }
+                let counts: Vec<i32> = fields
+                    .get(6)
+                    .map(|c| c.split_whitespace().filter_map(|n| n.parse().ok()).collect())
+                    .unwrap_or_default();
+                let upstream_counts = if fields[4].is_empty() { None } else { parse_track(fields[5]) };
+                Some(BranchSummary {
+                    refname: fields[0].to_string(),
+                    head_sha: fields[1].to_string(),
+                    author: fields[2].to_string(),
+                    date: fields[3].to_string(),
+                    ahead: counts.first().copied().unwrap_or(0),
+                    behind: counts.get(1).copied().unwrap_or(0),
+                    fork_sha: None,
+                    fork_date: None,
+                    upstream: upstream_counts.map(|_| fields[4].to_string()),
+                    upstream_counts,
+                })
+            })
+            .collect();
+
+        if summaries.is_empty() {
+            return Ok(summaries);
+        }
+
+        // One walk over every branch-only commit finds all fork points: each
+        // branch forks at the base commit its walk runs into, or at the best of
+        // them when it runs into several. Criss-cross histories have more than
+        // one best; like `Libgit2Backend`, take the newest by committer date.
+        let exclude = format!("^{}", base);
+        let mut heads: Vec<&str> = summaries.iter().map(|s| s.head_sha.as_str()).collect();
+        heads.sort_unstable();
+        heads.dedup();
+        let mut args = vec!["log", "--boundary", "--format=%m%x00%H%x00%P%x00%aI%x00%ct", exclude.as_str()];
+        args.extend(heads);
+        args.push("--");
+        let graph = parse_walk(&run(repo, &args)?);
+
+        // Fallback behind counts, keyed by the set of base commits each branch
+        // reaches (most branches share a fork point)
+        let mut behind_cache: HashMap<Vec<String>, i32> = HashMap::new();
+
+        for summary in &mut summaries {
+            let stops: Vec<String> = match walk_branch(&graph, &summary.head_sha) {
+                Some((ahead, boundaries)) => {
+                    if !has_counts {
+                        summary.ahead = ahead;
+                    }
+                    // The merge base is whichever boundary commit none of the
+                    // others descends from; only git can tell when there are several
+                    let bases = if boundaries.len() > 1 {
+                        let output = run(repo, &["merge-base", "--all", base, &summary.head_sha])?;
+                        output.lines().map(str::to_string).collect()
+                    } else {
+                        boundaries.iter().map(|sha| sha.to_string()).collect::<Vec<_>>()
+                    };
+                    let fork = bases
+                        .iter()
+                        .filter_map(|sha| graph.get_key_value(sha.as_str()))
+                        .max_by_key(|(sha, c)| (c.timestamp, sha.as_str()));
+                    if let Some((sha, commit)) = fork {
+                        summary.fork_sha = Some(sha.clone());
+                        summary.fork_date = Some(commit.date.clone());
+                    }
+                    boundaries.into_iter().map(str::to_string).collect()
+                }
+                None => {
+                    // Already contained in the base: the tip is the fork point
+                    if !has_counts {
+                        summary.ahead = 0;
+                    }
+                    summary.fork_sha = Some(summary.head_sha.clone());
+                    summary.fork_date = Some(summary.date.clone());
+                    vec![summary.head_sha.clone()]
+                }
+            };
+
+            if !has_counts {
+                let mut key = stops;
+                key.sort_unstable();
+                summary.behind = match behind_cache.get(&key) {
+                    Some(&behind) => behind,
+                    None => {
+                        let excludes: Vec<String> = key.iter().map(|sha| format!("^{}", sha)).collect();
+                        let mut args = vec!["rev-list", "--count", base];
+                        args.extend(excludes.iter().map(String::as_str));
+                        args.push("--");
+                        let behind = run(repo, &args)?.trim().parse().unwrap_or(0);
+                        behind_cache.insert(key, behind);
+                        behind
+                    }
+                };
+            }
+        }
+
+        Ok(summaries)
+    }
+
+    fn tags(&self, repo: &Path) -> Result<Vec<TagEntry>, GitError> {
+        let output = run(repo, &["for-each-ref", TAG_FORMAT, "refs/tags/"])?;
+        Ok(parse_tags(&output))
+    }
+
+    fn worktrees(&self, repo: &Path) -> Result<Vec<WorktreeEntry>, GitError> {
+        let output = run(repo, &["worktree", "list", "--porcelain"])?;
+        Ok(parse_worktrees(&output))
+    }
+
+    fn is_dirty(&self, worktree: &Path) -> Result<bool, GitError> {
+        let output = run(worktree, &["status", "--porcelain", "--untracked-files=normal"])?;
+        Ok(!output.trim().is_empty())
+    }
+
+    fn submodules(&self, repo: &Path) -> Result<Vec<SubmoduleEntry>, GitError> {
+        let root = PathBuf::from(self.toplevel(repo)?);
+        let gitmodules = root.join(".gitmodules");
+        if !gitmodules.is_file() {
+            return Ok(Vec::new());
+        }
+        let file = gitmodules.to_str().ok_or_else(|| GitError::InvalidPath(gitmodules.display().to_string()))?;
+        // Exits 1 when nothing matches
+        let config = run_optional(&root, &["config", "-z", "-f", file, "--get-regexp", r"^submodule\."])?;
+        let mut submodules = parse_gitmodules(&config.unwrap_or_default());
+        if submodules.is_empty() || self.resolve(&root, "HEAD")?.is_none() {
+            return Ok(submodules);
+        }
+
+        // Gitlinks (mode 160000) recorded in HEAD: `<mode> commit <sha>\t<path>`
+        let mut args = vec!["ls-tree", "-z", "HEAD", "--"];
+        args.extend(submodules.iter().map(|s| s.path.as_str()));
+        let tree = run(&root, &args)?;
+        let pins: HashMap<&str, &str> = tree
+            .split('\0')
+            .filter_map(|record| {
+                let (info, path) = record.split_once('\t')?;
+                match info.split(' ').collect::<Vec<_>>()[..] {
+                    ["160000", "commit", sha] => Some((path, sha)),
+                    _ => None,
+                }
+            })
+            .collect();
+        for submodule in &mut submodules {
+            submodule.pinned_sha = pins.get(submodule.path.as_str()).map(|sha| sha.to_string());
+        }
+        Ok(submodules)
+    }
+
+    fn working_changes(&self, worktree: &Path) -> Result<WorkingChanges, GitError> {
+        // Paths in status and diff output are relative to the top level
+        let root = PathBuf::from(self.toplevel(worktree)?);
+        let status = run(&root, &["status", "--porcelain=v2", "-z", "--untracked-files=all", "--renames"])?;
+        let mut changes = parse_status(&status);
+
+        let staged = parse_numstat(&run(&root, &["diff", "--cached", "--numstat", "-z", "-M"])?);
+        let unstaged = parse_numstat(&run(&root, &["diff", "--numstat", "-z", "--no-renames"])?);
+        for (files, stats) in [(&mut changes.staged, &staged), (&mut changes.unstaged, &unstaged)] {
+            for file in files.iter_mut() {
+                if let Some(&(additions, deletions)) = stats.get(&file.path) {
+                    file.additions = additions;
+                    file.deletions = deletions;
+                }
+            }
+        }
+        for file in &mut changes.untracked {
+            file.additions = count_lines(&root.join(&file.path));
+            file.deletions = file.additions.map(|_| 0);
+        }
+        Ok(changes)
+    }
+
+    fn stashes(&self, repo: &Path) -> Result<Vec<StashEntry>, GitError> {
+        // `git stash list` needs a work tree; walking the reflog doesn't
+        if self.resolve(repo, "refs/stash")?.is_none() {
+            return Ok(Vec::new());
+        }
+        let output = run(repo, &["log", "-g", "--format=%H%x00%cI%x00%gs", "refs/stash"])?;
+        Ok(parse_stashes(&output))
+    }
+
+    fn list_remotes(&self, repo: &Path) -> Result<Vec<String>, GitError> {
+        let output = run(repo, &["remote"])?;
+        let mut remotes: Vec<String> = output
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect();
+        remotes.sort();
+        Ok(remotes)
+    }
+
+    fn ahead_behind(&self, repo: &Path, ours: &str, theirs: &str) -> Result<(usize, usize), GitError> {
+        let range = format!("{}...{}", ours, theirs);
+        let output = run(repo, &["rev-list", "--count", "--left-right", &range, "--"])?;
+        let counts: Vec<usize> = output.split_whitespace().filter_map(|n| n.parse().ok()).collect();
+        match counts[..] {
+            [ahead, behind] => Ok((ahead, behind)),
+            _ => Err(GitError::CommandFailed(format!("unexpected rev-list output: {}", output))),
+        }
+    }
+
+    fn merge_base(&self, repo: &Path, revs: &[&str]) -> Result<Option<String>, GitError> {
+        if revs.is_empty() {
+            return Ok(None);
+        }
+        let mut args = vec!["merge-base", "--octopus"];
+        args.extend_from_slice(revs);
+        run_optional(repo, &args)
+    }
+
+    fn log(&self, repo: &Path, query: &LogQuery) -> Result<Vec<Commit>, GitError> {
+        let format = if query.verify_signatures {
+            format!("{}{}", LOG_FORMAT, SIGNATURE_FORMAT)
+        } else {
+            LOG_FORMAT.to_string()
+        };
+        let mut args: Vec<String> = vec!["log".into(), "-z".into(), format];
+        match query.merges {
+            MergeFilter::All => {}
+            MergeFilter::Exclude => args.push("--no-merges".into()),
+        }
+        if let Some(max) = query.max_count {
+            args.push(format!("--max-count={}", max));
+        }
+        if query.skip > 0 {
+            args.push(format!("--skip={}", query.skip));
+        }
+        if let Some(since) = query.since {
+            args.push(format!("--since=@{}", since));
+        }
+        if query.children_first {
+            args.push("--date-order".into());
+        }
+        if query.first_parent {
+            args.push("--first-parent".into());
+        }
+        args.extend(query.include.iter().map(|rev| rev.to_string()));
+        if let Some(exclude) = query.exclude {
+            args.push(format!("^{}", exclude));
+        }
+        args.push("--".into());
+
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = run(repo, &args)?;
+
+        Ok(parse_log(&output, query.verify_signatures))
+    }
+
+    fn diff(&self, repo: &Path, range: DiffRange) -> Result<String, GitError> {
+        run(repo, &["diff", &range.to_arg(), "--unified=3"])
+    }
+
+    fn diff_names(&self, repo: &Path, range: DiffRange) -> Result<Vec<String>, GitError> {
+        let output = run(repo, &["diff", "--name-only", &range.to_arg()])?;
+        Ok(output
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn ls_tree(&self, repo: &Path, rev: &str, paths: &[&str]) -> Result<Vec<String>, GitError> {
+        let mut args = vec!["ls-tree", "-r", "--name-only", rev, "--"];
+        args.extend_from_slice(paths);
+        let output = run(repo, &args)?;
+        Ok(output
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn patch_ids(&self, repo: &Path, include: &str, exclude: &str) -> Result<Vec<(String, String)>, GitError> {
+        let exclude = format!("^{}", exclude);
+        let patches = run_bytes(
+            repo,
+            &["log", "-p", "--no-merges", "--no-color", "--format=commit %H", include, &exclude, "--"],
+        )?;
+        let output = run_with_input(repo, &["patch-id", "--stable"], patches)?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let (patch_id, sha) = line.split_once(' ')?;
+                Some((sha.to_string(), patch_id.to_string()))
+            })
+            .collect())
+    }
+
+    fn diff_patch_id(&self, repo: &Path, from: &str, to: &str) -> Result<Option<String>, GitError> {
+        let patch = run_bytes(repo, &["diff", "--no-color", from, to, "--"])?;
+        if patch.is_empty() {
+            return Ok(None);
+        }
+        let output = run_with_input(repo, &["patch-id", "--stable"], patch)?;
+        Ok(output.split_whitespace().next().map(str::to_string))
+    }
+
+    fn merge_tree(&self, repo: &Path, ours: &str, theirs: &str) -> Result<Vec<MergeConflict>, GitError> {
+        let output = run_output(
+            repo,
+            &["merge-tree", "--write-tree", "-z", "--no-messages", ours, theirs],
+        )?;
+        match output.status.code() {
+            // 0: clean, 1: conflicts
+            Some(0) | Some(1) => Ok(parse_merge_tree(&output.stdout)),
+            _ => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                // `--write-tree` needs git 2.38+; older versions print usage
+                if stderr.contains("usage:") || stderr.contains("unknown option") {
+                    return Libgit2Backend.merge_tree(repo, ours, theirs);
+                }
+                if stderr.contains("not a git repository") {
+                    return Err(GitError::NotARepo(repo.display().to_string()));
+                }
+                Err(GitError::CommandFailed(stderr.into_owned()))
+            }
+        }
+    }
+
+    fn archive(&self, repo: &Path, rev: &str) -> Result<Vec<u8>, GitError> {
+        run_bytes(repo, &["archive", "--format=tar", rev])
+    }
 }
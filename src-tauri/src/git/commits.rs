@@ -1,53 +1,35 @@
-use super::cli::{self, GitError};
-use serde::Serialize;
+use super::backend::{backend, Commit, LogQuery, MergeFilter};
+use super::cli::GitError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
-#[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct DirectCommit {
-    pub full_sha: String,
-    pub sha: String,
-    pub message: String,
-    pub author: String,
-    pub date: String,
-}
-
 /// Get direct (non-merge) commits from a branch.
 pub fn get_direct_commits(
     repo: &Path,
     branch: &str,
     limit: u32,
-) -> Result<Vec<DirectCommit>, GitError> {
-    let output = cli::run(
-        repo,
-        &[
-            "log",
-            "--no-merges",
-            &format!("--max-count={}", limit),
-            "--format=%H|%h|%s|%an|%aI",
-            branch,
-        ],
-    )?;
-
-    let commits = output
-        .lines()
-        .filter(|s| !s.is_empty())
-        .filter_map(|line| {
-            let parts: Vec<&str> = line.splitn(5, '|').collect();
-            if parts.len() < 5 {
-                return None;
-            }
-            Some(DirectCommit {
-                full_sha: parts[0].to_string(),
-                sha: parts[1].to_string(),
-                message: parts[2].to_string(),
-                author: parts[3].to_string(),
-                date: parts[4].to_string(),
-            })
-        })
-        .collect();
+) -> Result<Vec<Commit>, GitError> {
+    let query = LogQuery {
+        include: &[branch],
+        merges: MergeFilter::Exclude,
+        max_count: Some(limit),
+        ..Default::default()
+    };
+
+    backend().log(repo, &query)
+}
 
-    Ok(commits)
+/// How a line of work landed on the branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    /// A merge commit
+    Merge,
+    /// A single commit, e.g. GitHub's `Title (#123)` squash commits
+    Squash,
+    /// A series of commits replayed onto the branch
+    Rebase,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -58,70 +40,170 @@ pub struct MergeNode {
     pub pr_number: Option<i32>,
     pub pr_title: Option<String>,
     pub date: String,
+    pub strategy: MergeStrategy,
+    /// Commits on the branch this node stands for (more than one only for
+    /// rebase merges; `sha` is the newest of them)
+    pub commit_count: usize,
+}
+
+/// A merged PR already fetched from GitHub. The frontend passes its
+/// `MergedPR` list back as-is; only these fields are read.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownPr {
+    pub number: i64,
+    pub title: String,
+    pub merge_commit_sha: String,
+    #[serde(default)]
+    pub commit_count: Option<i64>,
+    #[serde(default)]
+    pub author_login: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
 }
 
-/// Get merge commits from a branch (commits with 2+ parents)
-pub fn get_merge_commits(
+/// Get the PR nodes on a branch, whatever the merge strategy: merge commits,
+/// squash commits and rebased series along its first-parent history.
+/// Commits matching a `known` PR's merge commit SHA take its number and title.
+pub fn get_merge_nodes(
     repo: &Path,
     branch: &str,
     page: u32,
     per_page: u32,
+    known: &[KnownPr],
 ) -> Result<(Vec<MergeNode>, bool), GitError> {
-    let skip = page * per_page;
-    // Fetch one extra to determine if there are more
-    let limit = per_page + 1;
-
-    let output = cli::run(
-        repo,
-        &[
-            "log",
-            "--merges",
-            &format!("--max-count={}", limit),
-            &format!("--skip={}", skip),
-            "--format=%H|%h|%s|%aI",
-            branch,
-        ],
-    )?;
-
-    let mut nodes: Vec<MergeNode> = output
-        .lines()
-        .filter(|s| !s.is_empty())
-        .filter_map(|line| parse_merge_commit(line))
-        .collect();
-
-    // Check if there are more results
-    let has_more = nodes.len() > per_page as usize;
-    if has_more {
-        nodes.pop(); // Remove the extra one we fetched
+    let known: HashMap<&str, &KnownPr> = known.iter().map(|pr| (pr.merge_commit_sha.as_str(), pr)).collect();
+    let per_page = per_page as usize;
+    // Fetch one extra to determine if there are more. A page that far out
+    // can't exist.
+    let Some((skip, wanted)) = (page as usize)
+        .checked_mul(per_page)
+        .and_then(|skip| Some((skip, skip.checked_add(per_page)?.checked_add(1)?)))
+    else {
+        return Ok((Vec::new(), false));
+    };
+
+    // Direct commits don't become nodes, so read a generous window of
+    // history and widen it until it yields enough nodes
+    let mut window = u32::try_from(wanted).unwrap_or(u32::MAX).saturating_mul(4).max(200);
+    loop {
+        let query = LogQuery {
+            include: &[branch],
+            first_parent: true,
+            max_count: Some(window),
+            ..Default::default()
+        };
+        let commits = backend().log(repo, &query)?;
+        let truncated = commits.len() == window as usize;
+
+        let mut nodes = classify(&commits, &known);
+        if truncated {
+            // A rebased series may continue past the end of the window
+            nodes.retain(|(_, last)| last + 1 < commits.len());
+            if nodes.len() < wanted {
+                window = window.saturating_mul(2);
+                continue;
+            }
+        }
+
+        let mut nodes: Vec<MergeNode> = nodes.into_iter().skip(skip).map(|(node, _)| node).collect();
+        let has_more = nodes.len() > per_page;
+        nodes.truncate(per_page);
+        return Ok((nodes, has_more));
+    }
+}
+
+/// Turn first-parent history (newest first) into nodes, each paired with the
+/// index of the oldest commit it covers.
+pub(super) fn classify(commits: &[Commit], known: &HashMap<&str, &KnownPr>) -> Vec<(MergeNode, usize)> {
+    let mut nodes = Vec::new();
+    let mut i = 0;
+
+    while i < commits.len() {
+        let commit = &commits[i];
+        let pr = known.get(commit.full_sha.as_str());
+        let node = if commit.parents.len() > 1 {
+            Some((MergeStrategy::Merge, 1, parse_pr_info(&commit.subject)))
+        } else {
+            let series = replayed_series(&commits[i..]);
+            match pr {
+                Some(pr) => {
+                    // GitHub's merge commit SHA for a rebase merge is the
+                    // newest commit of the replayed series
+                    let count = pr.commit_count.map_or(series, |n| series.min(n.max(1) as usize));
+                    let strategy = if count > 1 { MergeStrategy::Rebase } else { MergeStrategy::Squash };
+                    Some((strategy, count, (None, None)))
+                }
+                None => match parse_squash_subject(&commit.subject) {
+                    Some((number, title)) => Some((MergeStrategy::Squash, 1, (Some(number), Some(title)))),
+                    // Anyone can replay a series locally and push it; only
+                    // GitHub's own identity marks it as a rebase-merged PR
+                    None if series > 1 && commit.committer_email == WEB_FLOW_EMAIL => {
+                        Some((MergeStrategy::Rebase, series, (None, None)))
+                    }
+                    None => None,
+                },
+            }
+        };
+
+        let Some((strategy, count, (pr_number, pr_title))) = node else {
+            // Direct commit
+            i += 1;
+            continue;
+        };
+
+        let (pr_number, pr_title) = match pr {
+            Some(pr) => (i32::try_from(pr.number).ok(), Some(pr.title.clone())),
+            None => (pr_number, pr_title),
+        };
+        nodes.push((
+            MergeNode {
+                sha: commit.short_sha.clone(),
+                full_sha: commit.full_sha.clone(),
+                pr_number,
+                pr_title,
+                date: commit.author_date.clone(),
+                strategy,
+                commit_count: count,
+            },
+            i + count - 1,
+        ));
+        i += count;
     }
 
-    Ok((nodes, has_more))
+    nodes
 }
 
-fn parse_merge_commit(line: &str) -> Option<MergeNode> {
-    let parts: Vec<&str> = line.splitn(4, '|').collect();
-    if parts.len() < 4 {
+/// Committer email of commits GitHub makes itself, e.g. the replayed series
+/// of a rebase merge (`GitHub <noreply@github.com>`, the "web-flow" user).
+const WEB_FLOW_EMAIL: &str = "noreply@github.com";
+
+/// Length of the run of single-parent commits at the start of `commits` that
+/// were committed together: same committer, same committer date. That's what
+/// a rebase merge leaves behind, but also a local rebase pushed directly, so
+/// callers need the committer or a known PR to tell the two apart.
+fn replayed_series(commits: &[Commit]) -> usize {
+    let first = &commits[0];
+    commits
+        .iter()
+        .take_while(|c| {
+            c.parents.len() == 1
+                && c.committer_email == first.committer_email
+                && c.committer_date == first.committer_date
+        })
+        .count()
+}
+
+/// `Title (#123)`, the subject GitHub gives squash merges.
+fn parse_squash_subject(subject: &str) -> Option<(i32, String)> {
+    let rest = subject.trim_end().strip_suffix(')')?;
+    let start = rest.rfind("(#")?;
+    let number = rest[start + 2..].parse().ok()?;
+    let title = rest[..start].trim();
+    if title.is_empty() {
         return None;
     }
-
-    let full_sha = parts[0].to_string();
-    let sha = parts[1].to_string();
-    let subject = parts[2];
-    let date = parts[3].to_string();
-
-    // Parse PR number from commit message
-    // Common formats:
-    // "Merge pull request #123 from user/branch"
-    // "Merge branch 'feature' (#123)"
-    let (pr_number, pr_title) = parse_pr_info(subject);
-
-    Some(MergeNode {
-        sha,
-        full_sha,
-        pr_number,
-        pr_title,
-        date,
-    })
+    Some((number, title.to_string()))
 }
 
 fn parse_pr_info(subject: &str) -> (Option<i32>, Option<String>) {
@@ -168,3 +250,110 @@ fn parse_pr_info(subject: &str) -> (Option<i32>, Option<String>) {
 
     (None, None)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fixture::Fixture;
+
+    fn summary(nodes: &[MergeNode]) -> Vec<(MergeStrategy, usize, Option<i32>, Option<&str>)> {
+        nodes
+            .iter()
+            .map(|n| (n.strategy, n.commit_count, n.pr_number, n.pr_title.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_nodes_for_every_strategy() {
+        let fx = Fixture::new("commits-merge-nodes");
+        fx.commit("README.md", "hello\n", "Initial commit");
+
+        fx.git(&["checkout", "-q", "-b", "pr1"]);
+        fx.commit("a.txt", "a\n", "Add a");
+        fx.git(&["checkout", "-q", "main"]);
+        fx.merge("pr1", "Merge pull request #1 from someone/pr1");
+
+        let squash = fx.commit("search.ts", "search\n", "Add search (#2)");
+
+        // Replaying both commits in one go gives them the same committer date
+        fx.git(&["checkout", "-q", "-b", "pr3"]);
+        let b = fx.commit("b.txt", "b\n", "Add b");
+        let c = fx.commit("c.txt", "c\n", "Add c");
+        fx.git(&["checkout", "-q", "main"]);
+        fx.git(&["-c", "user.name=GitHub", "-c", "user.email=noreply@github.com", "cherry-pick", &b, &c]);
+        let series_tip = fx.git(&["rev-parse", "HEAD"]);
+
+        fx.commit("typo.txt", "typo\n", "Fix typo");
+        let styles = fx.commit("styles.css", "body {}\n", "Tweak styles");
+
+        let (nodes, has_more) = get_merge_nodes(fx.path(), "main", 0, 10, &[]).unwrap();
+        assert!(!has_more);
+        assert_eq!(
+            summary(&nodes),
+            vec![
+                (MergeStrategy::Rebase, 2, None, None),
+                (MergeStrategy::Squash, 1, Some(2), Some("Add search")),
+                (MergeStrategy::Merge, 1, Some(1), Some("someone/pr1")),
+            ]
+        );
+        assert_eq!(nodes[0].full_sha, series_tip);
+
+        let known: Vec<KnownPr> = serde_json::from_value(serde_json::json!([
+            { "number": 2, "title": "Add search UI", "mergeCommitSha": squash, "commitCount": 5, "branchName": "search" },
+            { "number": 3, "title": "Add b and c", "mergeCommitSha": series_tip, "commitCount": 2 },
+            { "number": 4, "title": "Tweak styles", "mergeCommitSha": styles },
+        ]))
+        .unwrap();
+        let (nodes, has_more) = get_merge_nodes(fx.path(), "main", 0, 3, &known).unwrap();
+        assert!(has_more);
+        assert_eq!(
+            summary(&nodes),
+            vec![
+                (MergeStrategy::Squash, 1, Some(4), Some("Tweak styles")),
+                (MergeStrategy::Rebase, 2, Some(3), Some("Add b and c")),
+                (MergeStrategy::Squash, 1, Some(2), Some("Add search UI")),
+            ]
+        );
+
+        let (nodes, has_more) = get_merge_nodes(fx.path(), "main", 1, 3, &known).unwrap();
+        assert!(!has_more);
+        assert_eq!(summary(&nodes), vec![(MergeStrategy::Merge, 1, Some(1), Some("someone/pr1"))]);
+
+        let (nodes, has_more) = get_merge_nodes(fx.path(), "main", u32::MAX, u32::MAX, &known).unwrap();
+        assert!(nodes.is_empty() && !has_more);
+    }
+
+    #[test]
+    fn test_local_rebase_stays_direct() {
+        let fx = Fixture::new("commits-local-rebase");
+        fx.commit("README.md", "hello\n", "Initial commit");
+
+        // Rebased locally and pushed straight to main: same committer and
+        // date throughout, but not GitHub
+        fx.git(&["checkout", "-q", "-b", "topic"]);
+        let d = fx.commit("d.txt", "d\n", "Add d");
+        let e = fx.commit("e.txt", "e\n", "Add e");
+        fx.git(&["checkout", "-q", "main"]);
+        fx.git(&["cherry-pick", &d, &e]);
+        let tip = fx.git(&["rev-parse", "HEAD"]);
+
+        let (nodes, _) = get_merge_nodes(fx.path(), "main", 0, 10, &[]).unwrap();
+        assert!(nodes.is_empty());
+
+        // Unless a known PR says that is how it was merged
+        let known: Vec<KnownPr> = serde_json::from_value(serde_json::json!([
+            { "number": 7, "title": "Add d and e", "mergeCommitSha": tip, "commitCount": 2 },
+        ]))
+        .unwrap();
+        let (nodes, _) = get_merge_nodes(fx.path(), "main", 0, 10, &known).unwrap();
+        assert_eq!(summary(&nodes), vec![(MergeStrategy::Rebase, 2, Some(7), Some("Add d and e"))]);
+    }
+
+    #[test]
+    fn test_parse_squash_subject() {
+        assert_eq!(parse_squash_subject("Add search (#2)"), Some((2, "Add search".to_string())));
+        assert_eq!(parse_squash_subject("Fix (#12) (#34)"), Some((34, "Fix (#12)".to_string())));
+        assert_eq!(parse_squash_subject("Fix #12 crash"), None);
+        assert_eq!(parse_squash_subject("(#5)"), None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Settings files: `<config dir>/git-visualizer/config.toml` for the user and
+//! `.git-visualizer.toml` at the top level of the work tree. Each feature
+//! owns a table in them (`[staleness]`, `[cleanup]`, `[github]`) and layers
+//! the files itself.
+
+use super::backend::backend;
+use super::cli::GitError;
+use super::discovery::work_tree;
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+
+pub const REPO_CONFIG_FILE: &str = ".git-visualizer.toml";
+
+/// Parse every settings file that exists, app-level first and the repo's own
+/// file last, so later layers win.
+pub fn layers<T: DeserializeOwned>(repo: &Path) -> Result<Vec<T>, GitError> {
+    let mut paths: Vec<PathBuf> = app_config_path().into_iter().collect();
+    paths.push(repo_config_path(repo)?);
+
+    let mut layers = Vec::new();
+    for path in paths {
+        layers.extend(read_layer(&path)?);
+    }
+    Ok(layers)
+}
+
+/// Parse only the app-level settings file, for settings that must not come
+/// from a repo (which is usually committed and shared), like credentials.
+pub fn app_settings<T: DeserializeOwned>() -> Result<Option<T>, GitError> {
+    match app_config_path() {
+        Some(path) => read_layer(&path),
+        None => Ok(None),
+    }
+}
+
+/// `.git-visualizer.toml` at the top level of the work tree containing
+/// `repo`, which may be a subdirectory, or in a bare repository's git
+/// directory.
+fn repo_config_path(repo: &Path) -> Result<PathBuf, GitError> {
+    let root = match work_tree(repo)? {
+        Some(top) => top,
+        None => backend().git_dirs(repo)?.common_dir,
+    };
+    Ok(root.join(REPO_CONFIG_FILE))
+}
+
+fn read_layer<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, GitError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(GitError::Config(format!("{}: {}", path.display(), e))),
+    };
+    let layer = toml::from_str(&text).map_err(|e| GitError::Config(format!("{}: {}", path.display(), e)))?;
+    Ok(Some(layer))
+}
+
+fn app_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("git-visualizer").join("config.toml"))
+}
+
+/// Shell-style glob with `*` (any run of characters, including `/`) and `?`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let (p, t): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut pi, mut ti) = (0, 0);
+    // Position of the last `*` and the text index it was tried against
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("release/*", "release/1.2"));
+        assert!(glob_match("release/*", "release/1.x/hotfix"));
+        assert!(!glob_match("release/*", "releases/1.2"));
+        assert!(glob_match("env-?", "env-a"));
+        assert!(glob_match("staging", "staging"));
+        assert!(!glob_match("staging", "staging-2"));
+        assert!(glob_match("*/wip", "alice/wip"));
+    }
+}
@@ -0,0 +1,202 @@
+//! Conflict prediction: merge each branch into the default branch (or into
+//! each other) in memory and report what would conflict.
+
+use super::backend::{backend, DiffRange, MergeConflict};
+use super::branches::listed_refs;
+use super::cli::GitError;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergePrediction {
+    pub branch: String,
+    pub head_sha: String,
+    /// Default branch commit the merge was tried against
+    pub base_sha: String,
+    pub clean: bool,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// How two branches interact if both land.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchPair {
+    pub left: String,
+    pub right: String,
+    /// Files both branches changed since forking from the default branch
+    pub overlapping_files: Vec<String>,
+    /// Conflicts from merging `right` into `left`
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Pairwise overlap and conflicts between a set of branches. `pairs` holds
+/// every unordered pair once, in `branches` order.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictMatrix {
+    pub branches: Vec<String>,
+    pub pairs: Vec<BranchPair>,
+}
+
+/// Stop remembering results past this many SHA pairs.
+const CACHE_LIMIT: usize = 4096;
+
+/// Merge results keyed by `(base_sha, head_sha)`. Commits are immutable, so a
+/// pair only needs recomputing when either side moves to a new SHA.
+type MergeCache = Mutex<HashMap<(String, String), Vec<MergeConflict>>>;
+
+fn cache() -> &'static MergeCache {
+    static CACHE: OnceLock<MergeCache> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn merge_conflicts(repo: &Path, base_sha: &str, head_sha: &str) -> Result<Vec<MergeConflict>, GitError> {
+    let key = (base_sha.to_string(), head_sha.to_string());
+    if let Some(hit) = cache().lock().unwrap().get(&key) {
+        return Ok(hit.clone());
+    }
+
+    let conflicts = backend().merge_tree(repo, base_sha, head_sha)?;
+
+    let mut cache = cache().lock().unwrap();
+    if cache.len() >= CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(key, conflicts.clone());
+    Ok(conflicts)
+}
+
+fn resolve(repo: &Path, rev: &str) -> Result<String, GitError> {
+    backend()
+        .resolve(repo, &format!("{}^{{commit}}", rev))?
+        .ok_or_else(|| GitError::CommandFailed(format!("unknown revision: {}", rev)))
+}
+
+fn prediction(repo: &Path, branch: String, base_sha: &str, head_sha: String) -> Result<MergePrediction, GitError> {
+    let conflicts = merge_conflicts(repo, base_sha, &head_sha)?;
+    Ok(MergePrediction {
+        branch,
+        head_sha,
+        base_sha: base_sha.to_string(),
+        clean: conflicts.is_empty(),
+        conflicts,
+    })
+}
+
+/// Predict merging a single branch into `default_branch`.
+pub fn predict_merge(repo: &Path, default_branch: &str, branch: &str) -> Result<MergePrediction, GitError> {
+    let base_sha = resolve(repo, default_branch)?;
+    let head_sha = resolve(repo, branch)?;
+    prediction(repo, branch.to_string(), &base_sha, head_sha)
+}
+
+/// Predict merging every listed branch into `default_branch`.
+pub fn predict_merges(repo: &Path, default_branch: &str) -> Result<Vec<MergePrediction>, GitError> {
+    let base_sha = resolve(repo, default_branch)?;
+    listed_refs(repo, default_branch)?
+        .into_iter()
+        .map(|listed| prediction(repo, listed.name, &base_sha, listed.summary.head_sha))
+        .collect()
+}
+
+/// Compare every pair of `branches`: which files they both touched since
+/// leaving `default_branch`, and whether merging one into the other conflicts.
+pub fn conflict_matrix(repo: &Path, default_branch: &str, branches: &[String]) -> Result<ConflictMatrix, GitError> {
+    let git = backend();
+    let mut heads = Vec::with_capacity(branches.len());
+    for branch in branches {
+        let head_sha = resolve(repo, branch)?;
+        let changed: BTreeSet<String> = git
+            .diff_names(repo, DiffRange::Since(default_branch, &head_sha))?
+            .into_iter()
+            .collect();
+        heads.push((head_sha, changed));
+    }
+
+    let mut pairs = Vec::new();
+    for (i, (left_sha, left_files)) in heads.iter().enumerate() {
+        for (j, (right_sha, right_files)) in heads.iter().enumerate().skip(i + 1) {
+            pairs.push(BranchPair {
+                left: branches[i].clone(),
+                right: branches[j].clone(),
+                overlapping_files: left_files.intersection(right_files).cloned().collect(),
+                conflicts: merge_conflicts(repo, left_sha, right_sha)?,
+            });
+        }
+    }
+
+    Ok(ConflictMatrix {
+        branches: branches.to_vec(),
+        pairs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::backend::ConflictKind;
+    use crate::git::fixture::Fixture;
+
+    #[test]
+    fn test_predict_merges() {
+        let fx = Fixture::new("conflicts-predict");
+        fx.commit("shared.txt", "one\ntwo\n", "Initial commit");
+
+        fx.git(&["checkout", "-q", "-b", "clean"]);
+        fx.commit("other.txt", "x\n", "Touch another file");
+
+        fx.git(&["checkout", "-q", "-b", "clash", "main"]);
+        fx.commit("shared.txt", "one\nbranch\n", "Edit shared line");
+
+        fx.git(&["checkout", "-q", "main"]);
+        fx.commit("shared.txt", "one\nmain\n", "Edit shared line on main");
+
+        let mut predictions = predict_merges(fx.path(), "main").unwrap();
+        predictions.sort_by(|a, b| a.branch.cmp(&b.branch));
+        assert_eq!(predictions.len(), 2);
+
+        let clash = &predictions[0];
+        assert_eq!(clash.branch, "clash");
+        assert!(!clash.clean);
+        assert_eq!(clash.conflicts.len(), 1);
+        assert_eq!(clash.conflicts[0].path, "shared.txt");
+        assert_eq!(clash.conflicts[0].kind, ConflictKind::Content);
+
+        assert!(predictions[1].clean);
+        assert!(predict_merge(fx.path(), "main", "clean").unwrap().conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_conflict_matrix() {
+        let fx = Fixture::new("conflicts-matrix");
+        fx.commit("config.ts", "a\nb\nc\nd\ne\n", "Initial commit");
+
+        fx.git(&["checkout", "-q", "-b", "first"]);
+        fx.commit("config.ts", "a\nfirst\nc\nd\ne\n", "Tweak b");
+
+        fx.git(&["checkout", "-q", "-b", "second", "main"]);
+        fx.commit("config.ts", "a\nsecond\nc\nd\ne\n", "Tweak b differently");
+
+        fx.git(&["checkout", "-q", "-b", "third", "main"]);
+        fx.commit("config.ts", "a\nb\nc\nd\nthird\n", "Tweak e");
+        fx.commit("other.ts", "x\n", "Add other");
+
+        let branches = vec!["first".to_string(), "second".to_string(), "third".to_string()];
+        let matrix = conflict_matrix(fx.path(), "main", &branches).unwrap();
+        assert_eq!(matrix.branches, branches);
+
+        let pairs: Vec<(&str, &str, usize, usize)> = matrix
+            .pairs
+            .iter()
+            .map(|p| (p.left.as_str(), p.right.as_str(), p.overlapping_files.len(), p.conflicts.len()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![("first", "second", 1, 1), ("first", "third", 1, 0), ("second", "third", 1, 0)]
+        );
+        assert_eq!(matrix.pairs[0].overlapping_files, vec!["config.ts".to_string()]);
+    }
+}
@@ -0,0 +1,71 @@
+//! Conventional Commits headers (`type(scope)!: description`), see
+//! <https://www.conventionalcommits.org>.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConventionalHeader {
+    /// Lowercased, e.g. `feat` or `fix`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub scope: Option<String>,
+    /// Marked with `!` before the colon
+    pub breaking: bool,
+    pub description: String,
+}
+
+/// Parse a subject line; `None` if it doesn't follow the convention.
+pub fn parse_header(subject: &str) -> Option<ConventionalHeader> {
+    let (prefix, description) = subject.split_once(':')?;
+    let description = description.trim();
+    if description.is_empty() || !subject[prefix.len() + 1..].starts_with(' ') {
+        return None;
+    }
+
+    let (prefix, breaking) = match prefix.strip_suffix('!') {
+        Some(prefix) => (prefix, true),
+        None => (prefix, false),
+    };
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((kind, scope)) => {
+            let scope = scope.strip_suffix(')')?.trim();
+            if scope.is_empty() || scope.contains(['(', ')']) {
+                return None;
+            }
+            (kind, Some(scope.to_string()))
+        }
+        None => (prefix, None),
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+
+    Some(ConventionalHeader {
+        kind: kind.to_ascii_lowercase(),
+        scope,
+        breaking,
+        description: description.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        let header = parse_header("feat(ui)!: drop IE support").unwrap();
+        assert_eq!(header.kind, "feat");
+        assert_eq!(header.scope.as_deref(), Some("ui"));
+        assert!(header.breaking);
+        assert_eq!(header.description, "drop IE support");
+
+        let header = parse_header("Fix: handle empty repos (#4)").unwrap();
+        assert_eq!((header.kind.as_str(), header.scope, header.breaking), ("fix", None, false));
+
+        for subject in ["Update readme", "feat:missing space", "feat(): empty scope", "fix bug: in parser", "feat: "] {
+            assert_eq!(parse_header(subject), None, "{}", subject);
+        }
+    }
+}
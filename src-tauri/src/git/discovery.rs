@@ -0,0 +1,119 @@
+//! Recognising repositories while browsing the file system, and finding the
+//! work tree of a repository that might be bare.
+
+use super::backend::{backend, RepoKind};
+use super::cli::GitError;
+use std::path::{Path, PathBuf};
+
+/// What kind of repository `dir` is the root of, if any. Directories with
+/// neither a `.git` entry (directory or file) nor the layout of a bare
+/// repository are ruled out without asking git.
+pub fn repo_kind(dir: &Path) -> Option<RepoKind> {
+    let bare_layout = dir.join("HEAD").is_file() && dir.join("objects").is_dir() && dir.join("refs").is_dir();
+    if !dir.join(".git").exists() && !bare_layout {
+        return None;
+    }
+    backend().repo_kind(dir).ok().flatten()
+}
+
+/// Top level of the work tree at `repo`, or `None` for a bare repository.
+pub fn work_tree(repo: &Path) -> Result<Option<PathBuf>, GitError> {
+    let git = backend();
+    match git.toplevel(repo) {
+        Ok(path) => Ok(Some(PathBuf::from(path))),
+        Err(e) => match git.repo_kind(repo)? {
+            Some(RepoKind::Bare) => Ok(None),
+            _ => Err(e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::backend::{backend_for, canonical, BackendKind};
+    use crate::git::fixture::Fixture;
+    use crate::git::{get_commit_graph, get_default_branch, get_merge_nodes, get_repo_info, list_branches};
+    use crate::git::{list_stashes, list_submodules, list_tags, list_worktrees, repo_status, GraphWindow};
+
+    fn kinds(dir: &Path) -> Option<RepoKind> {
+        let kind = backend_for(BackendKind::Cli).repo_kind(dir).unwrap();
+        assert_eq!(kind, backend_for(BackendKind::Libgit2).repo_kind(dir).unwrap(), "{}", dir.display());
+        kind
+    }
+
+    #[test]
+    fn test_repo_kind() {
+        let lib = Fixture::new("discovery-lib");
+        lib.commit("lib.txt", "lib\n", "Initial commit");
+
+        let fx = Fixture::new("discovery");
+        fx.commit("src/main.rs", "fn main() {}\n", "Initial commit");
+        let url = lib.path().to_str().unwrap();
+        fx.git(&["-c", "protocol.file.allow=always", "submodule", "add", "-q", url, "lib"]);
+        let linked = fx.path().with_extension("linked");
+        let _ = std::fs::remove_dir_all(&linked);
+        fx.git(&["worktree", "add", "-q", "-b", "linked", linked.to_str().unwrap()]);
+        let bare = Fixture::new_bare("discovery-bare");
+
+        assert_eq!(kinds(fx.path()), Some(RepoKind::Normal));
+        assert_eq!(kinds(&fx.path().join("lib")), Some(RepoKind::Submodule));
+        assert_eq!(kinds(&linked), Some(RepoKind::Worktree));
+        assert_eq!(kinds(bare.path()), Some(RepoKind::Bare));
+        assert_eq!(kinds(&fx.path().join("src")), None);
+        assert_eq!(kinds(&fx.path().join(".git")), None);
+        assert_eq!(kinds(&bare.path().join("refs")), None);
+        assert_eq!(kinds(&std::env::temp_dir()), None);
+
+        let [cli, lib] = [backend_for(BackendKind::Cli), backend_for(BackendKind::Libgit2)];
+        let (cli_dirs, lib_dirs) = (cli.git_dirs(&linked).unwrap(), lib.git_dirs(&linked).unwrap());
+        assert_eq!(canonical(&cli_dirs.git_dir), canonical(&lib_dirs.git_dir));
+        assert_eq!(canonical(&cli_dirs.common_dir), canonical(&lib_dirs.common_dir));
+        assert_eq!(canonical(&cli_dirs.common_dir), canonical(&fx.path().join(".git")));
+        assert_ne!(canonical(&cli_dirs.git_dir), canonical(&cli_dirs.common_dir));
+
+        assert_eq!(repo_kind(&fx.path().join("lib")), Some(RepoKind::Submodule));
+        assert_eq!(repo_kind(&fx.path().join("src")), None);
+        assert_eq!(work_tree(bare.path()).unwrap(), None);
+        assert!(work_tree(&linked).unwrap().is_some());
+
+        fx.git(&["worktree", "remove", "--force", linked.to_str().unwrap()]);
+    }
+
+    #[test]
+    fn test_commands_on_bare_repo() {
+        let fx = Fixture::new("bare-source");
+        fx.commit("README.md", "hello\n", "Initial commit");
+        fx.git(&["tag", "v1.0.0"]);
+        fx.git(&["checkout", "-q", "-b", "feature"]);
+        fx.commit("a.txt", "a\n", "Add a");
+        fx.git(&["checkout", "-q", "main"]);
+        fx.merge("feature", "Merge pull request #1 from someone/feature");
+
+        let bare = fx.path().with_extension("git");
+        let _ = std::fs::remove_dir_all(&bare);
+        fx.git(&["clone", "-q", "--bare", fx.path().to_str().unwrap(), bare.to_str().unwrap()]);
+
+        let (name, path) = get_repo_info(&bare).unwrap();
+        assert_eq!(name, format!("git-viz-test-bare-source-{}", std::process::id()));
+        assert_eq!(Path::new(&path), bare.canonicalize().unwrap());
+
+        let default = get_default_branch(&bare).unwrap();
+        assert_eq!(default, "main");
+        let names: Vec<String> = list_branches(&bare, &default).unwrap().into_iter().map(|b| b.name).collect();
+        assert_eq!(names, vec!["feature"]);
+        let (nodes, _) = get_merge_nodes(&bare, "main", 0, 10, &[]).unwrap();
+        assert_eq!(nodes[0].pr_number, Some(1));
+        assert!(!get_commit_graph(&bare, &GraphWindow::default()).unwrap().nodes.is_empty());
+        assert_eq!(list_tags(&bare).unwrap().len(), 1);
+        assert!(list_worktrees(&bare).unwrap()[0].bare);
+
+        let status = repo_status(&bare).unwrap();
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert!(status.staged.is_empty() && status.untracked.is_empty() && status.operation.is_none());
+        assert!(list_stashes(&bare).unwrap().is_empty());
+        assert!(list_submodules(&bare).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&bare).unwrap();
+    }
+}
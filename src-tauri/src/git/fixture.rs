@@ -0,0 +1,102 @@
+//! Throwaway git repositories for tests, built with the real `git` CLI.
+
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub struct Fixture {
+    path: PathBuf,
+    /// Commits get increasing, deterministic timestamps (one hour apart)
+    clock: Cell<u32>,
+}
+
+impl Fixture {
+    /// Create an empty repository with `main` as the initial branch.
+    pub fn new(name: &str) -> Self {
+        let fx = Self::empty(name);
+        fx.git(&["init", "-q", "-b", "main"]);
+        fx.git(&["config", "user.name", "Test Author"]);
+        fx.git(&["config", "user.email", "author@example.com"]);
+        fx.git(&["config", "commit.gpgsign", "false"]);
+        fx
+    }
+
+    /// Create an empty bare repository, e.g. to push to as a remote.
+    pub fn new_bare(name: &str) -> Self {
+        let fx = Self::empty(name);
+        fx.git(&["init", "-q", "--bare", "-b", "main"]);
+        fx
+    }
+
+    fn empty(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("git-viz-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("create fixture dir");
+        Fixture {
+            path,
+            clock: Cell::new(0),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run git in the fixture, panicking on failure. Returns trimmed stdout.
+    pub fn git(&self, args: &[&str]) -> String {
+        let date = self.next_date();
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.path)
+            .args(args)
+            .env("GIT_AUTHOR_DATE", &date)
+            .env("GIT_COMMITTER_DATE", &date)
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .output()
+            .expect("spawn git");
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    /// Write `contents` to `file`, commit it, and return the new HEAD SHA.
+    pub fn commit(&self, file: &str, contents: &str, message: &str) -> String {
+        self.write(file, contents);
+        self.git(&["add", "--", file]);
+        self.git(&["commit", "-q", "-m", message]);
+        self.git(&["rev-parse", "HEAD"])
+    }
+
+    /// Merge `branch` into the current branch with a merge commit.
+    pub fn merge(&self, branch: &str, message: &str) -> String {
+        self.git(&["merge", "-q", "--no-ff", "-m", message, branch]);
+        self.git(&["rev-parse", "HEAD"])
+    }
+
+    /// Write a file in the working tree without staging it.
+    pub fn write(&self, file: &str, contents: &str) {
+        let full = self.path.join(file);
+        if let Some(parent) = full.parent() {
+            std::fs::create_dir_all(parent).expect("create parent dir");
+        }
+        std::fs::write(full, contents).expect("write fixture file");
+    }
+
+    fn next_date(&self) -> String {
+        let tick = self.clock.get();
+        self.clock.set(tick + 1);
+        let base = chrono::DateTime::parse_from_rfc3339("2024-01-01T09:00:00+02:00").unwrap();
+        (base + chrono::Duration::hours(tick as i64)).to_rfc3339()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,302 @@
+//! Commit DAG for the timeline: every commit with its parents, the refs
+//! pointing at it, and a lane assignment like `git log --graph`.
+
+use super::backend::{backend, Commit, LogQuery};
+use super::cli::GitError;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefKind {
+    Head,
+    Branch,
+    Remote,
+    Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefLabel {
+    /// Short name, e.g. `main`, `origin/main`, `v1.2.0`
+    pub name: String,
+    pub kind: RefKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+    /// Parents outside the window are kept so edges can be drawn running off
+    /// the end of the graph.
+    #[serde(flatten)]
+    pub commit: Commit,
+    pub refs: Vec<RefLabel>,
+    /// Lane the commit is drawn in (0 = leftmost)
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitGraph {
+    pub nodes: Vec<GraphNode>,
+    /// Number of lanes needed to draw the window
+    pub width: usize,
+    pub has_more: bool,
+}
+
+/// Which part of history to load.
+#[derive(Debug, Clone, Default)]
+pub struct GraphWindow {
+    /// Refs to start from; all branches, remote branches and tags when empty
+    pub refs: Vec<String>,
+    pub max_count: Option<u32>,
+    /// Commits to skip, to continue where a window with `has_more` ended
+    pub skip: u32,
+    /// Unix time of the oldest commit to include
+    pub since: Option<i64>,
+}
+
+const DEFAULT_MAX_COUNT: u32 = 500;
+
+/// Ref labels keyed by the commit they point at.
+type Labels = HashMap<String, Vec<RefLabel>>;
+
+/// Refs grouped by the commit they peel to, plus the tips to walk from.
+fn decorations(repo: &Path) -> Result<(Labels, Vec<String>), GitError> {
+    let git = backend();
+    let mut labels = Labels::new();
+    let mut tips = Vec::new();
+
+    if let Ok(Some(head)) = git.resolve(repo, "HEAD") {
+        labels.entry(head.clone()).or_default().push(RefLabel {
+            name: "HEAD".to_string(),
+            kind: RefKind::Head,
+        });
+        tips.push(head);
+    }
+
+    for (prefix, kind) in [
+        ("refs/heads/", RefKind::Branch),
+        ("refs/remotes/", RefKind::Remote),
+        ("refs/tags/", RefKind::Tag),
+    ] {
+        for entry in git.list_refs(repo, prefix)? {
+            let name = &entry.name[prefix.len()..];
+            // `origin/HEAD` just mirrors the remote's default branch
+            if kind == RefKind::Remote && name.ends_with("/HEAD") {
+                continue;
+            }
+            labels.entry(entry.sha.clone()).or_default().push(RefLabel {
+                name: name.to_string(),
+                kind,
+            });
+            tips.push(entry.sha);
+        }
+    }
+
+    tips.sort();
+    tips.dedup();
+    Ok((labels, tips))
+}
+
+/// Assign each commit a column. `commits` must list children before parents.
+///
+/// Every lane holds the SHA it is waiting for. A commit takes the leftmost
+/// lane waiting for it (or a free one), closes any other lanes that were also
+/// waiting for it, then hands its lane to its first parent and opens lanes for
+/// the other parents that nobody is waiting for yet.
+fn assign_lanes(commits: &[Commit]) -> (Vec<usize>, usize) {
+    let mut lanes: Vec<Option<&str>> = Vec::new();
+    let mut columns = Vec::with_capacity(commits.len());
+    let mut width = 0;
+
+    let free_lane = |lanes: &mut Vec<Option<&str>>| match lanes.iter().position(Option::is_none) {
+        Some(i) => i,
+        None => {
+            lanes.push(None);
+            lanes.len() - 1
+        }
+    };
+
+    for commit in commits {
+        let sha = commit.full_sha.as_str();
+        let column = match lanes.iter().position(|l| *l == Some(sha)) {
+            Some(i) => i,
+            None => free_lane(&mut lanes),
+        };
+        for lane in lanes.iter_mut().skip(column + 1) {
+            if *lane == Some(sha) {
+                *lane = None;
+            }
+        }
+
+        lanes[column] = commit.parents.first().map(String::as_str);
+        for parent in commit.parents.iter().skip(1) {
+            if !lanes.contains(&Some(parent.as_str())) {
+                let i = free_lane(&mut lanes);
+                lanes[i] = Some(parent);
+            }
+        }
+
+        columns.push(column);
+        width = width.max(column + 1).max(lanes.len());
+        while lanes.last() == Some(&None) {
+            lanes.pop();
+        }
+    }
+
+    (columns, width)
+}
+
+/// Load a window of history as a DAG with lanes.
+pub fn get_commit_graph(repo: &Path, window: &GraphWindow) -> Result<CommitGraph, GitError> {
+    let (mut labels, all_tips) = decorations(repo)?;
+    let tips = if window.refs.is_empty() {
+        all_tips
+    } else {
+        // Tags of trees and blobs have no history to walk
+        let mut tips = Vec::new();
+        for rev in &window.refs {
+            tips.extend(backend().resolve(repo, &format!("{}^{{commit}}", rev))?);
+        }
+        tips
+    };
+    if tips.is_empty() {
+        return Ok(CommitGraph {
+            nodes: Vec::new(),
+            width: 0,
+            has_more: false,
+        });
+    }
+
+    let limit = window.max_count.unwrap_or(DEFAULT_MAX_COUNT);
+    let include: Vec<&str> = tips.iter().map(String::as_str).collect();
+    let query = LogQuery {
+        include: &include,
+        // Fetch one extra to determine if there are more
+        max_count: Some(limit.saturating_add(1)),
+        skip: window.skip,
+        since: window.since,
+        children_first: true,
+        ..Default::default()
+    };
+    let mut commits = backend().log(repo, &query)?;
+    let has_more = commits.len() > limit as usize;
+    commits.truncate(limit as usize);
+
+    let (columns, width) = assign_lanes(&commits);
+    let nodes = commits
+        .into_iter()
+        .zip(columns)
+        .map(|(commit, column)| GraphNode {
+            refs: labels.remove(&commit.full_sha).unwrap_or_default(),
+            commit,
+            column,
+        })
+        .collect();
+
+    Ok(CommitGraph { nodes, width, has_more })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fixture::Fixture;
+
+    fn entry(sha: &str, parents: &[&str]) -> Commit {
+        Commit {
+            full_sha: sha.to_string(),
+            short_sha: sha.to_string(),
+            subject: String::new(),
+            body: String::new(),
+            author_name: String::new(),
+            author_email: String::new(),
+            author_date: String::new(),
+            committer_name: String::new(),
+            committer_email: String::new(),
+            committer_date: String::new(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            tree: String::new(),
+            conventional: None,
+            trailers: Default::default(),
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_assign_lanes_octopus_and_criss_cross() {
+        // Octopus: m merges a, b and c, which all fork from r
+        let octopus = [
+            entry("m", &["a", "b", "c"]),
+            entry("a", &["r"]),
+            entry("b", &["r"]),
+            entry("c", &["r"]),
+            entry("r", &[]),
+        ];
+        assert_eq!(assign_lanes(&octopus), (vec![0, 0, 1, 2, 0], 3));
+
+        // Criss-cross: x and y each merge both of p and q
+        let criss_cross = [
+            entry("x", &["p", "q"]),
+            entry("y", &["q", "p"]),
+            entry("p", &["r"]),
+            entry("q", &["r"]),
+            entry("r", &[]),
+        ];
+        let (columns, width) = assign_lanes(&criss_cross);
+        assert_eq!(columns, vec![0, 2, 0, 1, 0]);
+        assert_eq!(width, 3);
+    }
+
+    #[test]
+    fn test_get_commit_graph() {
+        let fx = Fixture::new("graph");
+        let root = fx.commit("README.md", "hello\n", "Initial commit");
+        fx.git(&["checkout", "-q", "-b", "topic"]);
+        let topic = fx.commit("a.txt", "a\n", "Add a");
+        fx.git(&["checkout", "-q", "main"]);
+        fx.commit("b.txt", "b\n", "Add b");
+        let merge = fx.merge("topic", "Merge branch 'topic'");
+        fx.git(&["tag", "-a", "v1.0", "-m", "Release 1.0"]);
+
+        let graph = get_commit_graph(fx.path(), &GraphWindow::default()).unwrap();
+        assert_eq!(graph.nodes.len(), 4);
+        assert!(!graph.has_more);
+        assert_eq!(graph.width, 2);
+
+        let top = &graph.nodes[0];
+        assert_eq!(top.commit.full_sha, merge);
+        assert_eq!(top.commit.parents.len(), 2);
+        let names: Vec<&str> = top.refs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["HEAD", "main", "v1.0"]);
+
+        let topic_node = graph.nodes.iter().find(|n| n.commit.full_sha == topic).unwrap();
+        assert_eq!(topic_node.column, 1);
+        assert_eq!(topic_node.refs[0].kind, RefKind::Branch);
+        assert_eq!(graph.nodes.last().unwrap().commit.full_sha, root);
+
+        let window = GraphWindow {
+            refs: vec!["topic".to_string()],
+            max_count: Some(1),
+            ..Default::default()
+        };
+        let graph = get_commit_graph(fx.path(), &window).unwrap();
+        assert_eq!(graph.nodes.len(), 1);
+        assert!(graph.has_more);
+
+        // The next window carries on where that one stopped
+        let window = GraphWindow { skip: 1, max_count: Some(u32::MAX), ..window };
+        let graph = get_commit_graph(fx.path(), &window).unwrap();
+        let shas: Vec<&str> = graph.nodes.iter().map(|n| n.commit.full_sha.as_str()).collect();
+        assert_eq!((shas, graph.has_more), (vec![root.as_str()], false));
+
+        // A tag of a tree has no history and is left out rather than failing
+        // the walk
+        let tree = fx.git(&["rev-parse", "HEAD^{tree}"]);
+        fx.git(&["tag", "tree-tag", &tree]);
+        assert_eq!(get_commit_graph(fx.path(), &GraphWindow::default()).unwrap().nodes.len(), 4);
+        let window = GraphWindow { refs: vec!["tree-tag".to_string(), "topic".to_string()], ..Default::default() };
+        assert_eq!(get_commit_graph(fx.path(), &window).unwrap().nodes.len(), 2);
+    }
+}
@@ -0,0 +1,859 @@
+use git2::{
+    Delta, DiffFindOptions, DiffFormat, DiffOptions, ErrorCode, ObjectType, Oid, Patch, Repository, Sort, TreeWalkMode,
+    TreeWalkResult,
+};
+use std::path::Path;
+
+use super::backend::{
+    canonical, count_lines, strip_tag_signature, BranchSummary, ChangedFile, Commit, ConflictKind, DiffRange, FileChange,
+    GitBackend, GitDirs, LogQuery, MergeConflict, MergeFilter, RefEntry, RepoKind, Signature, SignatureStatus, StashEntry,
+    SubmoduleEntry, TagEntry, WorkingChanges, WorktreeEntry,
+};
+use super::cli::{self, GitError};
+use super::conventional::parse_header;
+use super::trailers::parse_trailers;
+
+/// In-process backend built on libgit2. No process is spawned per query,
+/// which matters for repos with hundreds of branches.
+pub struct Libgit2Backend;
+
+fn open(repo: &Path) -> Result<Repository, GitError> {
+    Repository::discover(repo).map_err(|_| GitError::NotARepo(repo.display().to_string()))
+}
+
+fn to_git_error(e: git2::Error) -> GitError {
+    GitError::CommandFailed(e.message().to_string())
+}
+
+/// Errors that mean "no such revision" rather than a real failure.
+fn is_missing(e: &git2::Error) -> bool {
+    matches!(e.code(), ErrorCode::NotFound | ErrorCode::InvalidSpec | ErrorCode::Ambiguous | ErrorCode::Peel)
+}
+
+fn commit_oid(repo: &Repository, rev: &str) -> Result<Oid, GitError> {
+    repo.revparse_single(rev)
+        .and_then(|obj| obj.peel_to_commit())
+        .map(|commit| commit.id())
+        .map_err(to_git_error)
+}
+
+/// Format a libgit2 timestamp like git's `%aI` (strict ISO 8601 with offset).
+fn format_time(time: git2::Time) -> String {
+    let offset = chrono::FixedOffset::east_opt(time.offset_minutes() * 60)
+        .unwrap_or_else(|| chrono::FixedOffset::east_opt(0).unwrap());
+    chrono::DateTime::from_timestamp(time.seconds(), 0)
+        .map(|dt| {
+            dt.with_timezone(&offset)
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+        })
+        .unwrap_or_default()
+}
+
+/// Resolve a `DiffRange` into the two commits to compare.
+fn range_oids(repo: &Repository, range: DiffRange) -> Result<(Oid, Oid), GitError> {
+    match range {
+        DiffRange::Between(from, to) => Ok((commit_oid(repo, from)?, commit_oid(repo, to)?)),
+        DiffRange::Since(base, head) => {
+            let head = commit_oid(repo, head)?;
+            let base = commit_oid(repo, base)?;
+            let fork = repo.merge_base(base, head).map_err(to_git_error)?;
+            Ok((fork, head))
+        }
+    }
+}
+
+fn tree_diff<'r>(repo: &'r Repository, range: DiffRange) -> Result<git2::Diff<'r>, GitError> {
+    let (from, to) = range_oids(repo, range)?;
+    let old_tree = repo.find_commit(from).and_then(|c| c.tree()).map_err(to_git_error)?;
+    let new_tree = repo.find_commit(to).and_then(|c| c.tree()).map_err(to_git_error)?;
+    tree_patch(repo, Some(&old_tree), &new_tree)
+}
+
+/// Diff two trees the way `git log -p` / `git diff` do: 3 lines of context
+/// and, since git detects renames by default (diff.renames), renames.
+fn tree_patch<'r>(repo: &'r Repository, old: Option<&git2::Tree>, new: &git2::Tree) -> Result<git2::Diff<'r>, GitError> {
+    let mut opts = DiffOptions::new();
+    opts.context_lines(3);
+    let mut diff = repo
+        .diff_tree_to_tree(old, Some(new), Some(&mut opts))
+        .map_err(to_git_error)?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+        .map_err(to_git_error)?;
+    Ok(diff)
+}
+
+/// The changed files in a diff with their line counts, sorted by path.
+/// Untracked files and unmerged paths are left out.
+fn changed_files(diff: &git2::Diff) -> Result<Vec<ChangedFile>, GitError> {
+    let mut files = Vec::new();
+    for (i, delta) in diff.deltas().enumerate() {
+        let change = match delta.status() {
+            Delta::Added => FileChange::Added,
+            Delta::Modified => FileChange::Modified,
+            Delta::Deleted => FileChange::Deleted,
+            Delta::Renamed => FileChange::Renamed,
+            Delta::Copied => FileChange::Copied,
+            Delta::Typechange => FileChange::TypeChanged,
+            _ => continue,
+        };
+        let path = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().into_owned());
+        let (additions, deletions) = match Patch::from_diff(diff, i).map_err(to_git_error)? {
+            Some(patch) if !patch.delta().flags().is_binary() => {
+                let (_, additions, deletions) = patch.line_stats().map_err(to_git_error)?;
+                (Some(additions as u32), Some(deletions as u32))
+            }
+            _ => (None, None),
+        };
+        files.push(ChangedFile {
+            path: path(delta.new_file()).or_else(|| path(delta.old_file())).unwrap_or_default(),
+            old_path: matches!(change, FileChange::Renamed | FileChange::Copied)
+                .then(|| path(delta.old_file()))
+                .flatten(),
+            change,
+            additions,
+            deletions,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+impl GitBackend for Libgit2Backend {
+    fn resolve(&self, repo: &Path, rev: &str) -> Result<Option<String>, GitError> {
+        let repo = open(repo)?;
+        let resolved = match repo.revparse_single(rev) {
+            Ok(obj) => Ok(Some(obj.id().to_string())),
+            Err(e) if is_missing(&e) => Ok(None),
+            Err(e) => Err(to_git_error(e)),
+        };
+        resolved
+    }
+
+    fn symbolic_ref(&self, repo: &Path, name: &str) -> Result<Option<String>, GitError> {
+        let repo = open(repo)?;
+        let target = match repo.find_reference(name) {
+            Ok(reference) => Ok(reference.symbolic_target().map(str::to_string)),
+            Err(e) if is_missing(&e) => Ok(None),
+            Err(e) => Err(to_git_error(e)),
+        };
+        target
+    }
+
+    fn toplevel(&self, repo: &Path) -> Result<String, GitError> {
+        let repo = open(repo)?;
+        let workdir = repo
+            .workdir()
+            .ok_or_else(|| GitError::CommandFailed("this operation must be run in a work tree".into()))?;
+        let path = workdir.to_string_lossy();
+        Ok(path.trim_end_matches('/').to_string())
+    }
+
+    fn git_dirs(&self, repo: &Path) -> Result<GitDirs, GitError> {
+        let repo = open(repo)?;
+        Ok(GitDirs {
+            git_dir: repo.path().to_path_buf(),
+            common_dir: repo.commondir().to_path_buf(),
+        })
+    }
+
+    fn repo_kind(&self, dir: &Path) -> Result<Option<RepoKind>, GitError> {
+        let flags = git2::RepositoryOpenFlags::NO_SEARCH;
+        let Ok(repo) = Repository::open_ext(dir, flags, std::iter::empty::<&std::ffi::OsStr>()) else {
+            return Ok(None);
+        };
+        let root = canonical(dir);
+        if repo.is_bare() {
+            return Ok((canonical(repo.path()) == root).then_some(RepoKind::Bare));
+        }
+        // Opening a `.git` directory directly gives its parent's work tree
+        if repo.workdir().map(canonical) != Some(root.clone()) {
+            return Ok(None);
+        }
+        if repo.is_worktree() {
+            return Ok(Some(RepoKind::Worktree));
+        }
+
+        // Like git, a submodule is a work tree the repository around it
+        // records as a gitlink in its index
+        let superproject = dir.parent().and_then(|parent| Repository::discover(parent).ok());
+        let pinned = superproject.is_some_and(|superproject| {
+            let Some(workdir) = superproject.workdir().map(canonical) else {
+                return false;
+            };
+            let (Ok(path), Ok(index)) = (root.strip_prefix(&workdir), superproject.index()) else {
+                return false;
+            };
+            index.get_path(path, 0).is_some_and(|entry| entry.mode == 0o160000)
+        });
+        Ok(Some(if pinned { RepoKind::Submodule } else { RepoKind::Normal }))
+    }
+
+    fn list_refs(&self, repo: &Path, prefix: &str) -> Result<Vec<RefEntry>, GitError> {
+        let repo = open(repo)?;
+        let mut refs = Vec::new();
+
+        for reference in repo.references().map_err(to_git_error)? {
+            let reference = reference.map_err(to_git_error)?;
+            let Some(name) = reference.name() else { continue };
+            if !name.starts_with(prefix) {
+                continue;
+            }
+            let Ok(target) = reference.peel(ObjectType::Commit) else { continue };
+            let oid = target.id();
+            refs.push(RefEntry {
+                name: name.to_string(),
+                sha: oid.to_string(),
+            });
+        }
+
+        // for-each-ref sorts by refname
+        refs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(refs)
+    }
+
+    fn branch_summaries(&self, repo_path: &Path, prefixes: &[&str], base: &str) -> Result<Vec<BranchSummary>, GitError> {
+        let mut refs = Vec::new();
+        for prefix in prefixes {
+            refs.extend(self.list_refs(repo_path, prefix)?);
+        }
+        let repo = open(repo_path)?;
+        let base = commit_oid(&repo, base)?;
+
+        let mut summaries = Vec::with_capacity(refs.len());
+        for r in refs {
+            let Ok(commit) = Oid::from_str(&r.sha).and_then(|oid| repo.find_commit(oid)) else {
+                continue;
+            };
+            let (ahead, behind) = repo.graph_ahead_behind(commit.id(), base).map_err(to_git_error)?;
+            // Criss-cross histories have several merge bases; take the newest
+            // by committer date, as `CliBackend` does
+            let fork = match repo.merge_bases(base, commit.id()) {
+                Ok(oids) => {
+                    let mut forks = Vec::new();
+                    for oid in oids.iter() {
+                        forks.push(repo.find_commit(*oid).map_err(to_git_error)?);
+                    }
+                    forks.into_iter().max_by_key(|c| (c.time().seconds(), c.id().to_string()))
+                }
+                Err(e) if e.code() == ErrorCode::NotFound => None,
+                Err(e) => return Err(to_git_error(e)),
+            };
+
+            // Only local branches have an upstream; a configured but deleted
+            // upstream reads as none
+            let upstream = repo
+                .find_reference(&r.name)
+                .ok()
+                .filter(|reference| reference.is_branch())
+                .and_then(|reference| git2::Branch::wrap(reference).upstream().ok())
+                .and_then(|up| Some((up.get().name()?.to_string(), up.get().target()?)));
+            let upstream_counts = match &upstream {
+                Some((_, oid)) => Some(repo.graph_ahead_behind(commit.id(), *oid).map_err(to_git_error)?),
+                None => None,
+            };
+
+            let author = commit.author();
+            summaries.push(BranchSummary {
+                refname: r.name,
+                head_sha: r.sha,
+                author: author.name().unwrap_or_default().to_string(),
+                date: format_time(author.when()),
+                ahead: ahead as i32,
+                behind: behind as i32,
+                fork_sha: fork.as_ref().map(|c| c.id().to_string()),
+                fork_date: fork.as_ref().map(|c| format_time(c.author().when())),
+                upstream: upstream.map(|(name, _)| name),
+                upstream_counts: upstream_counts.map(|(a, b)| (a as i32, b as i32)),
+            });
+        }
+
+        Ok(summaries)
+    }
+
+    fn tags(&self, repo: &Path) -> Result<Vec<TagEntry>, GitError> {
+        let repo = open(repo)?;
+        let mut tags = Vec::new();
+
+        for reference in repo.references_glob("refs/tags/*").map_err(to_git_error)? {
+            let reference = reference.map_err(to_git_error)?;
+            let (Some(name), Some(oid)) = (reference.shorthand(), reference.target()) else {
+                continue;
+            };
+            let Ok(object) = repo.find_object(oid, None) else { continue };
+            let entry = match object.kind() {
+                Some(ObjectType::Commit) => {
+                    let commit = object.peel_to_commit().map_err(to_git_error)?;
+                    let date = format_time(commit.committer().when());
+                    TagEntry {
+                        name: name.to_string(),
+                        target_sha: oid.to_string(),
+                        annotated: false,
+                        tagger_name: None,
+                        tagger_email: None,
+                        date,
+                        message: None,
+                    }
+                }
+                Some(ObjectType::Tag) => {
+                    let tag = object.peel_to_tag().map_err(to_git_error)?;
+                    if tag.target_type() != Some(ObjectType::Commit) {
+                        continue;
+                    }
+                    let tagger = tag.tagger();
+                    let date = match &tagger {
+                        Some(tagger) => format_time(tagger.when()),
+                        None => format_time(
+                            repo.find_commit(tag.target_id()).map_err(to_git_error)?.committer().when(),
+                        ),
+                    };
+                    TagEntry {
+                        name: name.to_string(),
+                        target_sha: tag.target_id().to_string(),
+                        annotated: true,
+                        tagger_name: tagger.as_ref().and_then(|t| t.name().map(str::to_string)),
+                        tagger_email: tagger.as_ref().and_then(|t| t.email().map(str::to_string)),
+                        date,
+                        message: Some(strip_tag_signature(tag.message().unwrap_or_default())),
+                    }
+                }
+                _ => continue,
+            };
+            tags.push(entry);
+        }
+
+        // for-each-ref sorts by refname
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tags)
+    }
+
+    fn worktrees(&self, repo: &Path) -> Result<Vec<WorktreeEntry>, GitError> {
+        let repo = open(repo)?;
+        // Linked worktrees open as their own repository; start from the main one
+        let repo = if repo.is_worktree() {
+            Repository::open(repo.commondir()).map_err(to_git_error)?
+        } else {
+            repo
+        };
+
+        let entry = |repo: &Repository, path: &Path| {
+            let head = repo.head().ok();
+            // git reports resolved paths (e.g. /private/tmp rather than /tmp)
+            let path = canonical(path);
+            WorktreeEntry {
+                path: path.to_string_lossy().trim_end_matches('/').to_string(),
+                head_sha: head.as_ref().and_then(|h| h.target()).map(|oid| oid.to_string()),
+                branch: head.as_ref().filter(|h| h.is_branch()).and_then(|h| h.name()).map(str::to_string),
+                bare: repo.is_bare(),
+            }
+        };
+
+        let main_path = repo.workdir().unwrap_or_else(|| repo.path());
+        let mut worktrees = vec![entry(&repo, main_path)];
+        if worktrees[0].bare {
+            worktrees[0].head_sha = None;
+            worktrees[0].branch = None;
+        }
+
+        let mut linked = Vec::new();
+        for name in repo.worktrees().map_err(to_git_error)?.iter().flatten() {
+            let worktree = repo.find_worktree(name).map_err(to_git_error)?;
+            if worktree.validate().is_err() {
+                continue;
+            }
+            let opened = Repository::open_from_worktree(&worktree).map_err(to_git_error)?;
+            linked.push(entry(&opened, worktree.path()));
+        }
+        linked.sort_by(|a, b| a.path.cmp(&b.path));
+        worktrees.extend(linked);
+        Ok(worktrees)
+    }
+
+    fn is_dirty(&self, worktree: &Path) -> Result<bool, GitError> {
+        let repo = open(worktree)?;
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(true).include_ignored(false);
+        let statuses = repo.statuses(Some(&mut options)).map_err(to_git_error)?;
+        Ok(!statuses.is_empty())
+    }
+
+    fn submodules(&self, repo: &Path) -> Result<Vec<SubmoduleEntry>, GitError> {
+        let repo = open(repo)?;
+        let mut submodules: Vec<SubmoduleEntry> = repo
+            .submodules()
+            .map_err(to_git_error)?
+            .iter()
+            .map(|submodule| SubmoduleEntry {
+                name: submodule.name().unwrap_or_default().to_string(),
+                path: submodule.path().to_string_lossy().into_owned(),
+                url: submodule.url().map(str::to_string),
+                branch: submodule.branch().map(str::to_string),
+                pinned_sha: submodule.head_id().map(|oid| oid.to_string()),
+            })
+            .collect();
+        submodules.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(submodules)
+    }
+
+    fn working_changes(&self, worktree: &Path) -> Result<WorkingChanges, GitError> {
+        let repo = open(worktree)?;
+        let Some(root) = repo.workdir().map(Path::to_path_buf) else {
+            return Err(GitError::CommandFailed("this operation must be run in a work tree".to_string()));
+        };
+        let index = repo.index().map_err(to_git_error)?;
+
+        let mut conflicted = Vec::new();
+        for conflict in index.conflicts().map_err(to_git_error)? {
+            let conflict = conflict.map_err(to_git_error)?;
+            let Some(entry) = conflict.our.as_ref().or(conflict.their.as_ref()).or(conflict.ancestor.as_ref()) else {
+                continue;
+            };
+            conflicted.push(MergeConflict {
+                path: String::from_utf8_lossy(&entry.path).into_owned(),
+                kind: ConflictKind::from_stages(
+                    conflict.ancestor.is_some(),
+                    conflict.our.is_some(),
+                    conflict.their.is_some(),
+                ),
+            });
+        }
+        conflicted.sort_by(|a, b| a.path.cmp(&b.path));
+        let merged = |files: Vec<ChangedFile>| -> Vec<ChangedFile> {
+            files.into_iter().filter(|f| !conflicted.iter().any(|c| c.path == f.path)).collect()
+        };
+
+        // Unborn HEAD: everything in the index is staged
+        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let mut staged = repo
+            .diff_tree_to_index(head_tree.as_ref(), Some(&index), None)
+            .map_err(to_git_error)?;
+        staged.find_similar(Some(DiffFindOptions::new().renames(true)))
+            .map_err(to_git_error)?;
+
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+        let unstaged = repo
+            .diff_index_to_workdir(Some(&index), Some(&mut opts))
+            .map_err(to_git_error)?;
+
+        let mut untracked = Vec::new();
+        for delta in unstaged.deltas().filter(|d| d.status() == Delta::Untracked) {
+            let Some(path) = delta.new_file().path().map(|p| p.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            let additions = count_lines(&root.join(&path));
+            untracked.push(ChangedFile {
+                path,
+                old_path: None,
+                change: FileChange::Added,
+                additions,
+                deletions: additions.map(|_| 0),
+            });
+        }
+        untracked.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(WorkingChanges {
+            staged: merged(changed_files(&staged)?),
+            unstaged: merged(changed_files(&unstaged)?),
+            untracked,
+            conflicted,
+        })
+    }
+
+    fn stashes(&self, repo: &Path) -> Result<Vec<StashEntry>, GitError> {
+        let repo = open(repo)?;
+        let reflog = repo.reflog("refs/stash").map_err(to_git_error)?;
+        let mut stashes = Vec::new();
+        for (index, entry) in reflog.iter().enumerate() {
+            let commit = repo.find_commit(entry.id_new()).map_err(to_git_error)?;
+            stashes.push(StashEntry {
+                index,
+                sha: entry.id_new().to_string(),
+                message: entry.message().unwrap_or_default().to_string(),
+                date: format_time(commit.committer().when()),
+            });
+        }
+        Ok(stashes)
+    }
+
+    fn list_remotes(&self, repo: &Path) -> Result<Vec<String>, GitError> {
+        let repo = open(repo)?;
+        let remotes = repo.remotes().map_err(to_git_error)?;
+        let mut remotes: Vec<String> = remotes.iter().flatten().map(str::to_string).collect();
+        remotes.sort();
+        Ok(remotes)
+    }
+
+    fn ahead_behind(&self, repo: &Path, ours: &str, theirs: &str) -> Result<(usize, usize), GitError> {
+        let repo = open(repo)?;
+        let (ours, theirs) = (commit_oid(&repo, ours)?, commit_oid(&repo, theirs)?);
+        repo.graph_ahead_behind(ours, theirs).map_err(to_git_error)
+    }
+
+    fn merge_base(&self, repo: &Path, revs: &[&str]) -> Result<Option<String>, GitError> {
+        let repo = open(repo)?;
+        let oids = revs.iter().map(|rev| commit_oid(&repo, rev)).collect::<Result<Vec<_>, _>>()?;
+        // libgit2 wants at least two commits; one is its own merge base
+        let base = match oids[..] {
+            [] => return Ok(None),
+            [only] => Ok(only),
+            _ => repo.merge_base_octopus(&oids),
+        };
+        match base {
+            Ok(oid) => Ok(Some(oid.to_string())),
+            Err(e) if is_missing(&e) => Ok(None),
+            Err(e) => Err(to_git_error(e)),
+        }
+    }
+
+    fn log(&self, repo_path: &Path, query: &LogQuery) -> Result<Vec<Commit>, GitError> {
+        let repo = open(repo_path)?;
+        let mut walk = repo.revwalk().map_err(to_git_error)?;
+        // git's walk never emits a parent before its child, even when their
+        // dates tie (e.g. a rebased series), so that's needed either way
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME).map_err(to_git_error)?;
+        if query.first_parent {
+            walk.simplify_first_parent().map_err(to_git_error)?;
+        }
+        for rev in query.include {
+            walk.push(commit_oid(&repo, rev)?).map_err(to_git_error)?;
+        }
+        if let Some(exclude) = query.exclude {
+            walk.hide(commit_oid(&repo, exclude)?).map_err(to_git_error)?;
+        }
+
+        let mut entries = Vec::new();
+        let mut skipped = 0;
+        for oid in walk {
+            if query.max_count.is_some_and(|max| entries.len() >= max as usize) {
+                break;
+            }
+            let commit = repo
+                .find_commit(oid.map_err(to_git_error)?)
+                .map_err(to_git_error)?;
+
+            let is_merge = commit.parent_count() > 1;
+            let keep = match query.merges {
+                MergeFilter::All => true,
+                MergeFilter::Exclude => !is_merge,
+            };
+            if !keep || query.since.is_some_and(|since| commit.time().seconds() < since) {
+                continue;
+            }
+            if skipped < query.skip {
+                skipped += 1;
+                continue;
+            }
+
+            let short_sha = commit
+                .as_object()
+                .short_id()
+                .map_err(to_git_error)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            let author = commit.author();
+            let committer = commit.committer();
+
+            let subject = commit.summary().unwrap_or_default();
+            let body = commit.body().unwrap_or_default().trim_end();
+
+            entries.push(Commit {
+                full_sha: commit.id().to_string(),
+                short_sha,
+                subject: subject.to_string(),
+                body: body.to_string(),
+                conventional: parse_header(subject),
+                trailers: parse_trailers(body),
+                author_name: author.name().unwrap_or_default().to_string(),
+                author_email: author.email().unwrap_or_default().to_string(),
+                author_date: format_time(author.when()),
+                committer_name: committer.name().unwrap_or_default().to_string(),
+                committer_email: committer.email().unwrap_or_default().to_string(),
+                committer_date: format_time(committer.when()),
+                parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+                tree: commit.tree_id().to_string(),
+                signature: None,
+            });
+        }
+
+        if query.verify_signatures {
+            // libgit2 can read signatures but not check them: hand the signed
+            // commits to git and mark the rest unsigned without asking
+            let signed: Vec<String> = entries
+                .iter()
+                .filter(|c| Oid::from_str(&c.full_sha).is_ok_and(|oid| repo.extract_signature(&oid, None).is_ok()))
+                .map(|c| c.full_sha.clone())
+                .collect();
+            let mut verified = cli::verify_signatures(repo_path, &signed)?;
+            for entry in &mut entries {
+                entry.signature = Some(verified.remove(&entry.full_sha).unwrap_or(Signature {
+                    status: SignatureStatus::Unsigned,
+                    signer: None,
+                    key: None,
+                }));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn diff(&self, repo: &Path, range: DiffRange) -> Result<String, GitError> {
+        let repo = open(repo)?;
+        let diff = tree_diff(&repo, range)?;
+
+        let mut patch = Vec::new();
+        diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                patch.push(line.origin() as u8);
+            }
+            patch.extend_from_slice(line.content());
+            true
+        })
+        .map_err(to_git_error)?;
+
+        String::from_utf8(patch).map_err(|_| GitError::InvalidUtf8)
+    }
+
+    fn diff_names(&self, repo: &Path, range: DiffRange) -> Result<Vec<String>, GitError> {
+        let repo = open(repo)?;
+        let diff = tree_diff(&repo, range)?;
+
+        Ok(diff
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+            .map(|p| p.to_string_lossy().to_string())
+            .collect())
+    }
+
+    fn ls_tree(&self, repo: &Path, rev: &str, paths: &[&str]) -> Result<Vec<String>, GitError> {
+        let repo = open(repo)?;
+        let tree = repo
+            .revparse_single(rev)
+            .and_then(|obj| obj.peel_to_tree())
+            .map_err(to_git_error)?;
+
+        let matches = |path: &str| {
+            paths.is_empty()
+                || paths.iter().any(|p| {
+                    let p = p.trim_end_matches('/');
+                    path == p || path.strip_prefix(p).is_some_and(|rest| rest.starts_with('/'))
+                })
+        };
+
+        let mut files = Vec::new();
+        tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() != Some(ObjectType::Tree) {
+                let path = format!("{}{}", root, entry.name().unwrap_or_default());
+                if matches(&path) {
+                    files.push(path);
+                }
+            }
+            TreeWalkResult::Ok
+        })
+        .map_err(to_git_error)?;
+
+        Ok(files)
+    }
+
+    fn archive(&self, repo: &Path, rev: &str) -> Result<Vec<u8>, GitError> {
+        let repo = open(repo)?;
+        let obj = repo.revparse_single(rev).map_err(to_git_error)?;
+        let mtime = obj
+            .peel_to_commit()
+            .map(|c| c.time().seconds().max(0) as u64)
+            .unwrap_or(0);
+        let tree = obj.peel_to_tree().map_err(to_git_error)?;
+
+        // Collect first: the walk callback can't propagate blob lookup errors
+        let mut entries: Vec<(String, i32, Oid)> = Vec::new();
+        tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+            let path = format!("{}{}", root, entry.name().unwrap_or_default());
+            entries.push((path, entry.filemode(), entry.id()));
+            TreeWalkResult::Ok
+        })
+        .map_err(to_git_error)?;
+
+        let mut tar = tar::Writer::default();
+        for (path, mode, oid) in entries {
+            match mode {
+                // Trees and submodules (gitlinks) both become directories
+                0o040000 | 0o160000 => tar.directory(&path, mtime),
+                0o120000 => {
+                    let blob = repo.find_blob(oid).map_err(to_git_error)?;
+                    let target = String::from_utf8_lossy(blob.content());
+                    tar.symlink(&path, &target, mtime);
+                }
+                _ => {
+                    let blob = repo.find_blob(oid).map_err(to_git_error)?;
+                    let perm = if mode == 0o100755 { 0o755 } else { 0o644 };
+                    tar.file(&path, perm, blob.content(), mtime);
+                }
+            }
+        }
+        Ok(tar.finish())
+    }
+
+    fn patch_ids(&self, repo_path: &Path, include: &str, exclude: &str) -> Result<Vec<(String, String)>, GitError> {
+        let repo = open(repo_path)?;
+        let mut walk = repo.revwalk().map_err(to_git_error)?;
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME).map_err(to_git_error)?;
+        walk.push(commit_oid(&repo, include)?).map_err(to_git_error)?;
+        walk.hide(commit_oid(&repo, exclude)?).map_err(to_git_error)?;
+
+        let mut ids = Vec::new();
+        for oid in walk {
+            let commit = repo.find_commit(oid.map_err(to_git_error)?).map_err(to_git_error)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            let old_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree().map_err(to_git_error)?),
+                Err(_) => None,
+            };
+            let new_tree = commit.tree().map_err(to_git_error)?;
+            let diff = tree_patch(&repo, old_tree.as_ref(), &new_tree)?;
+            // Commits that change nothing have no patch id (git skips them too)
+            if diff.deltas().len() == 0 {
+                continue;
+            }
+            let patch_id = diff.patchid(None).map_err(to_git_error)?;
+            ids.push((commit.id().to_string(), patch_id.to_string()));
+        }
+        Ok(ids)
+    }
+
+    fn diff_patch_id(&self, repo_path: &Path, from: &str, to: &str) -> Result<Option<String>, GitError> {
+        let repo = open(repo_path)?;
+        let old_tree = repo.find_commit(commit_oid(&repo, from)?).and_then(|c| c.tree()).map_err(to_git_error)?;
+        let new_tree = repo.find_commit(commit_oid(&repo, to)?).and_then(|c| c.tree()).map_err(to_git_error)?;
+        let diff = tree_patch(&repo, Some(&old_tree), &new_tree)?;
+        if diff.deltas().len() == 0 {
+            return Ok(None);
+        }
+        Ok(Some(diff.patchid(None).map_err(to_git_error)?.to_string()))
+    }
+
+    fn merge_tree(&self, repo: &Path, ours: &str, theirs: &str) -> Result<Vec<MergeConflict>, GitError> {
+        let repo = open(repo)?;
+        let ours = repo.find_commit(commit_oid(&repo, ours)?).map_err(to_git_error)?;
+        let theirs = repo.find_commit(commit_oid(&repo, theirs)?).map_err(to_git_error)?;
+        let index = repo.merge_commits(&ours, &theirs, None).map_err(to_git_error)?;
+
+        let mut conflicts = Vec::new();
+        for conflict in index.conflicts().map_err(to_git_error)? {
+            let conflict = conflict.map_err(to_git_error)?;
+            let Some(entry) = conflict.our.as_ref().or(conflict.their.as_ref()).or(conflict.ancestor.as_ref()) else {
+                continue;
+            };
+            conflicts.push(MergeConflict {
+                path: String::from_utf8_lossy(&entry.path).into_owned(),
+                kind: ConflictKind::from_stages(
+                    conflict.ancestor.is_some(),
+                    conflict.our.is_some(),
+                    conflict.their.is_some(),
+                ),
+            });
+        }
+        conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(conflicts)
+    }
+}
+
+/// Minimal ustar writer, with PAX headers for paths over the ustar limits.
+mod tar {
+    const BLOCK: usize = 512;
+
+    #[derive(Default)]
+    pub struct Writer {
+        buf: Vec<u8>,
+    }
+
+    impl Writer {
+        pub fn directory(&mut self, path: &str, mtime: u64) {
+            self.entry(&format!("{path}/"), 0o755, b'5', "", &[], mtime);
+        }
+
+        pub fn symlink(&mut self, path: &str, target: &str, mtime: u64) {
+            self.entry(path, 0o777, b'2', target, &[], mtime);
+        }
+
+        pub fn file(&mut self, path: &str, mode: u32, data: &[u8], mtime: u64) {
+            self.entry(path, mode, b'0', "", data, mtime);
+        }
+
+        pub fn finish(mut self) -> Vec<u8> {
+            self.buf.extend_from_slice(&[0u8; BLOCK * 2]);
+            self.buf
+        }
+
+        fn entry(&mut self, path: &str, mode: u32, kind: u8, link: &str, data: &[u8], mtime: u64) {
+            let mut pax = String::new();
+            if path.len() > 100 {
+                pax.push_str(&pax_record("path", path));
+            }
+            if link.len() > 100 {
+                pax.push_str(&pax_record("linkpath", link));
+            }
+            if !pax.is_empty() {
+                let header = header("pax_header", 0o644, b'x', "", pax.len() as u64, mtime);
+                self.buf.extend_from_slice(&header);
+                self.data(pax.as_bytes());
+            }
+
+            let header = header(path, mode, kind, link, data.len() as u64, mtime);
+            self.buf.extend_from_slice(&header);
+            self.data(data);
+        }
+
+        fn data(&mut self, data: &[u8]) {
+            self.buf.extend_from_slice(data);
+            let pad = (BLOCK - data.len() % BLOCK) % BLOCK;
+            self.buf.extend(std::iter::repeat_n(0u8, pad));
+        }
+    }
+
+    /// One `"<len> <key>=<value>\n"` record, where `len` counts itself.
+    fn pax_record(key: &str, value: &str) -> String {
+        let body = format!(" {key}={value}\n");
+        let mut len = body.len() + 1;
+        while len.to_string().len() + body.len() != len {
+            len = len.to_string().len() + body.len();
+        }
+        format!("{len}{body}")
+    }
+
+    fn header(path: &str, mode: u32, kind: u8, link: &str, size: u64, mtime: u64) -> [u8; BLOCK] {
+        let mut h = [0u8; BLOCK];
+        put_str(&mut h[0..100], path);
+        put_octal(&mut h[100..108], mode as u64);
+        put_octal(&mut h[108..116], 0);
+        put_octal(&mut h[116..124], 0);
+        put_octal(&mut h[124..136], size);
+        put_octal(&mut h[136..148], mtime);
+        h[156] = kind;
+        put_str(&mut h[157..257], link);
+        h[257..263].copy_from_slice(b"ustar\0");
+        h[263..265].copy_from_slice(b"00");
+
+        // Checksum is computed with the checksum field itself set to spaces
+        h[148..156].fill(b' ');
+        let sum: u32 = h.iter().map(|&b| b as u32).sum();
+        put_octal(&mut h[148..155], sum as u64);
+        h
+    }
+
+    /// Copy as much of `s` as fits; longer values are carried by a PAX record.
+    fn put_str(field: &mut [u8], s: &str) {
+        let bytes = s.as_bytes();
+        let n = bytes.len().min(field.len());
+        field[..n].copy_from_slice(&bytes[..n]);
+    }
+
+    /// Zero-padded octal followed by a NUL terminator.
+    fn put_octal(field: &mut [u8], value: u64) {
+        let digits = field.len() - 1;
+        let s = format!("{value:0digits$o}");
+        field[..digits].copy_from_slice(&s.as_bytes()[s.len() - digits..]);
+        field[digits] = 0;
+    }
+}
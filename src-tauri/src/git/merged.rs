@@ -0,0 +1,337 @@
+//! Detect branches whose changes already landed in the base branch, including
+//! squash merges and cherry-picks where the branch's own commits never did.
+
+use super::backend::{backend, BranchSummary, LogQuery, MergeFilter};
+use super::branches::listed_refs;
+use super::cli::GitError;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// How a branch's changes reached the base branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum MergedBy {
+    /// A merge commit on the base brought in the branch tip
+    Merge { sha: String },
+    /// A single base commit carries the whole branch
+    Squash { sha: String },
+    /// Every branch commit has a patch-equivalent commit on the base
+    CherryPick { shas: Vec<String> },
+}
+
+/// Results keyed by `(base_sha, head_sha)`; see `conflicts::cache`.
+type MergedCache = Mutex<HashMap<(String, String), Option<MergedBy>>>;
+
+const CACHE_LIMIT: usize = 4096;
+
+fn cache() -> &'static MergedCache {
+    static CACHE: OnceLock<MergedCache> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn cached(base_sha: &str, head_sha: &str) -> Option<Option<MergedBy>> {
+    cache().lock().unwrap().get(&(base_sha.to_string(), head_sha.to_string())).cloned()
+}
+
+fn remember(base_sha: &str, head_sha: &str, result: Option<MergedBy>) {
+    let mut cache = cache().lock().unwrap();
+    if cache.len() >= CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert((base_sha.to_string(), head_sha.to_string()), result);
+}
+
+/// Work out cheaply which of `branches` are already part of `base_sha`,
+/// keyed by tip SHA: only tips the base contains are checked, for the merge
+/// commit that brought them in, all from one walk of the base. Squashes and
+/// cherry-picks need patch ids and come from `equivalent_merges`; once that
+/// has looked at a tip, its answer shows up here too.
+pub fn merged_tips<'a>(
+    repo: &Path,
+    base_sha: &str,
+    branches: impl IntoIterator<Item = &'a BranchSummary>,
+) -> Result<HashMap<String, MergedBy>, GitError> {
+    let mut found = HashMap::new();
+    let mut pending = Vec::new();
+    for branch in branches {
+        match cached(base_sha, &branch.head_sha) {
+            Some(hit) => found.extend(hit.map(|merged| (branch.head_sha.clone(), merged))),
+            None if branch.ahead == 0 => pending.push(branch.head_sha.as_str()),
+            None => {}
+        }
+    }
+    if pending.is_empty() {
+        return Ok(found);
+    }
+
+    let introduced = MergeIndex::read(repo, base_sha, &pending)?;
+    for head in pending {
+        // A tip on the first-parent line was fast-forwarded, which looks
+        // like any other branch that was created and never committed to
+        let result = introduced.by(head).filter(|&by| by != head).map(|by| MergedBy::Merge { sha: by.to_string() });
+        remember(base_sha, head, result.clone());
+        found.extend(result.map(|merged| (head.to_string(), merged)));
+    }
+    Ok(found)
+}
+
+/// Squash merges and cherry-picks of every listed branch with commits of its
+/// own, keyed by branch name. The base side (patch ids and trees of its
+/// commits since the oldest fork point) is read once and shared by all
+/// branches, so this costs a couple of git calls per branch rather than a
+/// walk of the base each.
+pub fn equivalent_merges(repo: &Path, default_branch: &str) -> Result<BTreeMap<String, MergedBy>, GitError> {
+    let git = backend();
+    let Some(base_sha) = git.resolve(repo, default_branch)? else {
+        return Ok(BTreeMap::new());
+    };
+
+    let mut found = BTreeMap::new();
+    let mut pending = Vec::new();
+    for listed in listed_refs(repo, default_branch)? {
+        let summary = listed.summary;
+        // Nothing of its own, or unrelated histories
+        let Some(fork) = summary.fork_sha.filter(|_| summary.ahead > 0) else {
+            continue;
+        };
+        match cached(&base_sha, &summary.head_sha) {
+            Some(hit) => found.extend(hit.map(|merged| (listed.name, merged))),
+            None => pending.push((listed.name, summary.head_sha, fork)),
+        }
+    }
+    if pending.is_empty() {
+        return Ok(found);
+    }
+
+    // Tip trees of every pending branch in one walk of the branch-only commits
+    let heads: Vec<&str> = pending.iter().map(|(_, head, _)| head.as_str()).collect();
+    let branch_only = LogQuery { include: &heads, exclude: Some(&base_sha), ..Default::default() };
+    let trees: HashMap<String, String> =
+        git.log(repo, &branch_only)?.into_iter().map(|c| (c.full_sha, c.tree)).collect();
+
+    let forks: Vec<&str> = pending.iter().map(|(_, _, fork)| fork.as_str()).collect();
+    let shared = match git.merge_base(repo, &forks)? {
+        Some(since) => Some(BaseIndex::read(repo, &base_sha, &since)?),
+        None => None,
+    };
+
+    for (name, head, fork) in pending {
+        let result = match &shared {
+            Some(index) => equivalent_changes(repo, index, &base_sha, &head, trees.get(&head), &fork)?,
+            // Forks with no common ancestor: fall back to the branch's own view of the base
+            None => {
+                let index = BaseIndex::read(repo, &base_sha, &head)?;
+                equivalent_changes(repo, &index, &base_sha, &head, trees.get(&head), &fork)?
+            }
+        };
+        remember(&base_sha, &head, result.clone());
+        if let Some(merged) = result {
+            found.insert(name, merged);
+        }
+    }
+    Ok(found)
+}
+
+/// The first-parent commit of the base that brought in each commit since
+/// the common ancestor of some tips: the commit itself when it's on the
+/// first-parent line, otherwise the merge that first reached it.
+struct MergeIndex {
+    introduced_by: HashMap<String, String>,
+}
+
+impl MergeIndex {
+    fn read(repo: &Path, base_sha: &str, heads: &[&str]) -> Result<MergeIndex, GitError> {
+        let git = backend();
+        let oldest = git.merge_base(repo, heads)?;
+        let query = LogQuery { include: &[base_sha], exclude: oldest.as_deref(), ..Default::default() };
+        let mut parents: HashMap<String, Vec<String>> =
+            git.log(repo, &query)?.into_iter().map(|c| (c.full_sha, c.parents)).collect();
+        // Where the walk stops, itself one of the tips when it's an ancestor
+        // of all the others
+        parents.extend(oldest.map(|sha| (sha, Vec::new())));
+
+        // First-parent line, newest first
+        let mut line = Vec::new();
+        let mut next = parents.get_key_value(base_sha);
+        while let Some((sha, commit_parents)) = next {
+            line.push(sha);
+            next = commit_parents.first().and_then(|p| parents.get_key_value(p));
+        }
+
+        // Oldest first, so each commit goes to the first line commit reaching it
+        let mut introduced_by = HashMap::new();
+        for &by in line.iter().rev() {
+            let mut stack = vec![by];
+            while let Some(sha) = stack.pop() {
+                if introduced_by.contains_key(sha) {
+                    continue;
+                }
+                let Some(commit_parents) = parents.get(sha) else { continue };
+                introduced_by.insert(sha.clone(), by.clone());
+                stack.extend(commit_parents);
+            }
+        }
+        Ok(MergeIndex { introduced_by })
+    }
+
+    fn by(&self, sha: &str) -> Option<&str> {
+        self.introduced_by.get(sha).map(String::as_str)
+    }
+}
+
+/// The base commits a branch could have landed as, by patch id and by tree.
+struct BaseIndex {
+    by_patch: HashMap<String, String>,
+    by_tree: HashMap<String, String>,
+}
+
+impl BaseIndex {
+    /// Non-merge commits of `base_sha` that `since` doesn't have.
+    fn read(repo: &Path, base_sha: &str, since: &str) -> Result<BaseIndex, GitError> {
+        let git = backend();
+        let by_patch = git.patch_ids(repo, base_sha, since)?.into_iter().map(|(sha, id)| (id, sha)).collect();
+        let query = LogQuery {
+            include: &[base_sha],
+            exclude: Some(since),
+            merges: MergeFilter::Exclude,
+            ..Default::default()
+        };
+        // Newest first; the newest commit with a given tree wins
+        let by_tree = git.log(repo, &query)?.into_iter().rev().map(|c| (c.tree, c.full_sha)).collect();
+        Ok(BaseIndex { by_patch, by_tree })
+    }
+}
+
+/// For a tip with commits of its own: look on the base for a patch
+/// equivalent of every branch commit (cherry-picks or a rebase), or for a
+/// single commit with the branch's exact tree or combined patch (a squash).
+fn equivalent_changes(
+    repo: &Path,
+    base: &BaseIndex,
+    base_sha: &str,
+    head: &str,
+    head_tree: Option<&String>,
+    fork: &str,
+) -> Result<Option<MergedBy>, GitError> {
+    let git = backend();
+    if base.by_patch.is_empty() && base.by_tree.is_empty() {
+        return Ok(None);
+    }
+
+    let branch_ids = git.patch_ids(repo, head, base_sha)?;
+    let picked: Option<Vec<String>> = branch_ids.iter().map(|(_, id)| base.by_patch.get(id).cloned()).collect();
+    if let Some(shas) = picked.filter(|shas| !shas.is_empty()) {
+        // A single-commit branch squashed is the same thing as a cherry-pick
+        return Ok(Some(match <[String; 1]>::try_from(shas) {
+            Ok([sha]) => MergedBy::Squash { sha },
+            Err(shas) => MergedBy::CherryPick { shas },
+        }));
+    }
+
+    // Squashed while up to date with the base (e.g. after merging the base
+    // into the branch): a base commit with the branch's exact tree
+    if let Some(sha) = head_tree.and_then(|tree| base.by_tree.get(tree)) {
+        return Ok(Some(MergedBy::Squash { sha: sha.clone() }));
+    }
+
+    // Squashed after the base moved on: a base commit with the same combined patch
+    if let Some(combined) = git.diff_patch_id(repo, fork, head)? {
+        if let Some(sha) = base.by_patch.get(&combined) {
+            return Ok(Some(MergedBy::Squash { sha: sha.clone() }));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::backend::{backend_for, BackendKind};
+    use crate::git::branches::list_branches;
+    use crate::git::fixture::Fixture;
+
+    #[test]
+    fn test_merged_by_each_strategy() {
+        let fx = Fixture::new("merged-by");
+        fx.commit("README.md", "hello\n", "Initial commit");
+
+        // Merged after main moved on
+        fx.git(&["checkout", "-q", "-b", "late"]);
+        fx.commit("late.txt", "l\n", "Add late");
+        fx.git(&["checkout", "-q", "main"]);
+        fx.commit("other.txt", "o\n", "Move main on");
+        let late = fx.merge("late", "Merge branch 'late'");
+
+        // Merged with a merge commit
+        fx.git(&["checkout", "-q", "-b", "merged"]);
+        fx.commit("merged.txt", "m\n", "Add merged");
+        fx.git(&["checkout", "-q", "main"]);
+        let merge = fx.merge("merged", "Merge branch 'merged'");
+
+        // Squashed after main moved on
+        fx.git(&["checkout", "-q", "-b", "squashed"]);
+        fx.commit("s1.txt", "1\n", "Squash part 1");
+        fx.commit("s2.txt", "2\n", "Squash part 2");
+        fx.git(&["checkout", "-q", "main"]);
+        fx.commit("unrelated.txt", "u\n", "Unrelated change");
+        fx.git(&["merge", "-q", "--squash", "squashed"]);
+        fx.git(&["commit", "-q", "-m", "Squashed branch (#5)"]);
+        let squash = fx.git(&["rev-parse", "HEAD"]);
+
+        // Cherry-picked commit by commit
+        fx.git(&["checkout", "-q", "-b", "picked"]);
+        let p1 = fx.commit("p1.txt", "1\n", "Pick 1");
+        let p2 = fx.commit("p2.txt", "2\n", "Pick 2");
+        fx.git(&["checkout", "-q", "main"]);
+        fx.git(&["cherry-pick", &p1, &p2]);
+        let picks = fx.git(&["rev-list", "-2", "HEAD"]);
+        let picks: Vec<String> = picks.lines().map(str::to_string).collect();
+
+        // Still has unlanded work, and a fresh branch with no commits
+        fx.git(&["checkout", "-q", "-b", "open"]);
+        fx.commit("open.txt", "o\n", "Work in progress");
+        fx.git(&["checkout", "-q", "-b", "empty", "main"]);
+        fx.git(&["checkout", "-q", "main"]);
+
+        // Listing branches only does the cheap merge-commit check, in one walk
+        let branches = list_branches(fx.path(), "main").unwrap();
+        let merged: HashMap<&str, Option<&MergedBy>> =
+            branches.iter().map(|b| (b.name.as_str(), b.merged_by.as_ref())).collect();
+
+        assert_eq!(merged["merged"], Some(&MergedBy::Merge { sha: merge.clone() }));
+        assert_eq!(merged["late"], Some(&MergedBy::Merge { sha: late }));
+        assert_eq!(merged["squashed"], None);
+        assert_eq!(merged["picked"], None);
+        assert_eq!(merged["open"], None);
+        assert_eq!(merged["empty"], None);
+
+        let squash = MergedBy::Squash { sha: squash };
+        let picks = MergedBy::CherryPick { shas: picks };
+        let equivalent = equivalent_merges(fx.path(), "main").unwrap();
+        assert_eq!(
+            equivalent,
+            BTreeMap::from([("picked".to_string(), picks.clone()), ("squashed".to_string(), squash.clone())])
+        );
+
+        // ...and picks up what the on-demand detection found
+        let branches = list_branches(fx.path(), "main").unwrap();
+        let merged: HashMap<&str, Option<&MergedBy>> =
+            branches.iter().map(|b| (b.name.as_str(), b.merged_by.as_ref())).collect();
+        assert_eq!(merged["merged"], Some(&MergedBy::Merge { sha: merge }));
+        assert_eq!(merged["squashed"], Some(&squash));
+        assert_eq!(merged["picked"], Some(&picks));
+        assert_eq!(merged["open"], None);
+
+        // Patch ids have to line up within a backend, and git and libgit2
+        // should agree on them too
+        let [cli, lib] = [backend_for(BackendKind::Cli), backend_for(BackendKind::Libgit2)];
+        assert_eq!(cli.patch_ids(fx.path(), "picked", "main~3").unwrap(), lib.patch_ids(fx.path(), "picked", "main~3").unwrap());
+        assert_eq!(
+            cli.diff_patch_id(fx.path(), "main~4", "squashed").unwrap(),
+            lib.diff_patch_id(fx.path(), "main~4", "squashed").unwrap()
+        );
+    }
+}
@@ -1,7 +1,45 @@
+pub mod backend;
 pub mod cli;
 mod branches;
+mod cleanup;
 mod commits;
+mod config;
+mod conflicts;
+mod conventional;
+mod discovery;
+mod graph;
+mod libgit2;
+mod merged;
+mod release_notes;
+mod signatures;
+mod staleness;
+mod stash;
+mod status;
+mod submodules;
+mod tags;
+mod trailers;
+mod worktrees;
 
+#[cfg(test)]
+mod fixture;
+
+pub use backend::{backend, backend_kind, set_backend, BackendKind, Commit, DiffRange, LogQuery, MergeFilter, RepoKind};
+pub use branches::{get_default_branch, get_repo_info, list_branches, list_remote_branches, list_remotes, Branch};
+pub use cleanup::{
+    delete_local_branches, delete_remote_branches, prune_remote, undo_deletion, undo_journal, CleanupReport, Deletion,
+};
 pub use cli::GitError;
-pub use branches::{Branch, get_default_branch, get_repo_info, list_branches};
-pub use commits::{DirectCommit, MergeNode, get_direct_commits, get_merge_commits};
+pub use commits::{get_direct_commits, get_merge_nodes, KnownPr, MergeNode};
+pub use config::app_settings;
+pub use conflicts::{conflict_matrix, predict_merge, predict_merges, ConflictMatrix, MergePrediction};
+pub use discovery::repo_kind;
+pub use graph::{get_commit_graph, CommitGraph, GraphWindow};
+pub use merged::{equivalent_merges, MergedBy};
+pub use release_notes::{release_notes, GroupBy, ReleaseNotes};
+pub use signatures::{branch_signatures, BranchSignatures};
+pub use staleness::StalenessRules;
+pub use stash::{list_stashes, stash_diff, Stash};
+pub use status::{repo_status, RepoStatus};
+pub use submodules::{list_submodules, open_submodule, Submodule};
+pub use tags::{first_releases, list_tags, Tag};
+pub use worktrees::{list_worktrees, worktree_for_branch, Worktree};
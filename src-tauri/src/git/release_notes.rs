@@ -0,0 +1,293 @@
+//! Release notes between two refs: the PRs that landed in `from..to`, grouped
+//! by Conventional Commit type or by GitHub label, as JSON and Markdown.
+
+use super::backend::{backend, LogQuery, MergeFilter};
+use super::cli::GitError;
+use super::commits::{classify, KnownPr, MergeStrategy};
+use super::conventional::parse_header;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// How to split the notes into sections.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// Conventional Commit type of the PR title (`feat`, `fix`, ...)
+    #[default]
+    Type,
+    /// First GitHub label of the PR
+    Label,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNote {
+    pub pr_number: Option<i32>,
+    /// PR title without its Conventional Commit prefix
+    pub title: String,
+    /// Conventional Commit type and scope, when the title has them
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub labels: Vec<String>,
+    /// GitHub login of the PR author, when the PR is known
+    pub author_login: Option<String>,
+    /// Authors and co-authors of the commits the PR brought in, oldest first
+    pub authors: Vec<String>,
+    pub full_sha: String,
+    pub strategy: MergeStrategy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseSection {
+    pub title: String,
+    pub notes: Vec<ReleaseNote>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNotes {
+    pub from: String,
+    pub to: String,
+    pub sections: Vec<ReleaseSection>,
+    pub markdown: String,
+}
+
+/// Section titles for the common Conventional Commit types, in the order
+/// they're listed.
+const TYPE_SECTIONS: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("build", "Build"),
+    ("ci", "CI"),
+    ("style", "Style"),
+    ("chore", "Chores"),
+    ("revert", "Reverts"),
+];
+
+const OTHER_SECTION: &str = "Other Changes";
+
+/// Build release notes for the PRs on `to`'s first-parent history that are
+/// not in `from`. `known` PRs (from GitHub) supply titles, labels and authors.
+pub fn release_notes(
+    repo: &Path,
+    from: &str,
+    to: &str,
+    known: &[KnownPr],
+    group_by: GroupBy,
+) -> Result<ReleaseNotes, GitError> {
+    let git = backend();
+    let known: HashMap<&str, &KnownPr> = known.iter().map(|pr| (pr.merge_commit_sha.as_str(), pr)).collect();
+    let query = LogQuery {
+        include: &[to],
+        exclude: Some(from),
+        first_parent: true,
+        ..Default::default()
+    };
+    let commits = git.log(repo, &query)?;
+
+    let mut notes = Vec::new();
+    for (node, last) in classify(&commits, &known) {
+        let first = last + 1 - node.commit_count;
+        let commit = &commits[first];
+        let pr = known.get(node.full_sha.as_str());
+
+        // Who wrote the change: the series itself, or what the merge brought in
+        let mut authors: Vec<String> = Vec::new();
+        let merged_in = match (node.strategy, commit.parents.as_slice()) {
+            (MergeStrategy::Merge, [first_parent, second, ..]) => git.log(
+                repo,
+                &LogQuery {
+                    include: &[second],
+                    exclude: Some(first_parent),
+                    merges: MergeFilter::Exclude,
+                    ..Default::default()
+                },
+            )?,
+            _ => commits[first..=last].to_vec(),
+        };
+        for c in merged_in.iter().rev() {
+            let co_authors = c.trailers.co_authors.iter().map(|p| &p.name);
+            for name in std::iter::once(&c.author_name).chain(co_authors) {
+                if !authors.contains(name) {
+                    authors.push(name.clone());
+                }
+            }
+        }
+
+        // GitHub puts the PR title in the body of its merge commits
+        let title = match (pr, node.strategy) {
+            (Some(pr), _) => pr.title.clone(),
+            (None, MergeStrategy::Merge) => commit
+                .body
+                .lines()
+                .next()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_string)
+                .or(node.pr_title)
+                .unwrap_or_else(|| commit.subject.clone()),
+            (None, _) => node.pr_title.unwrap_or_else(|| commit.subject.clone()),
+        };
+        let header = parse_header(&title);
+
+        notes.push(ReleaseNote {
+            pr_number: node.pr_number,
+            title: header.as_ref().map_or(title.clone(), |h| h.description.clone()),
+            kind: header.as_ref().map(|h| h.kind.clone()),
+            scope: header.as_ref().and_then(|h| h.scope.clone()),
+            breaking: header.as_ref().is_some_and(|h| h.breaking),
+            labels: pr.map(|pr| pr.labels.clone()).unwrap_or_default(),
+            author_login: pr.and_then(|pr| pr.author_login.clone()),
+            authors,
+            full_sha: node.full_sha,
+            strategy: node.strategy,
+        });
+    }
+
+    // Oldest first reads better in a changelog
+    notes.reverse();
+    let sections = group(notes, group_by);
+    let markdown = to_markdown(from, to, &sections);
+
+    Ok(ReleaseNotes {
+        from: from.to_string(),
+        to: to.to_string(),
+        sections,
+        markdown,
+    })
+}
+
+/// Split notes into sections, keeping their order within each. Type
+/// sections follow `TYPE_SECTIONS`, label sections are sorted by name, and
+/// anything that fits nowhere goes last.
+fn group(notes: Vec<ReleaseNote>, group_by: GroupBy) -> Vec<ReleaseSection> {
+    let mut sections: Vec<ReleaseSection> = Vec::new();
+    for note in notes {
+        let title = match group_by {
+            GroupBy::Type => note
+                .kind
+                .as_deref()
+                .and_then(|kind| TYPE_SECTIONS.iter().find(|(k, _)| *k == kind))
+                .map(|(_, title)| title.to_string()),
+            GroupBy::Label => note.labels.first().cloned(),
+        }
+        .unwrap_or_else(|| OTHER_SECTION.to_string());
+
+        match sections.iter_mut().find(|s| s.title == title) {
+            Some(section) => section.notes.push(note),
+            None => sections.push(ReleaseSection { title, notes: vec![note] }),
+        }
+    }
+
+    let rank = |title: &str| match group_by {
+        GroupBy::Type => TYPE_SECTIONS.iter().position(|(_, t)| *t == title).unwrap_or(TYPE_SECTIONS.len()),
+        GroupBy::Label => usize::from(title == OTHER_SECTION),
+    };
+    sections.sort_by(|a, b| rank(&a.title).cmp(&rank(&b.title)).then_with(|| match group_by {
+        GroupBy::Type => std::cmp::Ordering::Equal,
+        GroupBy::Label => a.title.cmp(&b.title),
+    }));
+    sections
+}
+
+fn to_markdown(from: &str, to: &str, sections: &[ReleaseSection]) -> String {
+    let mut out = format!("## Changes from {} to {}\n", from, to);
+    if sections.is_empty() {
+        out.push_str("\nNo pull requests.\n");
+    }
+    for section in sections {
+        let _ = write!(out, "\n### {}\n\n", section.title);
+        for note in &section.notes {
+            out.push_str("- ");
+            if note.breaking {
+                out.push_str("**BREAKING** ");
+            }
+            if let Some(scope) = &note.scope {
+                let _ = write!(out, "**{}:** ", scope);
+            }
+            out.push_str(&note.title);
+            if let Some(number) = note.pr_number {
+                let _ = write!(out, " (#{})", number);
+            }
+            match &note.author_login {
+                Some(login) => {
+                    let _ = write!(out, " by @{}", login);
+                }
+                None if !note.authors.is_empty() => {
+                    let _ = write!(out, " by {}", note.authors.join(", "));
+                }
+                None => {}
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fixture::Fixture;
+
+    fn lines(notes: &ReleaseNotes) -> Vec<String> {
+        notes.markdown.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_release_notes() {
+        let fx = Fixture::new("release-notes");
+        fx.commit("README.md", "hello\n", "Initial commit");
+        fx.git(&["tag", "v1.0.0"]);
+
+        fx.git(&["checkout", "-q", "-b", "search"]);
+        fx.git(&["-c", "user.name=Alice", "commit", "-q", "--allow-empty", "-m", "Add search box"]);
+        fx.git(&["checkout", "-q", "main"]);
+        fx.merge("search", "Merge pull request #1 from alice/search\n\nfeat(ui): add search");
+        let fix = fx.commit("a.txt", "a\n", "fix: handle empty repos (#2)");
+        fx.commit("b.txt", "b\n", "Update dependencies (#3)\n\nCo-authored-by: Dana <dana@example.com>");
+        fx.commit("c.txt", "c\n", "Tidy up");
+        fx.git(&["tag", "v1.1.0"]);
+
+        let notes = release_notes(fx.path(), "v1.0.0", "v1.1.0", &[], GroupBy::Type).unwrap();
+        assert_eq!(
+            lines(&notes),
+            vec![
+                "## Changes from v1.0.0 to v1.1.0",
+                "",
+                "### Features",
+                "",
+                "- **ui:** add search (#1) by Alice",
+                "",
+                "### Bug Fixes",
+                "",
+                "- handle empty repos (#2) by Test Author",
+                "",
+                "### Other Changes",
+                "",
+                "- Update dependencies (#3) by Test Author, Dana",
+            ]
+        );
+        assert_eq!(notes.sections[0].notes[0].kind.as_deref(), Some("feat"));
+
+        let known: Vec<KnownPr> = serde_json::from_value(serde_json::json!([
+            { "number": 2, "title": "fix!: refuse empty repos", "mergeCommitSha": fix, "authorLogin": "bob", "labels": ["bug"] },
+        ]))
+        .unwrap();
+        let notes = release_notes(fx.path(), "v1.0.0", "v1.1.0", &known, GroupBy::Label).unwrap();
+        let titles: Vec<&str> = notes.sections.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, vec!["bug", OTHER_SECTION]);
+        assert_eq!(lines(&notes)[4], "- **BREAKING** refuse empty repos (#2) by @bob");
+        assert_eq!(notes.sections[1].notes.len(), 2);
+
+        let empty = release_notes(fx.path(), "v1.1.0", "v1.1.0", &[], GroupBy::Type).unwrap();
+        assert!(empty.sections.is_empty());
+    }
+}
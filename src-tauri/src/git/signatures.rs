@@ -0,0 +1,147 @@
+//! Signature status of the commits on a branch, for teams that require
+//! every commit to be signed.
+
+use super::backend::{backend, Commit, LogQuery, SignatureStatus};
+use super::cli::GitError;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureSummary {
+    /// Every commit is signed (including branches with no commits)
+    AllSigned,
+    SomeUnsigned,
+    /// At least one signature is bad or made with a revoked key; takes
+    /// precedence over unsigned commits
+    BadSignature,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchSignatures {
+    pub branch: String,
+    pub summary: SignatureSummary,
+    /// Good signatures, including expired ones and keys of unknown validity
+    pub signed: usize,
+    pub unsigned: usize,
+    pub bad: usize,
+    /// Signed, but the key isn't available to check against
+    pub unverified: usize,
+    /// The branch's own commits, newest first, with `signature` set
+    pub commits: Vec<Commit>,
+}
+
+/// Verify every commit on `branch` that isn't on `default_branch` yet.
+pub fn branch_signatures(repo: &Path, default_branch: &str, branch: &str) -> Result<BranchSignatures, GitError> {
+    let query = LogQuery {
+        include: &[branch],
+        exclude: Some(default_branch),
+        verify_signatures: true,
+        ..Default::default()
+    };
+    let commits = backend().log(repo, &query)?;
+
+    let (mut signed, mut unsigned, mut bad, mut unverified) = (0, 0, 0, 0);
+    for commit in &commits {
+        match commit.signature.as_ref().map_or(SignatureStatus::Unsigned, |s| s.status) {
+            SignatureStatus::Bad | SignatureStatus::RevokedKey => bad += 1,
+            SignatureStatus::Unsigned => unsigned += 1,
+            SignatureStatus::CannotCheck => unverified += 1,
+            _ => signed += 1,
+        }
+    }
+    let summary = if bad > 0 {
+        SignatureSummary::BadSignature
+    } else if unsigned > 0 {
+        SignatureSummary::SomeUnsigned
+    } else {
+        SignatureSummary::AllSigned
+    };
+
+    Ok(BranchSignatures {
+        branch: branch.to_string(),
+        summary,
+        signed,
+        unsigned,
+        bad,
+        unverified,
+        commits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::backend::{backend_for, BackendKind};
+    use crate::git::fixture::Fixture;
+    use std::process::Command;
+
+    /// Sign commits in `fx` with a fresh SSH key that is also the only entry
+    /// in its allowed signers file.
+    fn use_throwaway_ssh_key(fx: &Fixture) {
+        let key = fx.path().join(".git").join("signing_key");
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", "test", "-f"])
+            .arg(&key)
+            .status()
+            .expect("spawn ssh-keygen");
+        assert!(status.success());
+
+        let public = std::fs::read_to_string(key.with_extension("pub")).unwrap();
+        let allowed = fx.path().join(".git").join("allowed_signers");
+        std::fs::write(&allowed, format!("author@example.com {}", public)).unwrap();
+
+        fx.git(&["config", "gpg.format", "ssh"]);
+        fx.git(&["config", "user.signingkey", key.to_str().unwrap()]);
+        fx.git(&["config", "gpg.ssh.allowedSignersFile", allowed.to_str().unwrap()]);
+    }
+
+    #[test]
+    fn test_branch_signatures() {
+        let fx = Fixture::new("signatures");
+        use_throwaway_ssh_key(&fx);
+        fx.commit("README.md", "hello\n", "Initial commit");
+
+        fx.git(&["config", "commit.gpgsign", "true"]);
+        fx.git(&["checkout", "-q", "-b", "signed"]);
+        let signed = fx.commit("a.txt", "a\n", "Signed work");
+        fx.commit("b.txt", "b\n", "More signed work");
+
+        fx.git(&["checkout", "-q", "-b", "mixed", "main"]);
+        fx.commit("c.txt", "c\n", "Signed");
+        fx.git(&["config", "commit.gpgsign", "false"]);
+        fx.commit("d.txt", "d\n", "Not signed");
+
+        // The same signed commit with its message edited afterwards
+        let raw = fx.git(&["cat-file", "commit", &signed]);
+        let forged = fx.path().join(".git").join("forged");
+        std::fs::write(&forged, raw.replace("Signed work", "Forged work") + "\n").unwrap();
+        let forged = fx.git(&["hash-object", "-t", "commit", "-w", forged.to_str().unwrap()]);
+        fx.git(&["branch", "forged", &forged]);
+
+        let report = branch_signatures(fx.path(), "main", "signed").unwrap();
+        assert_eq!(report.summary, SignatureSummary::AllSigned);
+        assert_eq!((report.signed, report.unsigned, report.bad), (2, 0, 0));
+        let signature = report.commits[0].signature.as_ref().unwrap();
+        assert_eq!(signature.status, SignatureStatus::Good);
+        assert_eq!(signature.signer.as_deref(), Some("author@example.com"));
+        assert!(signature.key.as_deref().is_some_and(|k| k.starts_with("SHA256:")));
+
+        let report = branch_signatures(fx.path(), "main", "mixed").unwrap();
+        assert_eq!(report.summary, SignatureSummary::SomeUnsigned);
+        assert_eq!((report.signed, report.unsigned), (1, 1));
+        assert_eq!(report.commits[0].signature.as_ref().unwrap().status, SignatureStatus::Unsigned);
+
+        let report = branch_signatures(fx.path(), "main", "forged").unwrap();
+        assert_eq!(report.summary, SignatureSummary::BadSignature);
+        assert_eq!(report.bad, 1);
+
+        // libgit2 hands signed commits to git, so both backends agree
+        let query = LogQuery { include: &["mixed", "forged"], verify_signatures: true, ..Default::default() };
+        let cli = backend_for(BackendKind::Cli).log(fx.path(), &query).unwrap();
+        assert_eq!(cli, backend_for(BackendKind::Libgit2).log(fx.path(), &query).unwrap());
+        let unverified = LogQuery { include: &["signed"], ..Default::default() };
+        assert_eq!(backend_for(BackendKind::Cli).log(fx.path(), &unverified).unwrap()[0].signature, None);
+    }
+}
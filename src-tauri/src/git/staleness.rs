@@ -0,0 +1,173 @@
+//! Branch staleness rules.
+//!
+//! Defaults can be overridden per user in `<config dir>/git-visualizer/config.toml`
+//! and per repository in `.git-visualizer.toml` at the repo root. Both files
+//! use a `[staleness]` table; the repo file wins field by field:
+//!
+//! ```toml
+//! [staleness]
+//! stale-behind = 80
+//! conflict-risk-behind = 20
+//! stale-after-days = 30
+//! exempt = ["release/*", "staging", "production"]
+//! ```
+
+use super::cli::GitError;
+use super::config::{self, glob_match};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BranchStatus {
+    Fresh,
+    Stale,
+    ConflictRisk,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StalenessRules {
+    /// More than this many commits behind the default branch is stale
+    pub stale_behind: i32,
+    /// More than this many commits behind is a conflict risk
+    pub conflict_risk_behind: i32,
+    /// No commits for more than this many days is stale
+    pub stale_after_days: i64,
+    /// Branch-name globs that are never considered stale (`*` matches `/`)
+    pub exempt: Vec<String>,
+}
+
+impl Default for StalenessRules {
+    fn default() -> Self {
+        StalenessRules {
+            stale_behind: 50,
+            conflict_risk_behind: 10,
+            stale_after_days: 14,
+            exempt: Vec::new(),
+        }
+    }
+}
+
+/// One layer of settings as written in a config file; unset fields fall through.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct StalenessLayer {
+    stale_behind: Option<i32>,
+    conflict_risk_behind: Option<i32>,
+    stale_after_days: Option<i64>,
+    exempt: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    staleness: StalenessLayer,
+}
+
+impl StalenessRules {
+    /// Defaults, then the app-level settings file, then the repo's own file.
+    pub fn load(repo: &Path) -> Result<Self, GitError> {
+        let mut rules = StalenessRules::default();
+        for file in config::layers::<ConfigFile>(repo)? {
+            rules.apply(file.staleness);
+        }
+        Ok(rules)
+    }
+
+    fn apply(&mut self, layer: StalenessLayer) {
+        if let Some(v) = layer.stale_behind {
+            self.stale_behind = v;
+        }
+        if let Some(v) = layer.conflict_risk_behind {
+            self.conflict_risk_behind = v;
+        }
+        if let Some(v) = layer.stale_after_days {
+            self.stale_after_days = v;
+        }
+        if let Some(v) = layer.exempt {
+            self.exempt = v;
+        }
+    }
+
+    pub fn is_exempt(&self, branch: &str) -> bool {
+        self.exempt.iter().any(|pattern| glob_match(pattern, branch))
+    }
+
+    /// Classify a branch. Exempt branches skip the stale checks but can
+    /// still be flagged as a conflict risk.
+    pub fn classify(&self, branch: &str, commits_behind: i32, last_commit_date: &str) -> BranchStatus {
+        let exempt = self.is_exempt(branch);
+
+        if !exempt && commits_behind > self.stale_behind {
+            return BranchStatus::Stale;
+        }
+
+        if commits_behind > self.conflict_risk_behind {
+            return BranchStatus::ConflictRisk;
+        }
+
+        // Check date freshness
+        if !exempt {
+            if let Ok(commit_date) = chrono::DateTime::parse_from_rfc3339(last_commit_date) {
+                let now = chrono::Utc::now();
+                let days_old = (now - commit_date.with_timezone(&chrono::Utc)).num_days();
+
+                if days_old > self.stale_after_days {
+                    return BranchStatus::Stale;
+                }
+            }
+        }
+
+        BranchStatus::Fresh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fixture::Fixture;
+
+    #[test]
+    fn test_classify_with_overrides() {
+        let recent = chrono::Utc::now().to_rfc3339();
+        let old = (chrono::Utc::now() - chrono::Duration::days(20)).to_rfc3339();
+
+        let defaults = StalenessRules::default();
+        assert_eq!(defaults.classify("feat", 0, &recent), BranchStatus::Fresh);
+        assert_eq!(defaults.classify("feat", 11, &recent), BranchStatus::ConflictRisk);
+        assert_eq!(defaults.classify("feat", 51, &recent), BranchStatus::Stale);
+        assert_eq!(defaults.classify("feat", 0, &old), BranchStatus::Stale);
+
+        let mut rules = defaults.clone();
+        let file: ConfigFile = toml::from_str(
+            "[staleness]\nstale-after-days = 30\nexempt = [\"release/*\"]\n",
+        )
+        .unwrap();
+        rules.apply(file.staleness);
+        assert_eq!(rules.stale_behind, 50);
+        assert_eq!(rules.classify("feat", 0, &old), BranchStatus::Fresh);
+        assert_eq!(rules.classify("release/2.0", 500, &recent), BranchStatus::ConflictRisk);
+        assert_eq!(rules.classify("release/2.0", 0, "2001-01-01T00:00:00Z"), BranchStatus::Fresh);
+
+        assert!(toml::from_str::<ConfigFile>("[staleness]\nstale-behnd = 3\n").is_err());
+        assert_eq!(serde_json::to_string(&BranchStatus::ConflictRisk).unwrap(), "\"conflict-risk\"");
+    }
+
+    #[test]
+    fn test_load_from_subdirectory() {
+        let fx = Fixture::new("staleness-subdir");
+        fx.write(".git-visualizer.toml", "[staleness]\nstale-behind = 7\n");
+        fx.commit("src/main.rs", "fn main() {}\n", "Initial commit");
+
+        let rules = StalenessRules::load(&fx.path().join("src")).unwrap();
+        assert_eq!(rules.stale_behind, 7);
+
+        // A typo is reported here, while branches still list with the defaults
+        fx.write(".git-visualizer.toml", "[staleness]\nstale-behnd = 7\n");
+        fx.git(&["branch", "topic"]);
+        assert!(StalenessRules::load(fx.path()).is_err());
+        let branches = crate::git::list_branches(fx.path(), "main").unwrap();
+        assert_eq!(branches[0].name, "topic");
+    }
+}
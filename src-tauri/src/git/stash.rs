@@ -0,0 +1,124 @@
+//! Stash entries as side nodes of the history: the commit each stash was
+//! made on, and what it holds.
+
+use super::backend::{backend, DiffRange, StashEntry};
+use super::cli::GitError;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stash {
+    #[serde(flatten)]
+    pub entry: StashEntry,
+    /// Branch that was checked out, from the stash message; `None` when
+    /// stashed on a detached HEAD
+    pub branch: Option<String>,
+    /// Commit HEAD was at when stashing (the stash commit's first parent)
+    pub base_sha: String,
+    /// The message without its `WIP on <branch>:` / `On <branch>:` prefix
+    pub description: String,
+    /// Tracked files the stash changes, staged or not
+    pub files: Vec<String>,
+    /// Untracked files saved with `git stash -u`
+    pub untracked: Vec<String>,
+}
+
+/// Split a stash reflog message into branch and description.
+fn parse_message(message: &str) -> (Option<String>, String) {
+    let rest = message
+        .strip_prefix("WIP on ")
+        .or_else(|| message.strip_prefix("On "));
+    match rest.and_then(|rest| rest.split_once(": ")) {
+        Some((branch, description)) => {
+            let branch = Some(branch.to_string()).filter(|b| b != "(no branch)");
+            (branch, description.to_string())
+        }
+        None => (None, message.to_string()),
+    }
+}
+
+/// Every stash entry, newest first.
+pub fn list_stashes(repo: &Path) -> Result<Vec<Stash>, GitError> {
+    let git = backend();
+    let mut stashes = Vec::new();
+    for entry in git.stashes(repo)? {
+        let base = format!("{}^1", entry.sha);
+        let base_sha = git
+            .resolve(repo, &base)?
+            .ok_or_else(|| GitError::CommandFailed(format!("stash {} has no parent", entry.sha)))?;
+        let files = git.diff_names(repo, DiffRange::Between(&base_sha, &entry.sha))?;
+        // The third parent holds untracked files, when there were any to save
+        let untracked_commit = format!("{}^3", entry.sha);
+        let untracked = match git.resolve(repo, &untracked_commit)? {
+            Some(sha) => git.ls_tree(repo, &sha, &[])?,
+            None => Vec::new(),
+        };
+        let (branch, description) = parse_message(&entry.message);
+        stashes.push(Stash {
+            entry,
+            branch,
+            base_sha,
+            description,
+            files,
+            untracked,
+        });
+    }
+    Ok(stashes)
+}
+
+/// Patch of a stash against the commit it was made on, like
+/// `git stash show -p` (untracked files are not included).
+pub fn stash_diff(repo: &Path, sha: &str) -> Result<String, GitError> {
+    let git = backend();
+    if !git.stashes(repo)?.iter().any(|entry| entry.sha == sha) {
+        return Err(GitError::CommandFailed(format!("{} is not a stash entry", sha)));
+    }
+    let base = format!("{}^1", sha);
+    git.diff(repo, DiffRange::Between(&base, sha))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fixture::Fixture;
+
+    #[test]
+    fn test_stashes() {
+        let fx = Fixture::new("stash");
+        let base = fx.commit("README.md", "hello\n", "Initial commit");
+        fx.write("README.md", "hello\nstashed\n");
+        fx.git(&["stash", "push", "-q"]);
+
+        fx.git(&["checkout", "-q", "-b", "feature"]);
+        let feature = fx.commit("a.txt", "a\n", "Add a");
+        fx.write("a.txt", "b\n");
+        fx.write("scratch.txt", "notes\n");
+        fx.git(&["stash", "push", "-q", "-u", "-m", "try another letter"]);
+
+        fx.git(&["checkout", "-q", "--detach"]);
+        fx.write("a.txt", "c\n");
+        fx.git(&["stash", "push", "-q"]);
+
+        let stashes = list_stashes(fx.path()).unwrap();
+        assert_eq!(stashes.len(), 3);
+        assert_eq!(stashes[0].branch, None);
+        assert_eq!(stashes[0].entry.index, 0);
+
+        let with_untracked = &stashes[1];
+        assert_eq!(with_untracked.branch.as_deref(), Some("feature"));
+        assert_eq!(with_untracked.base_sha, feature);
+        assert_eq!(with_untracked.description, "try another letter");
+        assert_eq!(with_untracked.files, vec!["a.txt"]);
+        assert_eq!(with_untracked.untracked, vec!["scratch.txt"]);
+
+        let oldest = &stashes[2];
+        assert_eq!((oldest.branch.as_deref(), oldest.base_sha.as_str()), (Some("main"), base.as_str()));
+        assert!(oldest.description.ends_with(" Initial commit"));
+        assert!(oldest.untracked.is_empty());
+
+        let diff = stash_diff(fx.path(), &oldest.entry.sha).unwrap();
+        assert!(diff.contains("+stashed"));
+        assert!(stash_diff(fx.path(), &base).is_err());
+    }
+}
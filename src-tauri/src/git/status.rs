@@ -0,0 +1,194 @@
+//! The state of the current checkout: uncommitted changes, stashes, and any
+//! merge, rebase, cherry-pick or revert that stopped part way.
+
+use super::backend::{backend, ChangedFile, MergeConflict, StashEntry, WorkingChanges};
+use super::cli::GitError;
+use super::discovery::work_tree;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OperationKind {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+}
+
+/// A multi-step operation waiting on the user (usually to resolve conflicts).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Operation {
+    pub kind: OperationKind,
+    /// Commit being merged, cherry-picked or reverted; for a rebase, the
+    /// commit the branch is being replayed onto
+    pub sha: Option<String>,
+    /// Short name of the branch being rebased
+    pub branch: Option<String>,
+    /// Rebase progress: the commit being applied (1-based) out of `total`
+    pub step: Option<u32>,
+    pub total: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoStatus {
+    /// Short name of the checked-out branch; `None` when detached
+    pub branch: Option<String>,
+    /// `None` before the first commit
+    pub head_sha: Option<String>,
+    pub staged: Vec<ChangedFile>,
+    pub unstaged: Vec<ChangedFile>,
+    pub untracked: Vec<ChangedFile>,
+    pub conflicted: Vec<MergeConflict>,
+    /// Newest first
+    pub stashes: Vec<StashEntry>,
+    pub operation: Option<Operation>,
+}
+
+/// Status of the working tree at `repo`. A bare repository has no
+/// uncommitted changes to report, only its HEAD and stashes.
+pub fn repo_status(repo: &Path) -> Result<RepoStatus, GitError> {
+    let git = backend();
+    let changes = match work_tree(repo)? {
+        Some(_) => git.working_changes(repo)?,
+        None => WorkingChanges::default(),
+    };
+    let branch = git
+        .symbolic_ref(repo, "HEAD")?
+        .map(|r| r.strip_prefix("refs/heads/").unwrap_or(&r).to_string());
+
+    Ok(RepoStatus {
+        branch,
+        head_sha: git.resolve(repo, "HEAD^{commit}")?,
+        staged: changes.staged,
+        unstaged: changes.unstaged,
+        untracked: changes.untracked,
+        conflicted: changes.conflicted,
+        stashes: git.stashes(repo)?,
+        operation: operation(repo)?,
+    })
+}
+
+/// Work out the operation in progress from the state files git leaves in
+/// the (per-worktree) git directory.
+fn operation(repo: &Path) -> Result<Option<Operation>, GitError> {
+    let git_dir = backend().git_dirs(repo)?.git_dir;
+    let read = |name: &str| {
+        std::fs::read_to_string(git_dir.join(name))
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    // `rebase-merge` is the default backend; `rebase-apply` is also used by
+    // `git am`, which is not a rebase
+    for (dir, step, total) in [("rebase-merge", "msgnum", "end"), ("rebase-apply", "next", "last")] {
+        if !git_dir.join(dir).is_dir() || git_dir.join(dir).join("applying").exists() {
+            continue;
+        }
+        let file = |name: &str| read(&format!("{}/{}", dir, name));
+        return Ok(Some(Operation {
+            kind: OperationKind::Rebase,
+            sha: file("onto"),
+            branch: file("head-name")
+                .filter(|name| name.starts_with("refs/"))
+                .map(|name| name.strip_prefix("refs/heads/").unwrap_or(&name).to_string()),
+            step: file(step).and_then(|n| n.parse().ok()),
+            total: file(total).and_then(|n| n.parse().ok()),
+        }));
+    }
+
+    let heads = [
+        ("MERGE_HEAD", OperationKind::Merge),
+        ("CHERRY_PICK_HEAD", OperationKind::CherryPick),
+        ("REVERT_HEAD", OperationKind::Revert),
+    ];
+    Ok(heads.into_iter().find_map(|(head, kind)| {
+        // MERGE_HEAD lists one commit per line for an octopus merge
+        let sha = read(head)?.lines().next()?.to_string();
+        Some(Operation {
+            kind,
+            sha: Some(sha),
+            branch: None,
+            step: None,
+            total: None,
+        })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::backend::{backend_for, BackendKind, ConflictKind, FileChange};
+    use crate::git::fixture::Fixture;
+
+    #[test]
+    fn test_repo_status() {
+        let fx = Fixture::new("status");
+        fx.commit("README.md", "hello\n", "Initial commit");
+        fx.commit("notes.txt", "one\ntwo\nthree\n", "Add notes");
+
+        fx.git(&["mv", "notes.txt", "docs.txt"]);
+        fx.write("README.md", "hello\nworld\n");
+        fx.write("docs.txt", "one\ntwo\nthree\nfour\n");
+        fx.write("new/file.txt", "a\nb");
+        std::fs::write(fx.path().join("image.bin"), [0u8, 1, 2]).unwrap();
+        fx.git(&["stash", "push", "-q", "--", "README.md"]);
+        fx.write("README.md", "bye\n");
+
+        let status = repo_status(fx.path()).unwrap();
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.operation, None);
+        let staged = &status.staged[0];
+        assert_eq!((staged.path.as_str(), staged.old_path.as_deref()), ("docs.txt", Some("notes.txt")));
+        assert_eq!((staged.change, staged.additions, staged.deletions), (FileChange::Renamed, Some(0), Some(0)));
+        let unstaged: Vec<(&str, Option<u32>, Option<u32>)> =
+            status.unstaged.iter().map(|f| (f.path.as_str(), f.additions, f.deletions)).collect();
+        assert_eq!(unstaged, vec![("README.md", Some(1), Some(1)), ("docs.txt", Some(1), Some(0))]);
+        let untracked: Vec<(&str, Option<u32>)> = status.untracked.iter().map(|f| (f.path.as_str(), f.additions)).collect();
+        assert_eq!(untracked, vec![("image.bin", None), ("new/file.txt", Some(2))]);
+        assert_eq!(status.stashes.len(), 1);
+        assert!(status.stashes[0].message.starts_with("WIP on main: "));
+
+        let [cli, lib] = [backend_for(BackendKind::Cli), backend_for(BackendKind::Libgit2)];
+        assert_eq!(cli.working_changes(fx.path()).unwrap(), lib.working_changes(fx.path()).unwrap());
+        assert_eq!(cli.stashes(fx.path()).unwrap(), lib.stashes(fx.path()).unwrap());
+
+        // Stopped on a conflict partway through a rebase
+        fx.git(&["reset", "-q", "--hard"]);
+        fx.git(&["clean", "-q", "-fd"]);
+        fx.commit("README.md", "main\n", "Update readme");
+        fx.git(&["checkout", "-q", "-b", "feature", "HEAD~2"]);
+        fx.commit("README.md", "feature\n", "Change readme");
+        fx.commit("other.txt", "other\n", "Add other");
+        let onto = fx.git(&["rev-parse", "main"]);
+        let rebase = std::process::Command::new("git")
+            .arg("-C")
+            .arg(fx.path())
+            .args(["rebase", "-q", "main"])
+            .output()
+            .unwrap();
+        assert!(!rebase.status.success());
+
+        let status = repo_status(fx.path()).unwrap();
+        assert_eq!(status.branch, None);
+        assert_eq!(
+            status.operation,
+            Some(Operation {
+                kind: OperationKind::Rebase,
+                sha: Some(onto),
+                branch: Some("feature".to_string()),
+                step: Some(1),
+                total: Some(2),
+            })
+        );
+        assert_eq!(
+            status.conflicted,
+            vec![MergeConflict { path: "README.md".to_string(), kind: ConflictKind::Content }]
+        );
+        assert!(status.staged.is_empty() && status.unstaged.is_empty());
+        assert_eq!(cli.working_changes(fx.path()).unwrap(), lib.working_changes(fx.path()).unwrap());
+    }
+}
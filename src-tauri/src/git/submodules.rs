@@ -0,0 +1,165 @@
+//! Submodules of a repository: the commit the superproject pins each one
+//! to, compared with the head of the branch the submodule follows.
+
+use super::backend::backend;
+use super::branches::{get_default_branch, get_repo_info};
+use super::cli::GitError;
+use super::discovery::work_tree;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Submodule {
+    pub name: String,
+    /// Relative to the superproject's top level
+    pub path: String,
+    /// Absolute path, for opening the submodule as a repository of its own
+    pub repo_path: String,
+    pub url: Option<String>,
+    /// Checked out (`git submodule update --init`), so it can be inspected
+    pub initialized: bool,
+    /// Commit the superproject's HEAD pins the submodule to
+    pub pinned_sha: Option<String>,
+    /// Commit currently checked out in the submodule
+    pub checked_out_sha: Option<String>,
+    /// Branch the pin is compared with: `submodule.<name>.branch`, or the
+    /// submodule's default branch
+    pub branch: Option<String>,
+    /// Head of `branch`, from the remote-tracking ref when there is one
+    pub branch_sha: Option<String>,
+    /// Commits on `branch` the pin doesn't include yet; `None` when either
+    /// commit is missing from the submodule (e.g. not fetched)
+    pub behind: Option<usize>,
+    /// Commits in the pin that aren't on `branch`
+    pub ahead: Option<usize>,
+}
+
+/// Every submodule of `repo`, sorted by path. Bare repositories have no
+/// checked-out submodules, so none are listed.
+pub fn list_submodules(repo: &Path) -> Result<Vec<Submodule>, GitError> {
+    let git = backend();
+    let Some(root) = work_tree(repo)? else {
+        return Ok(Vec::new());
+    };
+    let current_branch = git
+        .symbolic_ref(&root, "HEAD")?
+        .and_then(|r| r.strip_prefix("refs/heads/").map(str::to_string));
+
+    let mut submodules = Vec::new();
+    for entry in git.submodules(&root)? {
+        let repo_path = root.join(&entry.path);
+        // An uninitialized submodule is an empty directory, and git would
+        // happily run in the superproject from there
+        let initialized = repo_path.join(".git").exists();
+
+        let (mut checked_out_sha, mut branch, mut branch_sha, mut behind, mut ahead) = (None, None, None, None, None);
+        if initialized {
+            checked_out_sha = git.resolve(&repo_path, "HEAD")?;
+            // `.` means "the same branch as the superproject"
+            branch = match entry.branch.as_deref() {
+                Some(".") => current_branch.clone(),
+                Some(name) => Some(name.to_string()),
+                None => Some(get_default_branch(&repo_path)?).filter(|name| name != "HEAD"),
+            };
+            if let Some(name) = &branch {
+                branch_sha = match git.resolve(&repo_path, &format!("refs/remotes/origin/{}", name))? {
+                    Some(sha) => Some(sha),
+                    None => git.resolve(&repo_path, &format!("refs/heads/{}", name))?,
+                };
+            }
+            let pinned = match &entry.pinned_sha {
+                Some(sha) => git.resolve(&repo_path, &format!("{}^{{commit}}", sha))?,
+                None => None,
+            };
+            if let (Some(pinned), Some(head)) = (&pinned, &branch_sha) {
+                let (pin_ahead, pin_behind) = git.ahead_behind(&repo_path, pinned, head)?;
+                ahead = Some(pin_ahead);
+                behind = Some(pin_behind);
+            }
+        }
+
+        submodules.push(Submodule {
+            name: entry.name,
+            path: entry.path,
+            repo_path: repo_path.to_string_lossy().into_owned(),
+            url: entry.url,
+            initialized,
+            pinned_sha: entry.pinned_sha,
+            checked_out_sha,
+            branch,
+            branch_sha,
+            behind,
+            ahead,
+        });
+    }
+    Ok(submodules)
+}
+
+/// Name and absolute path of the submodule at `path` (or named `path`), to
+/// open it as a repository of its own.
+pub fn open_submodule(repo: &Path, path: &str) -> Result<(String, String), GitError> {
+    let submodule = list_submodules(repo)?
+        .into_iter()
+        .find(|s| s.path == path || s.name == path)
+        .ok_or_else(|| GitError::CommandFailed(format!("no submodule at {}", path)))?;
+    if !submodule.initialized {
+        return Err(GitError::CommandFailed(format!(
+            "submodule {} is not initialized; run `git submodule update --init`",
+            submodule.path
+        )));
+    }
+    get_repo_info(Path::new(&submodule.repo_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::backend::{backend_for, BackendKind};
+    use crate::git::fixture::Fixture;
+
+    #[test]
+    fn test_submodules() {
+        let lib = Fixture::new("submodules-lib");
+        let pinned = lib.commit("lib.txt", "v1\n", "Initial commit");
+
+        let fx = Fixture::new("submodules");
+        fx.commit("README.md", "hello\n", "Initial commit");
+        let url = lib.path().to_str().unwrap();
+        fx.git(&["-c", "protocol.file.allow=always", "submodule", "add", "-q", url, "vendor/lib"]);
+        fx.git(&["commit", "-q", "-m", "Add lib"]);
+        fx.git(&["config", "-f", ".gitmodules", "submodule.docs.path", "docs"]);
+        fx.git(&["config", "-f", ".gitmodules", "submodule.docs.url", url]);
+
+        // Upstream moves on and the submodule fetches it, but the pin stays
+        lib.commit("lib.txt", "v2\n", "Second");
+        lib.commit("lib.txt", "v3\n", "Third");
+        let head = lib.git(&["rev-parse", "HEAD"]);
+        fx.git(&["-C", "vendor/lib", "fetch", "-q"]);
+
+        let entries = backend_for(BackendKind::Cli).submodules(fx.path()).unwrap();
+        assert_eq!(entries, backend_for(BackendKind::Libgit2).submodules(fx.path()).unwrap());
+
+        let submodules = list_submodules(fx.path()).unwrap();
+        let paths: Vec<&str> = submodules.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(paths, vec!["docs", "vendor/lib"]);
+
+        let docs = &submodules[0];
+        assert!(!docs.initialized);
+        assert_eq!((docs.pinned_sha.as_ref(), docs.branch.as_ref(), docs.behind), (None, None, None));
+
+        let vendored = &submodules[1];
+        assert!(vendored.initialized);
+        assert_eq!(vendored.pinned_sha.as_ref(), Some(&pinned));
+        assert_eq!(vendored.checked_out_sha.as_ref(), Some(&pinned));
+        assert_eq!(vendored.branch.as_deref(), Some("main"));
+        assert_eq!(vendored.branch_sha.as_ref(), Some(&head));
+        assert_eq!((vendored.behind, vendored.ahead), (Some(2), Some(0)));
+
+        let (name, path) = open_submodule(fx.path(), "vendor/lib").unwrap();
+        assert_eq!(name, "lib");
+        assert_eq!(Path::new(&path), Path::new(&vendored.repo_path).canonicalize().unwrap());
+        assert!(open_submodule(fx.path(), "docs").is_err());
+        assert!(open_submodule(fx.path(), "missing").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! Tags and releases: every tag with its target commit, tagger and message,
+//! semver ordering, and the first release that shipped a given commit.
+
+use super::backend::{backend, LogQuery, TagEntry};
+use super::cli::GitError;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// A semantic version read from a tag name such as `v1.4.0` or `2.0.0-rc.1`.
+/// Build metadata (`+...`) is dropped, as it doesn't affect precedence.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Pre-release part, e.g. `rc.1`
+    pub pre: Option<String>,
+}
+
+impl Version {
+    /// Parse `[v]MAJOR.MINOR.PATCH[-PRE][+BUILD]`.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let version = tag.strip_prefix(['v', 'V']).unwrap_or(tag);
+        let version = version.split_once('+').map_or(version, |(v, _)| v);
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version, None),
+        };
+        if pre.is_some_and(|pre| pre.is_empty() || pre.split('.').any(str::is_empty)) {
+            return None;
+        }
+
+        let mut parts = core.split('.').map(|part| {
+            let numeric = !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+            numeric.then(|| part.parse::<u64>().ok()).flatten()
+        });
+        let (major, minor, patch) = (parts.next()??, parts.next()??, parts.next()??);
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Version {
+            major,
+            minor,
+            patch,
+            pre: pre.map(str::to_string),
+        })
+    }
+}
+
+impl Ord for Version {
+    /// Semver precedence: a pre-release sorts before its release, and
+    /// pre-release identifiers compare numerically when both are numbers.
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => {
+                    let ids = |s: &str| s.split('.').map(|id| (id.parse::<u64>().ok(), id.to_string())).collect::<Vec<_>>();
+                    let (a, b) = (ids(a), ids(b));
+                    for (x, y) in a.iter().zip(&b) {
+                        let ord = match (x.0, y.0) {
+                            (Some(x), Some(y)) => x.cmp(&y),
+                            (Some(_), None) => Ordering::Less,
+                            (None, Some(_)) => Ordering::Greater,
+                            (None, None) => x.1.cmp(&y.1),
+                        };
+                        if ord != Ordering::Equal {
+                            return ord;
+                        }
+                    }
+                    a.len().cmp(&b.len())
+                }
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    #[serde(flatten)]
+    pub entry: TagEntry,
+    /// Set when the name is a semantic version
+    pub version: Option<Version>,
+}
+
+impl Tag {
+    /// A stable semver release, as opposed to a pre-release or any other tag.
+    fn is_release(&self) -> bool {
+        self.version.as_ref().is_some_and(|v| v.pre.is_none())
+    }
+}
+
+/// All tags on commits: semver tags first, highest version first, then the
+/// rest newest first.
+pub fn list_tags(repo: &Path) -> Result<Vec<Tag>, GitError> {
+    let mut tags: Vec<Tag> = backend()
+        .tags(repo)?
+        .into_iter()
+        .map(|entry| Tag {
+            version: Version::parse(&entry.name),
+            entry,
+        })
+        .collect();
+
+    tags.sort_by(|a, b| match (&a.version, &b.version) {
+        (Some(x), Some(y)) => y.cmp(x).then_with(|| a.entry.name.cmp(&b.entry.name)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => b.entry.date.cmp(&a.entry.date),
+    });
+    Ok(tags)
+}
+
+/// For each of `shas` (e.g. `MergeNode` commits), the name of the first
+/// release tag that contains it. Releases are stable semver tags in version
+/// order; commits no release contains yet are left out.
+///
+/// Walks the releases oldest first and reads only what each one adds over
+/// the previous one: a commit first shipped in release `n` can't be in
+/// release `n - 1`, so it always shows up in that range.
+pub fn first_releases(repo: &Path, shas: &[String]) -> Result<HashMap<String, String>, GitError> {
+    let mut releases: Vec<Tag> = list_tags(repo)?.into_iter().filter(Tag::is_release).collect();
+    releases.reverse();
+
+    let mut pending: HashSet<&str> = shas.iter().map(String::as_str).collect();
+    let mut found = HashMap::new();
+    let mut previous: Option<&str> = None;
+
+    for release in &releases {
+        if pending.is_empty() {
+            break;
+        }
+        let query = LogQuery {
+            include: &[release.entry.target_sha.as_str()],
+            exclude: previous,
+            ..Default::default()
+        };
+        for commit in backend().log(repo, &query)? {
+            if pending.remove(commit.full_sha.as_str()) {
+                found.insert(commit.full_sha, release.entry.name.clone());
+            }
+        }
+        previous = Some(&release.entry.target_sha);
+    }
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::commits::get_merge_nodes;
+    use crate::git::fixture::Fixture;
+
+    #[test]
+    fn test_version_parse_and_order() {
+        let v = |s: &str| Version::parse(s).unwrap();
+        assert_eq!(v("v1.2.3"), Version { major: 1, minor: 2, patch: 3, pre: None });
+        assert_eq!(v("2.0.0-rc.1+build.5").pre.as_deref(), Some("rc.1"));
+        for bad in ["v1.2", "1.2.3.4", "release", "v1.x.0", "1.0.0-", "1.0.0-rc..1"] {
+            assert_eq!(Version::parse(bad), None, "{}", bad);
+        }
+
+        let mut versions: Vec<Version> = [
+            "1.0.0", "1.0.0-rc.1", "1.0.0-alpha", "1.0.0-alpha.beta", "1.0.0-alpha.1", "1.0.0-beta.11",
+            "1.0.0-beta.2", "1.0.0-beta", "0.9.10", "0.9.9",
+        ]
+        .iter()
+        .map(|s| v(s))
+        .collect();
+        versions.sort();
+        let order: Vec<String> = versions
+            .iter()
+            .map(|v| format!("{}.{}.{}{}", v.major, v.minor, v.patch, v.pre.as_ref().map_or(String::new(), |p| format!("-{}", p))))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                "0.9.9", "0.9.10", "1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-alpha.beta", "1.0.0-beta", "1.0.0-beta.2",
+                "1.0.0-beta.11", "1.0.0-rc.1", "1.0.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_releases() {
+        let fx = Fixture::new("tags-releases");
+        fx.commit("README.md", "hello\n", "Initial commit");
+        let first = fx.commit("a.txt", "a\n", "Add a (#1)");
+        fx.git(&["tag", "-a", "v1.0.0", "-m", "Release 1.0.0"]);
+
+        // A maintenance release that doesn't have what main has since
+        let second = fx.commit("b.txt", "b\n", "Add b (#2)");
+        fx.git(&["checkout", "-q", "-b", "maint", "v1.0.0"]);
+        let fix = fx.commit("fix.txt", "f\n", "Fix crash (#3)");
+        fx.git(&["tag", "v1.0.1"]);
+        fx.git(&["checkout", "-q", "main"]);
+        fx.git(&["tag", "v1.1.0-rc.1"]);
+        fx.git(&["tag", "nightly"]);
+        fx.commit("c.txt", "c\n", "Add c (#4)");
+        fx.git(&["tag", "v1.1.0"]);
+        let unreleased = fx.commit("d.txt", "d\n", "Add d (#5)");
+
+        let tags = list_tags(fx.path()).unwrap();
+        let names: Vec<&str> = tags.iter().map(|t| t.entry.name.as_str()).collect();
+        assert_eq!(names, vec!["v1.1.0", "v1.1.0-rc.1", "v1.0.1", "v1.0.0", "nightly"]);
+        assert!(tags[3].entry.annotated);
+
+        let (nodes, _) = get_merge_nodes(fx.path(), "main", 0, 10, &[]).unwrap();
+        let mut shas: Vec<String> = nodes.into_iter().map(|n| n.full_sha).collect();
+        shas.push(fix.clone());
+        assert_eq!(shas.len(), 5);
+
+        let releases = first_releases(fx.path(), &shas).unwrap();
+        assert_eq!(releases[&first], "v1.0.0");
+        assert_eq!(releases[&fix], "v1.0.1");
+        assert_eq!(releases[&second], "v1.1.0");
+        assert_eq!(releases.get(&unreleased), None);
+        assert_eq!(releases.len(), 4);
+    }
+}
@@ -0,0 +1,145 @@
+//! Git trailers (`Key: value` lines closing a commit message), with the
+//! common ones picked out: co-authors, sign-offs, reviewers and fixed issues.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trailer {
+    /// Key as written, e.g. `Co-authored-by`
+    pub key: String,
+    pub value: String,
+}
+
+/// A `Name <email>` trailer value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Person {
+    pub name: String,
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trailers {
+    pub co_authors: Vec<Person>,
+    pub signed_off_by: Vec<Person>,
+    pub reviewed_by: Vec<Person>,
+    /// Issue numbers from `Fixes #n` (also `Closes`/`Resolves`, with or
+    /// without a colon)
+    pub fixes: Vec<u64>,
+    /// Every `Key: value` trailer in order, including the ones above
+    pub all: Vec<Trailer>,
+}
+
+const FIX_KEYWORDS: &[&str] = &["fixes", "fixed", "fix", "closes", "closed", "close", "resolves", "resolved", "resolve"];
+
+/// Parse the trailer block of a commit body: its last paragraph, if every
+/// line in it is a trailer, a `Fixes #n` line or an indented continuation.
+pub fn parse_trailers(body: &str) -> Trailers {
+    let mut trailers = Trailers::default();
+    let Some(block) = body.trim_end().rsplit("\n\n").next() else {
+        return trailers;
+    };
+
+    let mut entries: Vec<Trailer> = Vec::new();
+    let mut fixes = Vec::new();
+    for line in block.lines() {
+        if line.starts_with([' ', '\t']) && !entries.is_empty() {
+            let last = entries.last_mut().unwrap();
+            last.value.push(' ');
+            last.value.push_str(line.trim());
+        } else if let Some(numbers) = parse_fixes(line) {
+            fixes.extend(numbers);
+        } else if let Some(trailer) = parse_trailer(line) {
+            entries.push(trailer);
+        } else {
+            return trailers;
+        }
+    }
+
+    for trailer in &entries {
+        let list = match trailer.key.to_ascii_lowercase().as_str() {
+            "co-authored-by" => &mut trailers.co_authors,
+            "signed-off-by" => &mut trailers.signed_off_by,
+            "reviewed-by" => &mut trailers.reviewed_by,
+            _ => continue,
+        };
+        list.push(parse_person(&trailer.value));
+    }
+    trailers.fixes = fixes;
+    trailers.all = entries;
+    trailers
+}
+
+/// `Key: value`, where the key is letters, digits and dashes.
+fn parse_trailer(line: &str) -> Option<Trailer> {
+    let (key, value) = line.split_once(':')?;
+    let valid_key = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    let value = value.trim();
+    if !valid_key || value.is_empty() {
+        return None;
+    }
+    Some(Trailer {
+        key: key.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// `Fixes #12`, `Closes: #3, #4`. `None` unless the line is only that.
+fn parse_fixes(line: &str) -> Option<Vec<u64>> {
+    let (keyword, rest) = line.trim().split_once([' ', ':'])?;
+    if !FIX_KEYWORDS.contains(&keyword.to_ascii_lowercase().as_str()) {
+        return None;
+    }
+    rest.split([',', ' '])
+        .map(str::trim)
+        .filter(|part| !part.is_empty() && *part != "and")
+        .map(|part| part.strip_prefix('#')?.parse().ok())
+        .collect::<Option<Vec<u64>>>()
+        .filter(|numbers| !numbers.is_empty())
+}
+
+fn parse_person(value: &str) -> Person {
+    match value.rsplit_once('<') {
+        Some((name, email)) if email.ends_with('>') => Person {
+            name: name.trim().to_string(),
+            email: Some(email.trim_end_matches('>').trim().to_string()),
+        },
+        _ => Person {
+            name: value.trim().to_string(),
+            email: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trailers() {
+        let body = "Longer explanation: with a colon.\n\n\
+                    Co-authored-by: Alice Example <alice@example.com>\n\
+                    Signed-off-by: Bob <bob@example.com>\n\
+                    Reviewed-by: Carol\n\
+                    Fixes #12\n\
+                    Closes: #3, #4\n\
+                    Change-Id: I1234\n  continued";
+        let trailers = parse_trailers(body);
+        assert_eq!(
+            trailers.co_authors,
+            vec![Person { name: "Alice Example".to_string(), email: Some("alice@example.com".to_string()) }]
+        );
+        assert_eq!(trailers.signed_off_by[0].name, "Bob");
+        assert_eq!(trailers.reviewed_by, vec![Person { name: "Carol".to_string(), email: None }]);
+        assert_eq!(trailers.fixes, vec![12, 3, 4]);
+        assert_eq!(trailers.all.len(), 4);
+        assert_eq!(trailers.all[3].value, "I1234 continued");
+
+        // Prose in the last paragraph means there is no trailer block
+        assert_eq!(parse_trailers("Intro.\n\nSigned-off-by: Bob\nand some prose"), Trailers::default());
+        assert_eq!(parse_trailers("Fixes the crash on startup"), Trailers::default());
+        assert_eq!(parse_trailers(""), Trailers::default());
+    }
+}
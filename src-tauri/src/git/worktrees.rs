@@ -0,0 +1,110 @@
+//! Working trees attached to a repository (`git worktree`), and which
+//! branch each has checked out.
+
+use super::backend::backend;
+use super::cli::GitError;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Worktree {
+    pub path: String,
+    pub head_sha: Option<String>,
+    /// Short name of the checked-out branch; `None` when detached or bare
+    pub branch: Option<String>,
+    /// The repository's main working tree rather than a linked one
+    pub is_main: bool,
+    pub bare: bool,
+    /// Has staged, unstaged or untracked changes
+    pub dirty: bool,
+}
+
+/// Where a branch is checked out, as reported on `Branch`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchWorktree {
+    pub path: String,
+    pub is_main: bool,
+    pub dirty: bool,
+}
+
+impl From<&Worktree> for BranchWorktree {
+    fn from(worktree: &Worktree) -> Self {
+        BranchWorktree {
+            path: worktree.path.clone(),
+            is_main: worktree.is_main,
+            dirty: worktree.dirty,
+        }
+    }
+}
+
+/// All working trees, main first, each checked for uncommitted changes.
+pub fn list_worktrees(repo: &Path) -> Result<Vec<Worktree>, GitError> {
+    let git = backend();
+    let mut worktrees = Vec::new();
+    for (i, entry) in git.worktrees(repo)?.into_iter().enumerate() {
+        let dirty = !entry.bare && git.is_dirty(Path::new(&entry.path))?;
+        worktrees.push(Worktree {
+            branch: entry
+                .branch
+                .as_deref()
+                .map(|b| b.strip_prefix("refs/heads/").unwrap_or(b).to_string()),
+            path: entry.path,
+            head_sha: entry.head_sha,
+            is_main: i == 0,
+            bare: entry.bare,
+            dirty,
+        });
+    }
+    Ok(worktrees)
+}
+
+/// The working tree that has `branch` checked out, if any.
+pub fn worktree_for_branch(repo: &Path, branch: &str) -> Result<Option<Worktree>, GitError> {
+    Ok(list_worktrees(repo)?
+        .into_iter()
+        .find(|w| w.branch.as_deref() == Some(branch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::backend::{backend_for, BackendKind};
+    use crate::git::branches::list_branches;
+    use crate::git::fixture::Fixture;
+
+    #[test]
+    fn test_worktrees() {
+        let fx = Fixture::new("worktrees");
+        fx.commit("README.md", "hello\n", "Initial commit");
+        fx.git(&["branch", "feature"]);
+        fx.git(&["branch", "other"]);
+        fx.git(&["checkout", "-q", "-b", "current"]);
+
+        let linked = std::env::temp_dir().join(format!("git-viz-test-worktrees-linked-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&linked);
+        fx.git(&["worktree", "add", "-q", linked.to_str().unwrap(), "feature"]);
+        std::fs::write(linked.join("wip.txt"), "work in progress\n").unwrap();
+
+        let [cli, lib] = [backend_for(BackendKind::Cli), backend_for(BackendKind::Libgit2)];
+        let entries = cli.worktrees(fx.path()).unwrap();
+        assert_eq!(entries, lib.worktrees(fx.path()).unwrap());
+        assert_eq!(entries, lib.worktrees(&linked).unwrap());
+        assert_eq!(entries.len(), 2);
+        assert!(cli.is_dirty(&linked).unwrap() && lib.is_dirty(&linked).unwrap());
+        assert!(!cli.is_dirty(fx.path()).unwrap() && !lib.is_dirty(fx.path()).unwrap());
+
+        let worktree = worktree_for_branch(fx.path(), "feature").unwrap().unwrap();
+        assert!(!worktree.is_main && worktree.dirty);
+        assert_eq!(Path::new(&worktree.path), linked.canonicalize().unwrap());
+
+        let branches = list_branches(fx.path(), "main").unwrap();
+        let flagged = |name: &str| branches.iter().find(|b| b.name == name).unwrap().worktree.clone();
+        assert_eq!(flagged("feature"), Some(BranchWorktree::from(&worktree)));
+        assert!(flagged("current").is_some_and(|w| w.is_main && !w.dirty));
+        assert_eq!(flagged("other"), None);
+
+        fx.git(&["worktree", "remove", "--force", linked.to_str().unwrap()]);
+    }
+}
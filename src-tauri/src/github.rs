@@ -1,22 +1,49 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use std::process::Command;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
 
+use crate::dates::parse_since;
 use crate::git;
 
+const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Cached responses not used for this long are dropped.
+const CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Past this many bytes, the least recently used responses are dropped.
+const CACHE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GitHubInfo {
     pub owner: String,
     pub repo: String,
     pub gh_available: bool,
+    /// A token is configured, so the API is reachable without `gh`
+    pub token_available: bool,
 }
 
+/// An open PR with what decides whether it is ready to land.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OpenPR {
     pub number: i64,
     pub branch_name: String,
+    pub title: String,
+    pub author_login: String,
+    pub author_avatar: String,
+    pub draft: bool,
+    /// Users (by login) and teams (by `org/slug`) yet to review
+    pub requested_reviewers: Vec<String>,
+    pub review_decision: Option<ReviewDecision>,
+    pub checks: Option<CheckStatus>,
+    pub labels: Vec<String>,
+    pub mergeable: MergeableState,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -31,32 +58,549 @@ pub struct MergedPR {
     pub merged_at: String,
     pub merge_commit_sha: String,
     pub commit_count: i64,
+    pub labels: Vec<String>,
 }
 
-/// Response from GitHub API for a pull request
+/// Merged PRs from one page of the API, and where to continue from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergedPRPage {
+    pub prs: Vec<MergedPR>,
+    /// Pass back to fetch older PRs; `None` once the repo's first PR (or
+    /// the `since` cutoff) is reached
+    pub next_cursor: Option<String>,
+}
+
+/// A pull request as listed by the REST API; the rest of it comes from
+/// `get_pr_details`.
 #[derive(Debug, Deserialize)]
 struct GitHubPR {
     number: i64,
-    title: String,
-    state: String,
-    merged_at: Option<String>,
-    created_at: String,
-    merge_commit_sha: Option<String>,
-    commits: Option<i64>,
-    head: GitHubHead,
-    user: GitHubUser,
 }
 
 #[derive(Debug, Deserialize)]
-struct GitHubHead {
-    #[serde(rename = "ref")]
-    ref_name: String,
+struct GitHubLabel {
+    name: String,
+}
+
+/// `[github]` table of the app-level settings file. It is never read from a
+/// repo's `.git-visualizer.toml`, which is usually committed.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct GitHubSettings {
+    token: Option<String>,
+    api_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    github: GitHubSettings,
+}
+
+/// Serialized for the frontend as `{ kind, ... }`, so it can tell a rate
+/// limit (and when it lifts) from other failures.
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum GitHubError {
+    #[error("GitHub rate limit exhausted; try again after {}", .reset_at.with_timezone(&chrono::Local).format("%H:%M"))]
+    #[serde(rename_all = "camelCase")]
+    RateLimited { reset_at: DateTime<Utc> },
+
+    #[error("GitHub API {status}: {message}")]
+    Api { status: u16, message: String },
+
+    #[error("GitHub GraphQL error: {message}")]
+    GraphQL { message: String },
+
+    #[error("Network error: {message}")]
+    Network { message: String },
+
+    #[error("No GitHub token configured (set GITHUB_PAT) and gh CLI is unavailable: {message}")]
+    GhUnavailable { message: String },
+
+    #[error("gh api failed: {message}")]
+    Gh { message: String },
+
+    #[error("Failed to parse GitHub response: {message}")]
+    Parse { message: String },
+
+    #[error("{message}")]
+    Invalid { message: String },
+}
+
+/// Until when each API's rate limit (`core` for REST, `graphql`) is used up,
+/// keyed by API URL too. Requests fail fast until then instead of spending
+/// the wait hitting GitHub.
+static EXHAUSTED: Mutex<BTreeMap<String, DateTime<Utc>>> = Mutex::new(BTreeMap::new());
+
+/// Talks to the GitHub REST API directly when a token is configured, and
+/// through `gh api` (with whatever account `gh` is logged in to) otherwise.
+#[derive(Clone)]
+pub struct Client {
+    api_url: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+    /// Where GET responses are kept for revalidating with their ETag
+    cache_dir: Option<PathBuf>,
+}
+
+impl Client {
+    pub fn new(api_url: &str, token: Option<String>) -> Self {
+        Client {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            token: token.filter(|t| !t.is_empty()),
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(30)).build(),
+            cache_dir: None,
+        }
+    }
+
+    /// Cache GET responses in `dir`. A cached response is revalidated with
+    /// `If-None-Match`, and GitHub's 304 doesn't count against the limit.
+    /// The cache is kept under `CACHE_MAX_BYTES` and `CACHE_MAX_AGE`, checked
+    /// at most once an hour.
+    pub fn with_cache(mut self, dir: PathBuf) -> Self {
+        static PRUNED: Mutex<Option<std::time::Instant>> = Mutex::new(None);
+        let mut pruned = PRUNED.lock().unwrap();
+        if pruned.is_none_or(|at| at.elapsed() > Duration::from_secs(60 * 60)) {
+            prune_cache(&dir, CACHE_MAX_AGE, CACHE_MAX_BYTES);
+            *pruned = Some(std::time::Instant::now());
+        }
+        drop(pruned);
+        self.cache_dir = Some(dir);
+        self
+    }
+
+    /// Token from `GITHUB_PAT` or `GITHUB_TOKEN`, else `[github] token` in the
+    /// app settings; API URL from `GITHUB_API_URL` or `[github] api-url`
+    /// (for GitHub Enterprise), else api.github.com. Responses are cached in
+    /// the user's cache directory.
+    pub fn from_settings() -> Result<Self, GitHubError> {
+        let settings = git::app_settings::<ConfigFile>()
+            .map_err(|e| GitHubError::Invalid { message: e.to_string() })?
+            .unwrap_or_default()
+            .github;
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let token = env("GITHUB_PAT").or_else(|| env("GITHUB_TOKEN")).or(settings.token);
+        let api_url = env("GITHUB_API_URL")
+            .or(settings.api_url)
+            .unwrap_or_else(|| DEFAULT_API_URL.to_string());
+        let client = Client::new(&api_url, token.map(|t| t.trim().to_string()));
+        Ok(match dirs::cache_dir() {
+            Some(dir) => client.with_cache(dir.join("git-visualizer").join("github")),
+            None => client,
+        })
+    }
+
+    pub fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// GET `path` (relative to the API root, e.g. `repos/o/r/pulls`), parse
+    /// the JSON response, and return it with the path of the next page from
+    /// the `Link` header, if there is one.
+    fn get_page<T: DeserializeOwned>(&self, path: &str) -> Result<(T, Option<String>), GitHubError> {
+        let response = self.execute("GET", path, None, Some("core"))?;
+        let value = serde_json::from_str(&response.body).map_err(|e| GitHubError::Parse { message: e.to_string() })?;
+        let next = response
+            .header("link")
+            .and_then(next_link)
+            .map(|url| self.relative_path(&url));
+        Ok((value, next))
+    }
+
+    /// Make an absolute API URL (as found in `Link` headers) relative to the
+    /// API root, so it can be requested like any other path.
+    fn relative_path(&self, url: &str) -> String {
+        if let Some(path) = url.strip_prefix(&self.api_url) {
+            return path.trim_start_matches('/').to_string();
+        }
+        // `gh` talks to its own host; drop the scheme and host
+        let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+        without_scheme.split_once('/').map_or("", |(_, path)| path).to_string()
+    }
+
+    /// Run a GraphQL query and return its `data`. Fields that failed (like a
+    /// PR number that doesn't exist) come back null rather than failing the
+    /// whole query.
+    fn graphql<T: DeserializeOwned>(&self, query: &str, variables: serde_json::Value) -> Result<T, GitHubError> {
+        let body = serde_json::json!({ "query": query, "variables": variables }).to_string();
+        let response = self.execute("POST", "graphql", Some(&body), Some("graphql"))?;
+        let parsed: GraphQLResponse<T> =
+            serde_json::from_str(&response.body).map_err(|e| GitHubError::Parse { message: e.to_string() })?;
+        // GraphQL reports an exhausted limit with a 200
+        if parsed.errors.iter().any(|e| e.kind.as_deref() == Some("RATE_LIMITED")) {
+            return Err(self.exhausted("graphql", &response));
+        }
+        parsed.data.ok_or_else(|| {
+            let messages: Vec<String> = parsed.errors.into_iter().map(|e| e.message).collect();
+            GitHubError::GraphQL { message: messages.join("; ") }
+        })
+    }
+
+    /// Current quota of the REST and GraphQL APIs. Asking doesn't use any.
+    pub fn rate_limit(&self) -> Result<RateLimits, GitHubError> {
+        let response = self.execute("GET", "rate_limit", None, None)?;
+        let parsed: RateLimitResponse =
+            serde_json::from_str(&response.body).map_err(|e| GitHubError::Parse { message: e.to_string() })?;
+        let mut exhausted = EXHAUSTED.lock().unwrap();
+        for (resource, quota) in [("core", &parsed.resources.core), ("graphql", &parsed.resources.graphql)] {
+            if quota.remaining > 0 {
+                exhausted.remove(&self.limit_key(resource));
+            }
+        }
+        Ok(RateLimits {
+            core: parsed.resources.core.into(),
+            graphql: parsed.resources.graphql.into(),
+        })
+    }
+
+    /// Send a request counted against `resource`'s rate limit (`None` for
+    /// requests that aren't), going through the cache for GETs and turning
+    /// error statuses into errors.
+    fn execute(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+        resource: Option<&str>,
+    ) -> Result<Response, GitHubError> {
+        if let Some(resource) = resource {
+            if let Some(&reset_at) = EXHAUSTED.lock().unwrap().get(&self.limit_key(resource)) {
+                if reset_at > Utc::now() {
+                    return Err(GitHubError::RateLimited { reset_at });
+                }
+            }
+        }
+
+        let cache_path = (method == "GET").then(|| self.cache_path(path)).flatten();
+        let cached: Option<CachedResponse> = cache_path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|text| serde_json::from_str(&text).ok());
+
+        let response = self.send(method, path, body, cached.as_ref().map(|c| c.etag.as_str()))?;
+        if response.status == 304 {
+            if let (Some(cached), Some(path)) = (cached, &cache_path) {
+                // Recently used entries are the last to be pruned
+                let touched = std::fs::File::options().append(true).open(path);
+                let _ = touched.and_then(|file| file.set_modified(std::time::SystemTime::now()));
+                return Ok(cached.into());
+            }
+        }
+        if response.status >= 400 {
+            let rate_limited = matches!(response.status, 403 | 429)
+                && (response.header("x-ratelimit-remaining") == Some("0") || response.header("retry-after").is_some());
+            if rate_limited {
+                return Err(self.exhausted(resource.unwrap_or("core"), &response));
+            }
+            let parsed: serde_json::Value = serde_json::from_str(&response.body).unwrap_or_default();
+            let message = parsed["message"].as_str().unwrap_or("Unknown error").to_string();
+            return Err(GitHubError::Api {
+                status: response.status,
+                message,
+            });
+        }
+
+        if let (Some(path), Some(etag)) = (cache_path, response.header("etag")) {
+            let entry = CachedResponse {
+                etag: etag.to_string(),
+                link: response.header("link").map(str::to_string),
+                body: response.body.clone(),
+            };
+            // A cache that can't be written only costs the next refresh
+            let _ = write_atomically(&path, serde_json::to_string(&entry).unwrap_or_default().as_bytes());
+        }
+        Ok(response)
+    }
+
+    /// Remember that `resource` ran out, until the reset time (or the
+    /// `Retry-After` of a secondary limit) the response gives.
+    fn exhausted(&self, resource: &str, response: &Response) -> GitHubError {
+        let header = |name: &str| response.header(name).and_then(|v| v.trim().parse::<i64>().ok());
+        let reset_at = match (header("retry-after"), header("x-ratelimit-reset")) {
+            (Some(seconds), _) => Utc::now() + chrono::Duration::seconds(seconds),
+            (None, Some(reset)) => DateTime::from_timestamp(reset, 0).unwrap_or_else(Utc::now),
+            // GitHub's advice when it doesn't say: wait a minute
+            (None, None) => Utc::now() + chrono::Duration::seconds(60),
+        };
+        EXHAUSTED.lock().unwrap().insert(self.limit_key(resource), reset_at);
+        GitHubError::RateLimited { reset_at }
+    }
+
+    fn limit_key(&self, resource: &str) -> String {
+        format!("{} {}", self.api_url, resource)
+    }
+
+    /// Where the response to GET `path` is cached: named after a SHA-256 of
+    /// the API URL and path, so the name stays the same across builds.
+    fn cache_path(&self, path: &str) -> Option<PathBuf> {
+        use sha2::{Digest, Sha256};
+        let dir = self.cache_dir.as_ref()?;
+        let digest = Sha256::new().chain_update(&self.api_url).chain_update("\n").chain_update(path).finalize();
+        let name: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+        Some(dir.join(format!("{name}.json")))
+    }
+
+    /// Send one request, over HTTP with the token or through `gh`.
+    fn send(&self, method: &str, path: &str, body: Option<&str>, etag: Option<&str>) -> Result<Response, GitHubError> {
+        let Some(token) = &self.token else {
+            return gh_api(method, path, body, etag);
+        };
+        let url = match path {
+            "graphql" => self.graphql_url(),
+            _ => format!("{}/{}", self.api_url, path.trim_start_matches('/')),
+        };
+        let mut request = self
+            .agent
+            .request(method, &url)
+            .set("Accept", "application/vnd.github+json")
+            .set("Authorization", &format!("Bearer {token}"))
+            .set("X-GitHub-Api-Version", "2022-11-28")
+            .set("User-Agent", "git-visualizer");
+        if let Some(etag) = etag {
+            request = request.set("If-None-Match", etag);
+        }
+        let result = match body {
+            Some(body) => request.set("Content-Type", "application/json").send_string(body),
+            None => request.call(),
+        };
+        let resp = match result {
+            Ok(resp) | Err(ureq::Error::Status(_, resp)) => resp,
+            Err(e) => return Err(GitHubError::Network { message: e.to_string() }),
+        };
+        let status = resp.status();
+        let headers = resp
+            .headers_names()
+            .into_iter()
+            .filter_map(|name| resp.header(&name).map(|value| (name.to_ascii_lowercase(), value.to_string())))
+            .collect();
+        let body = resp.into_string().map_err(|e| GitHubError::Network { message: e.to_string() })?;
+        Ok(Response { status, headers, body })
+    }
+
+    /// api.github.com serves GraphQL at `/graphql`; GitHub Enterprise serves
+    /// REST at `/api/v3` and GraphQL at `/api/graphql`.
+    fn graphql_url(&self) -> String {
+        match self.api_url.strip_suffix("/v3") {
+            Some(root) => format!("{root}/graphql"),
+            None => format!("{}/graphql", self.api_url),
+        }
+    }
+}
+
+struct Response {
+    status: u16,
+    /// Names lowercased
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A GET response kept on disk, keyed by API URL and path.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResponse {
+    etag: String,
+    link: Option<String>,
+    body: String,
+}
+
+/// Write `contents` to a temporary file next to `path` and rename it into
+/// place, so readers (other threads, another instance of the app) never see
+/// half an entry.
+fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let unique = format!("{}.{}.tmp", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+    let temp = path.with_extension(unique);
+    std::fs::write(&temp, contents).and_then(|_| std::fs::rename(&temp, path)).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp);
+    })
+}
+
+/// Drop cached responses last used more than `max_age` ago, then the least
+/// recently used ones until the rest fit in `max_bytes`. Leftover temporary
+/// files count as entries too.
+fn prune_cache(dir: &Path, max_age: Duration, max_bytes: u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let now = std::time::SystemTime::now();
+    let mut kept = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else { continue };
+        if !meta.is_file() {
+            continue;
+        }
+        let age = meta.modified().ok().and_then(|m| now.duration_since(m).ok()).unwrap_or_default();
+        if age > max_age {
+            let _ = std::fs::remove_file(entry.path());
+        } else {
+            kept.push((age, meta.len(), entry.path()));
+        }
+    }
+
+    // Oldest first
+    kept.sort_by_key(|(age, ..)| std::cmp::Reverse(*age));
+    let mut total: u64 = kept.iter().map(|(_, len, _)| len).sum();
+    for (_, len, path) in kept {
+        if total <= max_bytes {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+}
+
+impl From<CachedResponse> for Response {
+    fn from(cached: CachedResponse) -> Self {
+        let mut headers = vec![("etag".to_string(), cached.etag)];
+        headers.extend(cached.link.map(|link| ("link".to_string(), link)));
+        Response {
+            status: 200,
+            headers,
+            body: cached.body,
+        }
+    }
+}
+
+/// The `rel="next"` URL of a `Link` header.
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|p| p.trim() == "rel=\"next\"")
+            .then(|| url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
 }
 
 #[derive(Debug, Deserialize)]
-struct GitHubUser {
-    login: String,
-    avatar_url: String,
+struct GraphQLResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQLError {
+    message: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+/// Remaining quota of one API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quota {
+    pub limit: u32,
+    pub remaining: u32,
+    pub used: u32,
+    /// When `remaining` goes back up to `limit`
+    pub reset_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimits {
+    /// REST requests
+    pub core: Quota,
+    /// GraphQL points (a query costs about one per 100 nodes it asks for)
+    pub graphql: Quota,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitResponse {
+    resources: RateLimitResources,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitResources {
+    core: GitHubQuota,
+    graphql: GitHubQuota,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubQuota {
+    limit: u32,
+    remaining: u32,
+    used: u32,
+    reset: i64,
+}
+
+impl From<GitHubQuota> for Quota {
+    fn from(quota: GitHubQuota) -> Self {
+        Quota {
+            limit: quota.limit,
+            remaining: quota.remaining,
+            used: quota.used,
+            reset_at: DateTime::from_timestamp(quota.reset, 0)
+                .unwrap_or_default()
+                .to_rfc3339(),
+        }
+    }
+}
+
+/// Run `gh api --include` and split its output into status, headers and
+/// body. `gh` exits non-zero on error statuses, but still prints them.
+fn gh_api(method: &str, path: &str, body: Option<&str>, etag: Option<&str>) -> Result<Response, GitHubError> {
+    let unavailable = |e: std::io::Error| GitHubError::GhUnavailable { message: e.to_string() };
+    let mut command = Command::new("gh");
+    command.args(["api", "--include", "--method", method, path]);
+    if let Some(etag) = etag {
+        command.args(["--header", &format!("If-None-Match: {etag}")]);
+    }
+    if body.is_some() {
+        command.args(["--input", "-"]).stdin(Stdio::piped());
+    }
+    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().map_err(unavailable)?;
+    if let (Some(body), Some(mut stdin)) = (body, child.stdin.take()) {
+        stdin.write_all(body.as_bytes()).map_err(unavailable)?;
+    }
+    let output = child.wait_with_output().map_err(unavailable)?;
+
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|_| GitHubError::Gh { message: "Invalid UTF-8 in gh output".to_string() })?;
+    let mut lines = stdout.split_inclusive('\n');
+    // e.g. `HTTP/2.0 200 OK`; missing when gh failed before sending anything
+    // (such as when it isn't logged in)
+    let status = lines
+        .next()
+        .filter(|line| line.starts_with("HTTP/"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok());
+    let Some(status) = status else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitHubError::Gh { message: stderr.trim().to_string() });
+    };
+    let mut headers = Vec::new();
+    // Headers, up to the first blank line
+    for line in lines.by_ref() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    Ok(Response {
+        status,
+        headers,
+        body: lines.collect(),
+    })
 }
 
 /// Parse GitHub owner/repo from a git remote URL.
@@ -94,13 +638,14 @@ pub fn parse_remote_url(url: &str) -> Option<(String, String)> {
 }
 
 /// Get GitHub info from a local repository by reading its remote URL.
-pub fn get_github_info(repo_path: &Path) -> Result<GitHubInfo, String> {
+pub fn get_github_info(repo_path: &Path) -> Result<GitHubInfo, GitHubError> {
     // Get the remote URL
     let remote_url = git::cli::run(repo_path, &["remote", "get-url", "origin"])
-        .map_err(|e| format!("Failed to get remote URL: {e}"))?;
+        .map_err(|e| GitHubError::Invalid { message: format!("Failed to get remote URL: {e}") })?;
 
-    let (owner, repo) = parse_remote_url(&remote_url)
-        .ok_or_else(|| format!("Could not parse GitHub info from remote URL: {}", remote_url.trim()))?;
+    let (owner, repo) = parse_remote_url(&remote_url).ok_or_else(|| {
+        GitHubError::Invalid { message: format!("Could not parse GitHub info from remote URL: {}", remote_url.trim()) }
+    })?;
 
     // Check if gh CLI is available
     let gh_available = Command::new("gh")
@@ -113,162 +658,844 @@ pub fn get_github_info(repo_path: &Path) -> Result<GitHubInfo, String> {
         owner,
         repo,
         gh_available,
+        token_available: Client::from_settings()?.has_token(),
     })
 }
 
-/// Response type for PR commits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "kebab-case", deserialize = "SCREAMING_SNAKE_CASE"))]
+pub enum PrState {
+    Open,
+    Closed,
+    Merged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "kebab-case", deserialize = "SCREAMING_SNAKE_CASE"))]
+pub enum ReviewDecision {
+    Approved,
+    ChangesRequested,
+    ReviewRequired,
+}
+
+/// Whether GitHub can merge the PR without conflicts. `Unknown` while GitHub
+/// is still computing it in the background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "kebab-case", deserialize = "SCREAMING_SNAKE_CASE"))]
+pub enum MergeableState {
+    Mergeable,
+    Conflicting,
+    Unknown,
+}
+
+/// Combined state of every check run and commit status on the PR's head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "kebab-case", deserialize = "SCREAMING_SNAKE_CASE"))]
+pub enum CheckStatus {
+    Success,
+    Pending,
+    Expected,
+    Failure,
+    Error,
+}
+
+/// Everything the map shows about a PR, open or closed, from one GraphQL
+/// query per batch of PRs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequestDetails {
+    pub number: i64,
+    pub title: String,
+    pub state: PrState,
+    pub draft: bool,
+    pub branch_name: String,
+    pub base_branch: String,
+    /// `ghost` when the account was deleted
+    pub author_login: String,
+    pub author_avatar: String,
+    pub created_at: String,
+    pub merged_at: Option<String>,
+    pub merge_commit_sha: Option<String>,
+    pub additions: i64,
+    pub deletions: i64,
+    pub commit_count: i64,
+    /// Full SHAs, oldest first; only the first 100 of `commit_count`
+    pub commits: Vec<String>,
+    pub labels: Vec<String>,
+    /// Users (by login) and teams (by `org/slug`) yet to review
+    pub requested_reviewers: Vec<String>,
+    /// `None` when the base branch doesn't require reviews
+    pub review_decision: Option<ReviewDecision>,
+    pub mergeable: MergeableState,
+    /// `None` when the head commit has no checks
+    pub checks: Option<CheckStatus>,
+}
+
+/// PRs per GraphQL query, keeping each well under GitHub's node limits
+const DETAILS_BATCH: usize = 25;
+
+const PR_DETAILS_FRAGMENT: &str = "
+fragment PrDetails on PullRequest {
+  number title state isDraft headRefName baseRefName createdAt mergedAt
+  author { login avatarUrl }
+  mergeCommit { oid }
+  additions deletions
+  commits(first: 100) { totalCount nodes { commit { oid } } }
+  labels(first: 20) { nodes { name } }
+  reviewRequests(first: 20) {
+    nodes { requestedReviewer { ... on User { login } ... on Team { combinedSlug } ... on Bot { login } } }
+  }
+  reviewDecision
+  mergeable
+  head: commits(last: 1) { nodes { commit { statusCheckRollup { state } } } }
+}";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Nodes<T> {
+    #[serde(default)]
+    total_count: i64,
+    nodes: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlPullRequest {
+    number: i64,
+    title: String,
+    state: PrState,
+    is_draft: bool,
+    head_ref_name: String,
+    base_ref_name: String,
+    created_at: String,
+    merged_at: Option<String>,
+    author: Option<GqlActor>,
+    merge_commit: Option<GqlCommit>,
+    additions: i64,
+    deletions: i64,
+    commits: Nodes<GqlPrCommit>,
+    labels: Nodes<GitHubLabel>,
+    review_requests: Nodes<GqlReviewRequest>,
+    review_decision: Option<ReviewDecision>,
+    mergeable: MergeableState,
+    head: Nodes<GqlPrCommit>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlActor {
+    login: String,
+    avatar_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlPrCommit {
+    commit: GqlCommit,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlCommit {
+    #[serde(default)]
+    oid: String,
+    status_check_rollup: Option<GqlRollup>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlReviewRequest {
+    /// Null for reviewers the token can't see (e.g. a secret team)
+    requested_reviewer: Option<GqlReviewer>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlReviewer {
+    login: Option<String>,
+    combined_slug: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
-struct GitHubCommit {
-    sha: String,
+struct GqlRollup {
+    state: CheckStatus,
+}
+
+impl From<GqlPullRequest> for PullRequestDetails {
+    fn from(pr: GqlPullRequest) -> Self {
+        let (author_login, author_avatar) = match pr.author {
+            Some(author) => (author.login, author.avatar_url),
+            None => ("ghost".to_string(), String::new()),
+        };
+        PullRequestDetails {
+            number: pr.number,
+            title: pr.title,
+            state: pr.state,
+            draft: pr.is_draft,
+            branch_name: pr.head_ref_name,
+            base_branch: pr.base_ref_name,
+            author_login,
+            author_avatar,
+            created_at: pr.created_at,
+            merged_at: pr.merged_at,
+            merge_commit_sha: pr.merge_commit.map(|c| c.oid),
+            additions: pr.additions,
+            deletions: pr.deletions,
+            commit_count: pr.commits.total_count,
+            commits: pr.commits.nodes.into_iter().map(|n| n.commit.oid).collect(),
+            labels: pr.labels.nodes.into_iter().map(|l| l.name).collect(),
+            requested_reviewers: pr
+                .review_requests
+                .nodes
+                .into_iter()
+                .filter_map(|r| r.requested_reviewer)
+                .filter_map(|r| r.login.or(r.combined_slug))
+                .collect(),
+            review_decision: pr.review_decision,
+            mergeable: pr.mergeable,
+            checks: pr
+                .head
+                .nodes
+                .into_iter()
+                .next()
+                .and_then(|n| n.commit.status_check_rollup)
+                .map(|r| r.state),
+        }
+    }
+}
+
+/// Fetch details of the given PRs in batches, one GraphQL query per batch.
+/// PR numbers that don't exist are left out; the rest keep their order.
+pub fn get_pr_details(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_numbers: &[i64],
+) -> Result<Vec<PullRequestDetails>, GitHubError> {
+    use std::collections::{HashMap, HashSet};
+
+    let mut seen = HashSet::new();
+    let numbers: Vec<i64> = pr_numbers.iter().copied().filter(|n| seen.insert(*n)).collect();
+
+    #[derive(Deserialize)]
+    struct Data {
+        repository: Option<HashMap<String, Option<GqlPullRequest>>>,
+    }
+
+    let mut details = Vec::new();
+    for batch in numbers.chunks(DETAILS_BATCH) {
+        let fields: String = batch
+            .iter()
+            .map(|n| format!("pr{n}: pullRequest(number: {n}) {{ ...PrDetails }}\n"))
+            .collect();
+        let query = format!(
+            "query($owner: String!, $name: String!) {{\n  repository(owner: $owner, name: $name) {{\n{fields}  }}\n}}\n{PR_DETAILS_FRAGMENT}"
+        );
+        let data: Data = client.graphql(&query, serde_json::json!({ "owner": owner, "name": repo }))?;
+        let mut prs = data
+            .repository
+            .ok_or_else(|| GitHubError::Invalid { message: format!("Repository {owner}/{repo} not found") })?;
+        for n in batch {
+            if let Some(Some(pr)) = prs.remove(&format!("pr{n}")) {
+                details.push(pr.into());
+            }
+        }
+    }
+    Ok(details)
 }
 
-/// Fetch commit SHAs for multiple PRs in parallel.
+impl From<PullRequestDetails> for OpenPR {
+    fn from(pr: PullRequestDetails) -> Self {
+        OpenPR {
+            number: pr.number,
+            branch_name: pr.branch_name,
+            title: pr.title,
+            author_login: pr.author_login,
+            author_avatar: pr.author_avatar,
+            draft: pr.draft,
+            requested_reviewers: pr.requested_reviewers,
+            review_decision: pr.review_decision,
+            checks: pr.checks,
+            labels: pr.labels,
+            mergeable: pr.mergeable,
+        }
+    }
+}
+
+/// Fetch commit SHAs for multiple PRs, batched through `get_pr_details`.
 /// Returns a map of PR number to list of short SHAs.
 pub fn get_pr_commits(
+    client: &Client,
     owner: &str,
     repo: &str,
     pr_numbers: &[i64],
-) -> Result<std::collections::HashMap<i64, Vec<String>>, String> {
-    use std::collections::HashMap;
-    use std::thread;
-
-    let mut results = HashMap::new();
-
-    // Fetch commits for each PR (could be parallelized further with rayon if needed)
-    let handles: Vec<_> = pr_numbers
-        .iter()
-        .map(|&num| {
-            let owner = owner.to_string();
-            let repo = repo.to_string();
-            thread::spawn(move || {
-                let output = Command::new("gh")
-                    .args([
-                        "api",
-                        &format!("repos/{owner}/{repo}/pulls/{num}/commits?per_page=100"),
-                    ])
-                    .output();
-
-                match output {
-                    Ok(out) if out.status.success() => {
-                        let stdout = String::from_utf8_lossy(&out.stdout);
-                        if let Ok(commits) = serde_json::from_str::<Vec<GitHubCommit>>(&stdout) {
-                            let shas: Vec<String> = commits
-                                .iter()
-                                .map(|c| c.sha[..7.min(c.sha.len())].to_string())
-                                .collect();
-                            Some((num, shas))
-                        } else {
-                            None
-                        }
-                    }
-                    _ => None,
-                }
-            })
+) -> Result<std::collections::HashMap<i64, Vec<String>>, GitHubError> {
+    Ok(get_pr_details(client, owner, repo, pr_numbers)?
+        .into_iter()
+        .map(|pr| {
+            let shas = pr.commits.iter().map(|sha| sha[..7.min(sha.len())].to_string()).collect();
+            (pr.number, shas)
         })
-        .collect();
+        .collect())
+}
 
-    for handle in handles {
-        if let Ok(Some((num, shas))) = handle.join() {
-            results.insert(num, shas);
-        }
+/// Whether GitHub timestamp `time` is before `since`. Unparseable times are
+/// never before it.
+fn is_before(time: &str, since: Option<DateTime<Utc>>) -> bool {
+    match (since, DateTime::parse_from_rfc3339(time)) {
+        (Some(since), Ok(time)) => time < since,
+        _ => false,
+    }
+}
+
+const MERGED_PRS_QUERY: &str = "
+query($owner: String!, $name: String!, $base: String!, $after: String) {
+  repository(owner: $owner, name: $name) {
+    pullRequests(states: MERGED, baseRefName: $base, first: 100, after: $after, orderBy: {field: CREATED_AT, direction: DESC}) {
+      pageInfo { hasNextPage endCursor }
+      nodes { ...MergedPr }
     }
+  }
+}";
+
+/// Search rather than list, so the cutoff applies to the merge date.
+const MERGED_PRS_SINCE_QUERY: &str = "
+query($query: String!, $after: String) {
+  search(type: ISSUE, query: $query, first: 100, after: $after) {
+    pageInfo { hasNextPage endCursor }
+    nodes { ...MergedPr }
+  }
+}";
+
+const MERGED_PR_FRAGMENT: &str = "
+fragment MergedPr on PullRequest {
+  number title headRefName createdAt mergedAt
+  author { login avatarUrl }
+  mergeCommit { oid }
+  commits { totalCount }
+  labels(first: 20) { nodes { name } }
+}";
 
-    Ok(results)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlMergedPullRequest {
+    number: i64,
+    title: String,
+    head_ref_name: String,
+    created_at: String,
+    merged_at: Option<String>,
+    author: Option<GqlActor>,
+    merge_commit: Option<GqlCommit>,
+    commits: GqlCount,
+    labels: Nodes<GitHubLabel>,
 }
 
-/// Fetch merged PRs from GitHub using the gh CLI.
-/// Returns PRs merged into the specified base branch.
-pub fn get_merged_prs(
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlCount {
+    total_count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlPageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+/// Fetch one page of PRs merged into the specified base branch, newest
+/// first by creation, which unlike the update time never changes. `cursor`
+/// is the `next_cursor` of the previous page (`None` starts from the newest
+/// PRs); it's GitHub's connection cursor, so PRs merged or updated in the
+/// meantime don't shift later pages. With `since`, only PRs merged since
+/// then are searched for, however long before it they were opened.
+pub fn get_merged_prs_page(
+    client: &Client,
     owner: &str,
     repo: &str,
     base_branch: &str,
-    limit: usize,
-) -> Result<Vec<MergedPR>, String> {
-    // Use gh api to fetch closed PRs
-    // We fetch more than we need since not all closed PRs are merged
-    let fetch_limit = limit * 2;
-
-    let output = Command::new("gh")
-        .args([
-            "api",
-            &format!(
-                "repos/{owner}/{repo}/pulls?state=closed&base={base_branch}&per_page={fetch_limit}&sort=updated&direction=desc"
-            ),
-        ])
-        .output()
-        .map_err(|e| format!("Failed to run gh CLI: {e}"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("gh api failed: {stderr}"));
+    cursor: Option<&str>,
+    since: Option<&str>,
+) -> Result<MergedPRPage, GitHubError> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Data {
+        repository: Option<Repository>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Repository {
+        pull_requests: Connection,
+    }
+    #[derive(Deserialize)]
+    struct SearchData {
+        search: Connection,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Connection {
+        page_info: GqlPageInfo,
+        nodes: Vec<GqlMergedPullRequest>,
     }
 
-    let stdout = String::from_utf8(output.stdout)
-        .map_err(|_| "Invalid UTF-8 in gh output")?;
-
-    let prs: Vec<GitHubPR> = serde_json::from_str(&stdout)
-        .map_err(|e| format!("Failed to parse GitHub response: {e}"))?;
+    let since = since.map(parse_since).transpose().map_err(|message| GitHubError::Invalid { message })?;
+    let connection = match since {
+        Some(since) => {
+            let date = since.to_rfc3339_opts(SecondsFormat::Secs, false);
+            let query = format!("repo:{owner}/{repo} is:pr is:merged base:{base_branch} merged:>={date} sort:created-desc");
+            let variables = serde_json::json!({ "query": query, "after": cursor });
+            let data: SearchData = client.graphql(&format!("{MERGED_PRS_SINCE_QUERY}{MERGED_PR_FRAGMENT}"), variables)?;
+            data.search
+        }
+        None => {
+            let variables = serde_json::json!({ "owner": owner, "name": repo, "base": base_branch, "after": cursor });
+            let data: Data = client.graphql(&format!("{MERGED_PRS_QUERY}{MERGED_PR_FRAGMENT}"), variables)?;
+            data
+                .repository
+                .ok_or_else(|| GitHubError::Invalid { message: format!("Repository {owner}/{repo} not found") })?
+                .pull_requests
+        }
+    };
 
-    // Filter to only merged PRs and convert to our type
-    let merged: Vec<MergedPR> = prs
+    let merged: Vec<MergedPR> = connection
+        .nodes
         .into_iter()
         .filter_map(|pr| {
-            // Only include PRs that were actually merged
-            let merged_at = pr.merged_at?;
-            let merge_commit_sha = pr.merge_commit_sha?;
-
+            let merged_at = pr.merged_at.filter(|at| !is_before(at, since))?;
+            let (author_login, author_avatar) = match pr.author {
+                Some(author) => (author.login, author.avatar_url),
+                None => ("ghost".to_string(), String::new()),
+            };
             Some(MergedPR {
                 number: pr.number,
                 title: pr.title,
-                branch_name: pr.head.ref_name,
-                author_login: pr.user.login,
-                author_avatar: pr.user.avatar_url,
+                branch_name: pr.head_ref_name,
+                author_login,
+                author_avatar,
                 created_at: pr.created_at,
                 merged_at,
-                merge_commit_sha,
-                commit_count: pr.commits.unwrap_or(1),
+                merge_commit_sha: pr.merge_commit?.oid,
+                commit_count: pr.commits.total_count.max(1),
+                labels: pr.labels.nodes.into_iter().map(|l| l.name).collect(),
             })
         })
-        .take(limit)
         .collect();
 
-    Ok(merged)
+    let page_info = connection.page_info;
+    Ok(MergedPRPage {
+        prs: merged,
+        next_cursor: page_info.end_cursor.filter(|_| page_info.has_next_page),
+    })
 }
 
-/// Fetch all currently open PRs for a repo using the gh CLI.
-/// Returns the PR number and head branch name for each open PR.
-pub fn get_open_prs(owner: &str, repo: &str) -> Result<Vec<OpenPR>, String> {
-    let output = Command::new("gh")
-        .args([
-            "api",
-            &format!("repos/{owner}/{repo}/pulls?state=open&per_page=100"),
-        ])
-        .output()
-        .map_err(|e| format!("Failed to run gh CLI: {e}"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("gh api failed: {stderr}"));
+/// Fetch up to `limit` PRs merged into the specified base branch, following
+/// pages as needed, optionally only those merged since a date.
+pub fn get_merged_prs(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    base_branch: &str,
+    limit: usize,
+    since: Option<&str>,
+) -> Result<Vec<MergedPR>, GitHubError> {
+    let mut merged = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = get_merged_prs_page(client, owner, repo, base_branch, cursor.as_deref(), since)?;
+        merged.extend(page.prs);
+        cursor = page.next_cursor;
+        if merged.len() >= limit || cursor.is_none() {
+            break;
+        }
     }
+    merged.truncate(limit);
+    Ok(merged)
+}
 
-    let stdout = String::from_utf8(output.stdout)
-        .map_err(|_| "Invalid UTF-8 in gh output")?;
-
-    let prs: Vec<GitHubPR> = serde_json::from_str(&stdout)
-        .map_err(|e| format!("Failed to parse GitHub response: {e}"))?;
-
-    Ok(prs
+/// Fetch all currently open PRs for a repo, following every page, with
+/// their review, check and merge state from `get_pr_details`.
+pub fn get_open_prs(client: &Client, owner: &str, repo: &str) -> Result<Vec<OpenPR>, GitHubError> {
+    let mut numbers = Vec::new();
+    let mut path = Some(format!("repos/{owner}/{repo}/pulls?state=open&per_page=100"));
+    while let Some(current) = path {
+        let (prs, next): (Vec<GitHubPR>, _) = client.get_page(&current)?;
+        numbers.extend(prs.iter().map(|pr| pr.number));
+        path = next;
+    }
+    if numbers.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(get_pr_details(client, owner, repo, &numbers)?
         .into_iter()
-        .map(|pr| OpenPR {
-            number: pr.number,
-            branch_name: pr.head.ref_name,
-        })
+        // Merged or closed since it was listed
+        .filter(|pr| pr.state == PrState::Open)
+        .map(OpenPR::from)
         .collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    /// Status, extra headers and body
+    type MockResponse = (u16, Vec<(&'static str, String)>, String);
+
+    /// Serve one canned response per connection, in order, on a local port.
+    /// `responses` gets the server's URL, for responses that link back to it.
+    /// Returns the URL and a handle yielding each request's request line and
+    /// headers.
+    fn mock_server(responses: impl FnOnce(&str) -> Vec<MockResponse>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = responses(&url);
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, headers, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                    request.push_str(&line);
+                }
+                // Read the whole body, or closing the socket resets the connection
+                let mut content = vec![0; length];
+                reader.read_exact(&mut content).unwrap();
+                request.push_str(&String::from_utf8(content).unwrap());
+                requests.push(request);
+                let mut head = format!(
+                    "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    body.len()
+                );
+                for (name, value) in headers {
+                    head.push_str(&format!("{name}: {value}\r\n"));
+                }
+                stream.write_all(format!("{head}\r\n{body}").as_bytes()).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    /// A PR last updated at `updated`, and merged then when `merged`.
+    fn pr_json(number: i64, branch: &str, merged: bool, updated: &str) -> serde_json::Value {
+        serde_json::json!({
+            "number": number,
+            "title": format!("PR {number}"),
+            "state": if merged { "closed" } else { "open" },
+            "merged_at": merged.then_some(updated),
+            "created_at": "2024-01-01T10:00:00Z",
+            "updated_at": updated,
+            "merge_commit_sha": merged.then(|| format!("{number:040}")),
+            "head": { "ref": branch },
+            "user": { "login": "octocat", "avatar_url": "https://example.com/a.png" },
+            "labels": [{ "name": "bug" }],
+        })
+    }
+
+    /// A PR as returned for the `PrDetails` fragment: ready to land unless
+    /// the test changes it.
+    fn details_json(number: i64, branch: &str, state: &str) -> serde_json::Value {
+        serde_json::json!({
+            "number": number,
+            "title": format!("PR {number}"),
+            "state": state,
+            "isDraft": false,
+            "headRefName": branch,
+            "baseRefName": "main",
+            "createdAt": "2024-05-01T10:00:00Z",
+            "mergedAt": null,
+            "author": { "login": "octocat", "avatarUrl": "https://example.com/a.png" },
+            "mergeCommit": null,
+            "additions": 1,
+            "deletions": 0,
+            "commits": { "totalCount": 1, "nodes": [{ "commit": { "oid": "a".repeat(40) } }] },
+            "labels": { "nodes": [] },
+            "reviewRequests": { "nodes": [] },
+            "reviewDecision": "APPROVED",
+            "mergeable": "MERGEABLE",
+            "head": { "nodes": [{ "commit": { "statusCheckRollup": { "state": "SUCCESS" } } }] },
+        })
+    }
+
+    /// A merged PR as returned by `MERGED_PRS_QUERY`.
+    fn merged_json(number: i64, branch: &str, merged_at: &str) -> serde_json::Value {
+        serde_json::json!({
+            "number": number,
+            "title": format!("PR {number}"),
+            "headRefName": branch,
+            "createdAt": "2024-01-01T10:00:00Z",
+            "mergedAt": merged_at,
+            "author": { "login": "octocat", "avatarUrl": "https://example.com/a.png" },
+            "mergeCommit": { "oid": format!("{number:040}") },
+            "commits": { "totalCount": 3 },
+            "labels": { "nodes": [{ "name": "bug" }] },
+        })
+    }
+
+    /// One page of the merged PR connection, ending at `cursor` if there is more.
+    fn merged_page(prs: Vec<serde_json::Value>, cursor: Option<&str>) -> String {
+        let page_info = serde_json::json!({ "hasNextPage": cursor.is_some(), "endCursor": cursor });
+        let connection = serde_json::json!({ "pageInfo": page_info, "nodes": prs });
+        serde_json::json!({ "data": { "repository": { "pullRequests": connection } } }).to_string()
+    }
+
+    /// One page of merged PR search results, ending at `cursor` if there is more.
+    fn search_page(prs: Vec<serde_json::Value>, cursor: Option<&str>) -> String {
+        let page_info = serde_json::json!({ "hasNextPage": cursor.is_some(), "endCursor": cursor });
+        serde_json::json!({ "data": { "search": { "pageInfo": page_info, "nodes": prs } } }).to_string()
+    }
+
+    fn graphql_body(prs: Vec<serde_json::Value>) -> String {
+        let fields: serde_json::Map<String, serde_json::Value> =
+            prs.into_iter().map(|pr| (format!("pr{}", pr["number"]), pr)).collect();
+        serde_json::json!({ "data": { "repository": fields } }).to_string()
+    }
+
+    #[test]
+    fn test_rest_client() {
+        let day = "2024-05-02T10:00:00Z";
+        let open = serde_json::json!([pr_json(3, "feature", false, day)]).to_string();
+        let merged = merged_page(vec![merged_json(2, "merged", day)], None);
+        let error = serde_json::json!({ "message": "Not Found" }).to_string();
+        let mut details = details_json(3, "feature", "OPEN");
+        details["reviewRequests"] = serde_json::json!({
+            "nodes": [{ "requestedReviewer": { "login": "hubot" } }, { "requestedReviewer": { "combinedSlug": "o/core" } }],
+        });
+        details["reviewDecision"] = serde_json::Value::Null;
+        let details = graphql_body(vec![details]);
+        let (url, server) = mock_server(|_| {
+            vec![(200, vec![], open), (200, vec![], details), (200, vec![], merged), (404, vec![], error)]
+        });
+        let client = Client::new(&format!("{url}/"), Some("secret".to_string()));
+        assert!(client.has_token());
+
+        let open = get_open_prs(&client, "o", "r").unwrap();
+        assert_eq!((open[0].number, open[0].branch_name.as_str(), open[0].title.as_str()), (3, "feature", "PR 3"));
+        assert_eq!(open[0].requested_reviewers, vec!["hubot", "o/core"]);
+        assert_eq!((open[0].review_decision, open[0].checks), (None, Some(CheckStatus::Success)));
+        assert_eq!((open[0].draft, open[0].mergeable), (false, MergeableState::Mergeable));
+
+        let merged = get_merged_prs(&client, "o", "r", "main", 5, None).unwrap();
+        assert_eq!(merged.len(), 1);
+        assert_eq!((merged[0].number, merged[0].branch_name.as_str()), (2, "merged"));
+        assert_eq!((merged[0].commit_count, merged[0].labels.as_slice()), (3, ["bug".to_string()].as_slice()));
+
+        let err = get_open_prs(&client, "o", "missing").unwrap_err();
+        assert_eq!(err.to_string(), "GitHub API 404: Not Found");
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /repos/o/r/pulls?state=open&per_page=100 HTTP/1.1"));
+        assert!(requests[1].starts_with("POST /graphql HTTP/1.1"));
+        assert!(requests[2].starts_with("POST /graphql HTTP/1.1"));
+        assert!(requests[2].contains(r#""variables":{"after":null,"base":"main","name":"r","owner":"o"}"#));
+        assert!(requests.iter().all(|r| r.to_lowercase().contains("authorization: bearer secret")));
+    }
+
+    #[test]
+    fn test_pagination() {
+        let page = |prs: Vec<serde_json::Value>| serde_json::json!(prs).to_string();
+        let created = |mut pr: serde_json::Value, at: &str| {
+            pr["createdAt"] = at.into();
+            pr
+        };
+        let (url, server) = mock_server(|url| {
+            let link = |path: &str| vec![("Link", format!("<{url}/{path}>; rel=\"next\", <{url}/last>; rel=\"last\""))];
+            vec![
+            (200, link("repositories/1/pulls?state=open&page=2"), page(vec![pr_json(9, "a", false, "2024-05-01T00:00:00Z")])),
+            (200, vec![], page(vec![pr_json(8, "b", false, "2024-05-01T00:00:00Z")])),
+            (200, vec![], graphql_body(vec![details_json(9, "a", "OPEN"), details_json(8, "b", "MERGED")])),
+            (
+                200,
+                vec![],
+                merged_page(vec![merged_json(7, "newest", "2024-05-01T00:00:00Z")], Some("Y3Vyc29yOjE=")),
+            ),
+            (
+                200,
+                vec![],
+                search_page(
+                    vec![created(merged_json(6, "recent", "2024-04-01T00:00:00Z"), "2024-01-01T10:00:00Z")],
+                    Some("Y3Vyc29yOjI="),
+                ),
+            ),
+            (
+                200,
+                vec![],
+                search_page(
+                    vec![created(merged_json(5, "long-lived", "2024-03-15T00:00:00Z"), "2023-06-01T10:00:00Z")],
+                    None,
+                ),
+            ),
+            ]
+        });
+        let client = Client::new(&url, Some("secret".to_string()));
+
+        let open: Vec<i64> = get_open_prs(&client, "o", "r").unwrap().iter().map(|pr| pr.number).collect();
+        // #8 was merged between listing it and fetching its details
+        assert_eq!(open, vec![9]);
+
+        let first = get_merged_prs_page(&client, "o", "r", "main", None, None).unwrap();
+        assert_eq!((first.prs[0].number, first.next_cursor.as_deref()), (7, Some("Y3Vyc29yOjE=")));
+
+        // With a cutoff, pages come from a search on the merge date, so a PR
+        // opened long before the cutoff still turns up on a later page
+        let first = get_merged_prs_page(&client, "o", "r", "main", None, Some("2024-03-01")).unwrap();
+        assert_eq!((first.prs[0].number, first.next_cursor.as_deref()), (6, Some("Y3Vyc29yOjI=")));
+        let second = get_merged_prs_page(&client, "o", "r", "main", first.next_cursor.as_deref(), Some("2024-03-01")).unwrap();
+        let numbers: Vec<i64> = second.prs.iter().map(|pr| pr.number).collect();
+        assert_eq!((numbers, second.next_cursor), (vec![5], None));
+
+        assert!(get_merged_prs_page(&client, "o", "r", "main", None, Some("last week")).is_err());
+
+        let requests = server.join().unwrap();
+        assert!(requests[1].starts_with("GET /repositories/1/pulls?state=open&page=2 HTTP/1.1"));
+        assert!(requests[3].contains("pullRequests(states: MERGED"));
+        let search = r#""query":"repo:o/r is:pr is:merged base:main merged:>=2024-03-01T00:00:00+00:00 sort:created-desc""#;
+        assert!(requests[4].contains(search));
+        assert!(requests[5].contains(r#""after":"Y3Vyc29yOjI=""#));
+    }
+
+    #[test]
+    fn test_pr_details() {
+        let mut pr = details_json(12, "feature", "OPEN");
+        pr["isDraft"] = true.into();
+        pr["author"] = serde_json::Value::Null;
+        pr["additions"] = 10.into();
+        pr["deletions"] = 2.into();
+        pr["commits"] = serde_json::json!({
+            "totalCount": 2,
+            "nodes": [{ "commit": { "oid": "a".repeat(40) } }, { "commit": { "oid": "b".repeat(40) } }],
+        });
+        pr["labels"] = serde_json::json!({ "nodes": [{ "name": "wip" }] });
+        pr["reviewDecision"] = "CHANGES_REQUESTED".into();
+        pr["mergeable"] = "CONFLICTING".into();
+        pr["head"] = serde_json::json!({ "nodes": [{ "commit": { "statusCheckRollup": { "state": "FAILURE" } } }] });
+        let body = serde_json::json!({
+            "data": { "repository": { "pr12": pr, "pr99": null } },
+            "errors": [{ "message": "Could not resolve to a PullRequest with the number of 99." }],
+        });
+        let missing = serde_json::json!({
+            "data": { "repository": null },
+            "errors": [{ "message": "Could not resolve to a Repository with the name 'o/missing'." }],
+        });
+        let (url, server) = mock_server(|_| {
+            vec![(200, vec![], body.to_string()), (200, vec![], body.to_string()), (200, vec![], missing.to_string())]
+        });
+        let client = Client::new(&format!("{url}/api/v3"), Some("secret".to_string()));
+
+        let details = get_pr_details(&client, "o", "r", &[12, 99, 12]).unwrap();
+        assert_eq!(details.len(), 1);
+        let pr = &details[0];
+        assert_eq!((pr.state, pr.draft, pr.author_login.as_str()), (PrState::Open, true, "ghost"));
+        assert_eq!((pr.additions, pr.deletions, pr.commit_count), (10, 2, 2));
+        assert_eq!(pr.review_decision, Some(ReviewDecision::ChangesRequested));
+        assert_eq!((pr.mergeable, pr.checks), (MergeableState::Conflicting, Some(CheckStatus::Failure)));
+        assert_eq!(pr.labels, vec!["wip"]);
+
+        let commits = get_pr_commits(&client, "o", "r", &[12]).unwrap();
+        assert_eq!(commits[&12], vec!["aaaaaaa", "bbbbbbb"]);
+
+        assert!(get_pr_details(&client, "o", "missing", &[1]).is_err());
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("POST /api/graphql HTTP/1.1"));
+        assert!(requests[0].contains(r#"pr12: pullRequest(number: 12) { ...PrDetails }\npr99: pullRequest(number: 99)"#));
+        assert!(requests[0].contains(r#""variables":{"name":"r","owner":"o"}"#));
+    }
+
+    #[test]
+    fn test_cache_and_rate_limit() {
+        let cache = std::env::temp_dir().join(format!("git-viz-test-github-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&cache);
+        let page = serde_json::json!([pr_json(2, "merged", true, "2024-05-02T10:00:00Z")]).to_string();
+        let reset = (Utc::now() + chrono::Duration::hours(1)).timestamp();
+        let quota = serde_json::json!({ "limit": 5000, "remaining": 5000, "used": 0, "reset": reset });
+        let limits = serde_json::json!({ "resources": { "core": quota, "graphql": quota } }).to_string();
+        let (url, server) = mock_server(|_| {
+            vec![
+                (200, vec![("ETag", "\"v1\"".to_string())], page),
+                (304, vec![], String::new()),
+                (
+                    403,
+                    vec![("X-RateLimit-Remaining", "0".to_string()), ("X-RateLimit-Reset", reset.to_string())],
+                    serde_json::json!({ "message": "API rate limit exceeded" }).to_string(),
+                ),
+                (200, vec![], limits),
+                (304, vec![], String::new()),
+            ]
+        });
+        let client = Client::new(&url, Some("secret".to_string())).with_cache(cache.clone());
+        let fetch = || client.get_page::<Vec<GitHubPR>>("repos/o/r/pulls?state=closed").map(|(prs, _)| prs);
+
+        assert_eq!(fetch().unwrap()[0].number, 2);
+        // Not modified: served from the cache
+        assert_eq!(fetch().unwrap()[0].number, 2);
+
+        assert!(matches!(fetch(), Err(GitHubError::RateLimited { reset_at }) if reset_at.timestamp() == reset));
+        // Fails fast until the reset, without asking GitHub
+        let err = serde_json::to_value(fetch().unwrap_err()).unwrap();
+        assert_eq!(err["kind"], "rateLimited");
+        assert_eq!(err["resetAt"].as_str().map(|t| parse_since(t).unwrap().timestamp()), Some(reset));
+
+        let limits = client.rate_limit().unwrap();
+        assert_eq!((limits.core.remaining, limits.graphql.limit), (5000, 5000));
+        // Quota is back, so requests go out again
+        assert_eq!(fetch().unwrap()[0].number, 2);
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 5);
+        assert!(!requests[0].to_lowercase().contains("if-none-match"));
+        assert!(requests[1].to_lowercase().contains("if-none-match: \"v1\""));
+        assert!(requests[3].starts_with("GET /rate_limit HTTP/1.1"));
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn test_cache_files() {
+        let cache = std::env::temp_dir().join(format!("git-viz-test-github-prune-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&cache);
+
+        // Entry names must not change between builds, or every upgrade
+        // would start from a cold cache
+        let client = Client::new(DEFAULT_API_URL, None).with_cache(cache.clone());
+        assert_eq!(
+            client.cache_path("repos/o/r/pulls"),
+            Some(cache.join("91f62b9e5285c5fd694e794ae3bc3f7cfc4644b8583bd8584bde0ee259fe96df.json"))
+        );
+
+        let age = |name: &str, days: u64| {
+            let path = cache.join(name);
+            write_atomically(&path, &[b'x'; 100]).unwrap();
+            let modified = std::time::SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+            std::fs::File::options().append(true).open(&path).unwrap().set_modified(modified).unwrap();
+        };
+        age("stale.json", 40);
+        age("old.json", 3);
+        age("recent.json", 1);
+        age("new.json", 0);
+
+        prune_cache(&cache, CACHE_MAX_AGE, 250);
+        let mut left: Vec<String> = std::fs::read_dir(&cache)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["new.json", "recent.json"]);
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
 
     #[test]
     fn test_parse_ssh_url() {
@@ -1,10 +1,12 @@
+mod dates;
 mod git;
 mod github;
 
 use tauri::Manager;
 
-use git::{Branch, DirectCommit, MergeNode};
-use github::{GitHubInfo, MergedPR, OpenPR};
+use git::{BackendKind, Branch, Commit, DiffRange, LogQuery, MergeFilter, MergeNode, RepoKind};
+use github::{GitHubError, GitHubInfo, MergedPR, MergedPRPage, OpenPR, PullRequestDetails, RateLimits};
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
 /// Resolve a CLI binary name to its full path.
@@ -83,11 +85,13 @@ pub struct DirEntry {
     path: String,
     is_dir: bool,
     is_repo: bool,
+    /// Normal, bare, linked worktree or submodule; `None` when not a repo
+    repo_kind: Option<RepoKind>,
 }
 
 /// List contents of a directory.
 /// Returns directories first (sorted), then files (sorted).
-/// For directories, also indicates if they are git repositories.
+/// For directories, also indicates if they are git repositories, and what kind.
 #[tauri::command(rename_all = "camelCase")]
 fn list_directory(path: String) -> Result<Vec<DirEntry>, String> {
     let dir = Path::new(&path);
@@ -115,13 +119,14 @@ fn list_directory(path: String) -> Result<Vec<DirEntry>, String> {
 
         let entry_path = entry.path();
         let is_dir = entry_path.is_dir();
-        let is_repo = is_dir && entry_path.join(".git").exists();
+        let repo_kind = if is_dir { git::repo_kind(&entry_path) } else { None };
 
         let item = DirEntry {
             name,
             path: entry_path.to_string_lossy().to_string(),
             is_dir,
-            is_repo,
+            is_repo: repo_kind.is_some(),
+            repo_kind,
         };
 
         if is_dir {
@@ -189,7 +194,8 @@ const DEV_FOLDERS: &[&str] = &[
 ];
 
 /// Search for git repositories matching a query.
-/// Only returns directories containing a .git folder.
+/// Only returns repository roots (work trees, including submodules and linked
+/// worktrees, and bare repositories); nested repos are found too.
 /// When at home directory, only searches inside common dev folders.
 /// Returns up to `limit` matches sorted by relevance.
 #[tauri::command(rename_all = "camelCase")]
@@ -294,9 +300,9 @@ fn search_repos_recursive(
         }
 
         // Check if this is a git repository
-        let is_repo = entry_path.join(".git").exists();
+        let repo_kind = git::repo_kind(&entry_path);
 
-        if is_repo {
+        if repo_kind.is_some() {
             // Only add if name matches query
             let name_lower = name.to_lowercase();
             if query.is_empty() || name_lower.starts_with(query) || name_lower.contains(query) {
@@ -305,18 +311,23 @@ fn search_repos_recursive(
                     path: entry_path.to_string_lossy().to_string(),
                     is_dir: true,
                     is_repo: true,
+                    repo_kind,
                 });
 
                 if results.len() >= limit {
                     return true;
                 }
             }
-            // Don't recurse into repos (nested repos are rare)
-        } else {
-            // Not a repo, recurse to find repos inside
-            if search_repos_recursive(&entry_path, query, depth + 1, max_depth, results, limit) {
-                return true;
-            }
+        }
+
+        // A bare repo's directory is all git internals
+        if repo_kind == Some(RepoKind::Bare) {
+            continue;
+        }
+
+        // Recurse into repos as well, to find submodules and nested repos
+        if search_repos_recursive(&entry_path, query, depth + 1, max_depth, results, limit) {
+            return true;
         }
     }
 
@@ -345,19 +356,181 @@ fn get_branches(repo_path: String) -> Result<Vec<Branch>, String> {
     git::list_branches(path, &default).map_err(|e| e.to_string())
 }
 
+/// Branches whose work landed on the default branch as a squash merge or as
+/// cherry-picks, keyed by name. Slower than `get_branches`, so it runs on
+/// request; later branch listings include what it found.
+#[tauri::command]
+fn get_equivalent_merges(repo_path: String) -> Result<BTreeMap<String, git::MergedBy>, String> {
+    let path = Path::new(&repo_path);
+    let default = git::get_default_branch(path).unwrap_or_else(|_| "main".to_string());
+    git::equivalent_merges(path, &default).map_err(|e| e.to_string())
+}
+
+/// Names of the repository's remotes (e.g. `origin`, `upstream`).
+#[tauri::command]
+fn get_remotes(repo_path: String) -> Result<Vec<String>, String> {
+    let path = Path::new(&repo_path);
+    git::list_remotes(path).map_err(|e| e.to_string())
+}
+
+/// Branches that live on or track a single remote.
+#[tauri::command]
+fn get_remote_branches(repo_path: String, remote: String) -> Result<Vec<Branch>, String> {
+    let path = Path::new(&repo_path);
+    let default = git::get_default_branch(path).unwrap_or_else(|_| "main".to_string());
+    git::list_remote_branches(path, &default, &remote).map_err(|e| e.to_string())
+}
+
+/// Try merging one branch into the default branch in memory and report conflicts.
+#[tauri::command]
+fn predict_merge(repo_path: String, branch: String) -> Result<git::MergePrediction, String> {
+    let path = Path::new(&repo_path);
+    let default = git::get_default_branch(path).unwrap_or_else(|_| "main".to_string());
+    git::predict_merge(path, &default, &branch).map_err(|e| e.to_string())
+}
+
+/// Conflict prediction for every branch. Results are cached per pair of head SHAs.
+#[tauri::command]
+fn predict_merges(repo_path: String) -> Result<Vec<git::MergePrediction>, String> {
+    let path = Path::new(&repo_path);
+    let default = git::get_default_branch(path).unwrap_or_else(|_| "main".to_string());
+    git::predict_merges(path, &default).map_err(|e| e.to_string())
+}
+
+/// Overlapping files and merge conflicts between every pair of `branches`,
+/// to spot PRs that have to land in a particular order.
+#[tauri::command]
+fn get_conflict_matrix(repo_path: String, branches: Vec<String>) -> Result<git::ConflictMatrix, String> {
+    let path = Path::new(&repo_path);
+    let default = git::get_default_branch(path).unwrap_or_else(|_| "main".to_string());
+    git::conflict_matrix(path, &default, &branches).map_err(|e| e.to_string())
+}
+
+/// Effective staleness rules for a repo (defaults, app settings, `.git-visualizer.toml`).
+/// Fails on an invalid settings file, which `get_branches` ignores in favour
+/// of the defaults, so this is where the error shows up.
+#[tauri::command]
+fn get_staleness_rules(repo_path: String) -> Result<git::StalenessRules, String> {
+    git::StalenessRules::load(Path::new(&repo_path)).map_err(|e| e.to_string())
+}
+
+/// Delete local branches. With `dry_run`, only report what would be deleted
+/// and what is refused (default, protected or checked-out branches).
+#[tauri::command]
+fn delete_local_branches(repo_path: String, branches: Vec<String>, dry_run: bool) -> Result<git::CleanupReport, String> {
+    let path = Path::new(&repo_path);
+    let default = git::get_default_branch(path).unwrap_or_else(|_| "main".to_string());
+    git::delete_local_branches(path, &default, &branches, dry_run).map_err(|e| e.to_string())
+}
+
+/// Delete branches on a remote by pushing deletions.
+#[tauri::command]
+fn delete_remote_branches(
+    repo_path: String,
+    remote: String,
+    branches: Vec<String>,
+    dry_run: bool,
+) -> Result<git::CleanupReport, String> {
+    let path = Path::new(&repo_path);
+    let default = git::get_default_branch(path).unwrap_or_else(|_| "main".to_string());
+    git::delete_remote_branches(path, &default, &remote, &branches, dry_run).map_err(|e| e.to_string())
+}
+
+/// Remove remote-tracking refs for branches deleted on the remote.
+#[tauri::command]
+fn prune_remote_refs(repo_path: String, remote: String, dry_run: bool) -> Result<git::CleanupReport, String> {
+    let path = Path::new(&repo_path);
+    let default = git::get_default_branch(path).unwrap_or_else(|_| "main".to_string());
+    git::prune_remote(path, &default, &remote, dry_run).map_err(|e| e.to_string())
+}
+
+/// Deletions that can be undone, newest first.
+#[tauri::command]
+fn get_undo_journal(repo_path: String) -> Result<Vec<git::Deletion>, String> {
+    git::undo_journal(Path::new(&repo_path)).map_err(|e| e.to_string())
+}
+
+/// Restore a deleted ref from its undo journal entry.
+#[tauri::command]
+fn undo_deletion(repo_path: String, id: String) -> Result<git::Deletion, String> {
+    git::undo_deletion(Path::new(&repo_path), &id).map_err(|e| e.to_string())
+}
+
+/// Tags on commits, semver releases first (highest version first).
+#[tauri::command]
+fn get_tags(repo_path: String) -> Result<Vec<git::Tag>, String> {
+    git::list_tags(Path::new(&repo_path)).map_err(|e| e.to_string())
+}
+
+/// For each merge node SHA, the first release tag that contains it. SHAs not
+/// yet released are missing from the map.
+#[tauri::command]
+fn get_pr_releases(repo_path: String, shas: Vec<String>) -> Result<HashMap<String, String>, String> {
+    git::first_releases(Path::new(&repo_path), &shas).map_err(|e| e.to_string())
+}
+
+/// Release notes for the PRs in `from..to` (e.g. two tags), grouped by
+/// Conventional Commit type or GitHub label, as JSON plus rendered Markdown.
+#[tauri::command]
+fn get_release_notes(
+    repo_path: String,
+    from: String,
+    to: String,
+    merged_prs: Option<Vec<git::KnownPr>>,
+    group_by: Option<git::GroupBy>,
+) -> Result<git::ReleaseNotes, String> {
+    let known = merged_prs.unwrap_or_default();
+    git::release_notes(Path::new(&repo_path), &from, &to, &known, group_by.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// Signature status of every commit on a branch that isn't on the default
+/// branch yet, with an all signed / some unsigned / bad signature summary.
+#[tauri::command]
+fn get_branch_signatures(repo_path: String, branch: String) -> Result<git::BranchSignatures, String> {
+    let path = Path::new(&repo_path);
+    let default = git::get_default_branch(path).unwrap_or_else(|_| "main".to_string());
+    git::branch_signatures(path, &default, &branch).map_err(|e| e.to_string())
+}
+
+/// Every working tree of the repo, main first, with its branch and whether
+/// it has uncommitted changes.
+#[tauri::command]
+fn get_worktrees(repo_path: String) -> Result<Vec<git::Worktree>, String> {
+    git::list_worktrees(Path::new(&repo_path)).map_err(|e| e.to_string())
+}
+
+/// PR nodes on a branch (merge, squash and rebase merges). Pass the merged
+/// PRs from `get_merged_prs`, when available, to match them by commit SHA.
 #[tauri::command]
 fn get_merge_nodes(
     repo_path: String,
     branch: String,
     page: u32,
     per_page: u32,
+    merged_prs: Option<Vec<git::KnownPr>>,
 ) -> Result<MergeNodesResponse, String> {
     let path = Path::new(&repo_path);
+    let known = merged_prs.unwrap_or_default();
     let (nodes, has_more) =
-        git::get_merge_commits(path, &branch, page, per_page).map_err(|e| e.to_string())?;
+        git::get_merge_nodes(path, &branch, page, per_page, &known).map_err(|e| e.to_string())?;
     Ok(MergeNodesResponse { nodes, has_more })
 }
 
+/// Name of the active git backend ("cli" or "libgit2").
+#[tauri::command]
+fn get_git_backend() -> BackendKind {
+    git::backend_kind()
+}
+
+/// Switch the git backend used by all subsequent commands.
+#[tauri::command]
+fn set_git_backend(name: String) -> Result<BackendKind, String> {
+    let kind = BackendKind::parse(&name).ok_or_else(|| format!("Unknown git backend: {name}"))?;
+    git::set_backend(kind);
+    Ok(kind)
+}
+
 #[tauri::command]
 fn get_default_branch(repo_path: String) -> Result<String, String> {
     let path = Path::new(&repo_path);
@@ -374,12 +547,47 @@ fn get_repo_info(repo_path: String) -> Result<RepoInfo, String> {
     })
 }
 
+/// Uncommitted changes, stashes and any operation in progress in the
+/// checkout at `repo_path`, for the "you are here" node on the map.
+#[tauri::command]
+fn get_repo_status(repo_path: String) -> Result<git::RepoStatus, String> {
+    git::repo_status(Path::new(&repo_path)).map_err(|e| e.to_string())
+}
+
+/// Stash entries with the commit each was made on and the files it holds.
+#[tauri::command]
+fn get_stashes(repo_path: String) -> Result<Vec<git::Stash>, String> {
+    git::list_stashes(Path::new(&repo_path)).map_err(|e| e.to_string())
+}
+
+/// Patch of a stash entry (by SHA) against the commit it was made on.
+#[tauri::command]
+fn get_stash_diff(repo_path: String, sha: String) -> Result<String, String> {
+    git::stash_diff(Path::new(&repo_path), &sha).map_err(|e| e.to_string())
+}
+
+/// Submodules with their pinned commit compared to the branch they follow.
+#[tauri::command]
+fn get_submodules(repo_path: String) -> Result<Vec<git::Submodule>, String> {
+    git::list_submodules(Path::new(&repo_path)).map_err(|e| e.to_string())
+}
+
+/// Open a submodule (by path or name) as a repository of its own.
+#[tauri::command]
+fn open_submodule(repo_path: String, path: String) -> Result<RepoInfo, String> {
+    let (name, full_path) = git::open_submodule(Path::new(&repo_path), &path).map_err(|e| e.to_string())?;
+    Ok(RepoInfo {
+        name,
+        path: full_path,
+    })
+}
+
 // =============================================================================
 // GitHub Integration
 // =============================================================================
 
 #[tauri::command]
-fn get_github_info(repo_path: String) -> Result<GitHubInfo, String> {
+fn get_github_info(repo_path: String) -> Result<GitHubInfo, GitHubError> {
     let path = Path::new(&repo_path);
     github::get_github_info(path)
 }
@@ -390,14 +598,39 @@ fn get_merged_prs(
     repo: String,
     base_branch: String,
     limit: Option<usize>,
-) -> Result<Vec<MergedPR>, String> {
+    since: Option<String>,
+) -> Result<Vec<MergedPR>, GitHubError> {
     let limit = limit.unwrap_or(50);
-    github::get_merged_prs(&owner, &repo, &base_branch, limit)
+    let client = github::Client::from_settings()?;
+    github::get_merged_prs(&client, &owner, &repo, &base_branch, limit, since.as_deref())
+}
+
+/// One page of merged PRs, newest first. Pass the returned `nextCursor` back
+/// as `cursor` to load older ones, until it comes back null.
+#[tauri::command(rename_all = "camelCase")]
+fn get_merged_prs_page(
+    owner: String,
+    repo: String,
+    base_branch: String,
+    cursor: Option<String>,
+    since: Option<String>,
+) -> Result<MergedPRPage, GitHubError> {
+    let client = github::Client::from_settings()?;
+    github::get_merged_prs_page(&client, &owner, &repo, &base_branch, cursor.as_deref(), since.as_deref())
+}
+
+#[tauri::command(rename_all = "camelCase")]
+fn get_open_prs(owner: String, repo: String) -> Result<Vec<OpenPR>, GitHubError> {
+    let client = github::Client::from_settings()?;
+    github::get_open_prs(&client, &owner, &repo)
 }
 
+/// Commits, review and check state, labels and size of each PR, open or
+/// merged, fetched a batch at a time over GraphQL.
 #[tauri::command(rename_all = "camelCase")]
-fn get_open_prs(owner: String, repo: String) -> Result<Vec<OpenPR>, String> {
-    github::get_open_prs(&owner, &repo)
+fn get_pr_details(owner: String, repo: String, pr_numbers: Vec<i64>) -> Result<Vec<PullRequestDetails>, GitHubError> {
+    let client = github::Client::from_settings()?;
+    github::get_pr_details(&client, &owner, &repo, &pr_numbers)
 }
 
 #[tauri::command(rename_all = "camelCase")]
@@ -405,8 +638,16 @@ fn get_pr_commits(
     owner: String,
     repo: String,
     pr_numbers: Vec<i64>,
-) -> Result<std::collections::HashMap<i64, Vec<String>>, String> {
-    github::get_pr_commits(&owner, &repo, &pr_numbers)
+) -> Result<std::collections::HashMap<i64, Vec<String>>, GitHubError> {
+    let client = github::Client::from_settings()?;
+    github::get_pr_commits(&client, &owner, &repo, &pr_numbers)
+}
+
+/// Remaining GitHub API quota, REST and GraphQL. Checking doesn't use any.
+#[tauri::command]
+fn get_github_rate_limit() -> Result<RateLimits, GitHubError> {
+    let client = github::Client::from_settings()?;
+    client.rate_limit()
 }
 
 #[tauri::command(rename_all = "camelCase")]
@@ -420,12 +661,13 @@ fn get_branch_diff(
     let diff = if let Some(sha) = merge_commit_sha {
         // Historical diff: show what this PR added when it was merged
         let parent = format!("{}^1", sha);
-        git::cli::run(path, &["diff", &parent, &sha, "--unified=3"])
+        git::backend()
+            .diff(path, DiffRange::Between(&parent, &sha))
             .map_err(|e| e.to_string())?
     } else {
         // Current diff: unmerged changes ahead of base branch
-        let range = format!("{}...{}", base_branch, branch);
-        git::cli::run(path, &["diff", &range, "--unified=3"])
+        git::backend()
+            .diff(path, DiffRange::Since(&base_branch, &branch))
             .map_err(|e| e.to_string())?
     };
     const MAX_CHARS: usize = 60_000;
@@ -436,52 +678,32 @@ fn get_branch_diff(
     }
 }
 
-#[derive(serde::Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct CommitInfo {
-    sha: String,
-    message: String,
-    author: String,
-    date: String,
-}
-
 #[tauri::command(rename_all = "camelCase")]
 fn get_branch_commits(
     repo_path: String,
     branch: String,
     base_branch: String,
     merge_commit_sha: Option<String>,
-) -> Result<Vec<CommitInfo>, String> {
+    verify_signatures: Option<bool>,
+) -> Result<Vec<Commit>, String> {
     let path = Path::new(&repo_path);
-    let range = if let Some(sha) = merge_commit_sha {
+    let parent;
+    let (include, exclude) = if let Some(sha) = merge_commit_sha.as_deref() {
         // Commits that were part of this merged PR
-        format!("{}^1..{}", sha, sha)
+        parent = format!("{}^1", sha);
+        (sha, parent.as_str())
     } else {
         // Commits on this branch not yet in base
-        format!("{}..{}", base_branch, branch)
+        (branch.as_str(), base_branch.as_str())
     };
-    let output = git::cli::run(
-        path,
-        &["log", &range, "--format=%H|%h|%s|%an|%aI", "--no-merges"],
-    )
-    .map_err(|e| e.to_string())?;
-
-    let commits = output
-        .lines()
-        .filter(|l| !l.is_empty())
-        .filter_map(|line| {
-            let parts: Vec<&str> = line.splitn(5, '|').collect();
-            if parts.len() < 5 { return None; }
-            Some(CommitInfo {
-                sha: parts[1].to_string(),
-                message: parts[2].to_string(),
-                author: parts[3].to_string(),
-                date: parts[4].to_string(),
-            })
-        })
-        .collect();
-
-    Ok(commits)
+    let query = LogQuery {
+        include: &[include],
+        exclude: Some(exclude),
+        merges: MergeFilter::Exclude,
+        verify_signatures: verify_signatures.unwrap_or(false),
+        ..Default::default()
+    };
+    git::backend().log(path, &query).map_err(|e| e.to_string())
 }
 
 #[tauri::command(rename_all = "camelCase")]
@@ -489,7 +711,7 @@ fn get_direct_commits(
     repo_path: String,
     branch: String,
     limit: Option<u32>,
-) -> Result<Vec<DirectCommit>, String> {
+) -> Result<Vec<Commit>, String> {
     let path = Path::new(&repo_path);
     let limit = limit.unwrap_or(200);
     git::get_direct_commits(path, &branch, limit).map_err(|e| e.to_string())
@@ -501,29 +723,37 @@ fn get_recent_log(
     repo_path: String,
     branch: String,
     limit: Option<u32>,
-) -> Result<Vec<CommitInfo>, String> {
+) -> Result<Vec<Commit>, String> {
     let path = Path::new(&repo_path);
-    let limit_str = limit.unwrap_or(20).to_string();
-    let output = git::cli::run(
-        path,
-        &["log", &branch, &format!("--max-count={}", limit_str), "--format=%H|%h|%s|%an|%aI", "--no-merges"],
-    )
-    .map_err(|e| e.to_string())?;
-    let commits = output
-        .lines()
-        .filter(|l| !l.is_empty())
-        .filter_map(|line| {
-            let parts: Vec<&str> = line.splitn(5, '|').collect();
-            if parts.len() < 5 { return None; }
-            Some(CommitInfo {
-                sha: parts[1].to_string(),
-                message: parts[2].to_string(),
-                author: parts[3].to_string(),
-                date: parts[4].to_string(),
-            })
-        })
-        .collect();
-    Ok(commits)
+    let query = LogQuery {
+        include: &[&branch],
+        merges: MergeFilter::Exclude,
+        max_count: Some(limit.unwrap_or(20)),
+        ..Default::default()
+    };
+    git::backend().log(path, &query).map_err(|e| e.to_string())
+}
+
+/// Commit DAG with parents, ref labels and lane columns for the timeline.
+/// `since` is an ISO 8601 date or date-time; `refs` defaults to every branch and tag.
+/// Pass the number of commits loaded so far as `skip` to load the next window.
+#[tauri::command(rename_all = "camelCase")]
+fn get_commit_graph(
+    repo_path: String,
+    refs: Option<Vec<String>>,
+    max_count: Option<u32>,
+    skip: Option<u32>,
+    since: Option<String>,
+) -> Result<git::CommitGraph, String> {
+    let path = Path::new(&repo_path);
+    let since = since.as_deref().map(dates::parse_since).transpose()?.map(|time| time.timestamp());
+    let window = git::GraphWindow {
+        refs: refs.unwrap_or_default(),
+        max_count,
+        skip: skip.unwrap_or_default(),
+        since,
+    };
+    git::get_commit_graph(path, &window).map_err(|e| e.to_string())
 }
 
 // =============================================================================
@@ -552,18 +782,17 @@ fn normalize_name(s: &str) -> String {
 
 /// All page routes that exist in the repo at HEAD (used for fuzzy matching).
 fn all_page_routes(repo_path: &Path) -> Vec<String> {
-    let Ok(output) = git::cli::run(
+    let Ok(files) = git::backend().ls_tree(
         repo_path,
-        &["ls-tree", "-r", "--name-only", "HEAD", "--",
-          "app", "pages", "src/app", "src/pages", "src/routes"],
+        "HEAD",
+        &["app", "pages", "src/app", "src/pages", "src/routes"],
     ) else {
         return Vec::new();
     };
     let mut seen = std::collections::HashSet::new();
-    output
-        .lines()
-        .filter(|l| !l.is_empty())
-        .filter_map(file_to_route)
+    files
+        .iter()
+        .filter_map(|f| file_to_route(f))
         .filter(|r| seen.insert(r.clone()))
         .collect()
 }
@@ -632,9 +861,8 @@ fn fuzzy_matched_routes<'a>(changed_files: &[&str], all_routes: &'a [String]) ->
 #[tauri::command(rename_all = "camelCase")]
 fn debug_diff_files(repo_path: String, branch: String, base_branch: String) -> String {
     let path = Path::new(&repo_path);
-    let range = format!("{}...{}", base_branch, branch);
-    match git::cli::run(path, &["diff", "--name-only", &range]) {
-        Ok(s) => format!("OK: {:?}", s),
+    match git::backend().diff_names(path, DiffRange::Since(&base_branch, &branch)) {
+        Ok(files) => format!("OK: {:?}", files.join("\n")),
         Err(e) => format!("ERR: {e}"),
     }
 }
@@ -678,13 +906,11 @@ fn get_changed_routes(
     base_branch: String,
 ) -> Result<Vec<String>, String> {
     let path = Path::new(&repo_path);
-    let output = git::cli::run(
-        path,
-        &["diff", "--name-only", &format!("{}...{}", base_branch, branch)],
-    )
-    .map_err(|e| e.to_string())?;
+    let files = git::backend()
+        .diff_names(path, DiffRange::Since(&base_branch, &branch))
+        .map_err(|e| e.to_string())?;
 
-    let changed_files: Vec<&str> = output.lines().filter(|l| !l.is_empty()).collect();
+    let changed_files: Vec<&str> = files.iter().map(String::as_str).collect();
 
     let mut seen = std::collections::HashSet::new();
 
@@ -836,6 +1062,7 @@ async fn generate_preview_routes(repo_path: String, branch: String, fallback_sha
 /// Opens a visible Chrome window pointed at the branch's dev server so the user
 /// can authenticate. The session is stored in `~/.git-viz-preview-auth/setup`
 /// and is automatically seeded into CDP screenshot profiles on the next run.
+/// Like previews, a branch checked out in a worktree runs from there.
 fn run_open_browser_blocking(repo_path: String, branch: String, port: u16) -> Result<(), String> {
     use std::process::Stdio;
     use std::time::{Duration, Instant};
@@ -845,76 +1072,21 @@ fn run_open_browser_blocking(repo_path: String, branch: String, port: u16) -> Re
     let slug: String = branch.chars()
         .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
         .collect();
-    let preview_dir = std::env::temp_dir().join(format!("git-viz-preview-{slug}-{port}"));
-
-    let _ = std::fs::remove_dir_all(&preview_dir);
-    std::fs::create_dir_all(&preview_dir)
-        .map_err(|e| format!("Failed to create preview dir: {e}"))?;
-
-    let archive_path = std::env::temp_dir().join(format!("git-viz-archive-{port}.tar"));
-    let _ = std::fs::remove_file(&archive_path);
-
-    let arch_out = std::process::Command::new("git")
-        .args(["-C", &repo_path, "archive", "--format=tar", &branch])
-        .output()
-        .map_err(|e| format!("git archive failed to start: {e}"))?;
-
-    if !arch_out.status.success() {
-        let _ = std::fs::remove_dir_all(&preview_dir);
-        return Err(format!(
-            "git archive failed for branch '{}': {}",
-            branch,
-            String::from_utf8_lossy(&arch_out.stderr).trim()
-        ));
-    }
-
-    std::fs::write(&archive_path, &arch_out.stdout)
-        .map_err(|e| format!("Failed to write archive: {e}"))?;
-
-    let tar_out = std::process::Command::new("tar")
-        .args(["-xf", archive_path.to_str().unwrap_or(""), "-C", preview_dir.to_str().unwrap_or("")])
-        .output()
-        .map_err(|e| format!("tar failed to start: {e}"))?;
-
-    let _ = std::fs::remove_file(&archive_path);
-
-    if !tar_out.status.success() {
-        let _ = std::fs::remove_dir_all(&preview_dir);
-        return Err(format!(
-            "tar extraction failed: {}",
-            String::from_utf8_lossy(&tar_out.stderr).trim()
-        ));
-    }
+    let mut preview = PreviewDir::prepare(repo, &branch, None, &slug, port, false)?;
+    let preview_dir = preview.path.clone();
 
     if !preview_dir.join("package.json").exists() {
-        let _ = std::fs::remove_dir_all(&preview_dir);
         return Err("No package.json — not a Node.js project".to_string());
     }
 
-    for name in &[
-        ".env", ".env.local", ".env.development", ".env.development.local",
-        ".env.production", ".env.production.local",
-    ] {
-        let src = repo.join(name);
-        if src.exists() {
-            let _ = std::fs::copy(&src, preview_dir.join(name));
-        }
-    }
-
     let pm_name = if preview_dir.join("bun.lockb").exists() { "bun" }
         else if preview_dir.join("pnpm-lock.yaml").exists() { "pnpm" }
         else if preview_dir.join("yarn.lock").exists() { "yarn" }
         else { "npm" };
     let pm = resolve_bin(pm_name);
 
-    let main_modules = repo.join("node_modules");
-    if main_modules.exists() {
-        let _ = std::fs::remove_dir_all(main_modules.join(".vite"));
-        let link = preview_dir.join("node_modules");
-        if !link.exists() {
-            let _ = std::os::unix::fs::symlink(&main_modules, &link);
-        }
-    }
+    let _ = std::fs::remove_dir_all(repo.join("node_modules").join(".vite"));
+    preview.link_modules(repo);
 
     let port_str = port.to_string();
     let pm_args: Vec<&str> = match pm_name {
@@ -940,10 +1112,7 @@ fn run_open_browser_blocking(repo_path: String, branch: String, port: u16) -> Re
         .stdout(stdout_sink)
         .stderr(stderr_sink)
         .spawn()
-        .map_err(|e| {
-            let _ = std::fs::remove_dir_all(&preview_dir);
-            format!("Failed to start dev server ({pm_name}): {e}")
-        })?;
+        .map_err(|e| format!("Failed to start dev server ({pm_name}): {e}"))?;
 
     let requested_url = format!("http://localhost:{port}");
     let start = Instant::now();
@@ -955,7 +1124,6 @@ fn run_open_browser_blocking(repo_path: String, branch: String, port: u16) -> Re
             let tail = log.lines().rev().take(15)
                 .collect::<Vec<_>>().into_iter().rev().collect::<Vec<_>>().join("\n");
             let _ = std::fs::remove_file(&log_path);
-            let _ = std::fs::remove_dir_all(&preview_dir);
             return Err(format!("Dev server crashed.\nLog:\n{tail}"));
         }
 
@@ -979,7 +1147,6 @@ fn run_open_browser_blocking(repo_path: String, branch: String, port: u16) -> Re
         Some(u) => u,
         None => {
             let _ = server.kill();
-            let _ = std::fs::remove_dir_all(&preview_dir);
             return Err(format!("Dev server did not respond within 90s (tried port {port})"));
         }
     };
@@ -987,7 +1154,6 @@ fn run_open_browser_blocking(repo_path: String, branch: String, port: u16) -> Re
     let chrome = "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome";
     if !Path::new(chrome).exists() {
         let _ = server.kill();
-        let _ = std::fs::remove_dir_all(&preview_dir);
         return Err("Google Chrome not found — install Chrome to generate previews".to_string());
     }
 
@@ -1006,7 +1172,6 @@ fn run_open_browser_blocking(repo_path: String, branch: String, port: u16) -> Re
         .spawn()
         .map_err(|e| {
             let _ = server.kill();
-            let _ = std::fs::remove_dir_all(&preview_dir);
             format!("Failed to launch Chrome: {e}")
         })?;
 
@@ -1014,7 +1179,6 @@ fn run_open_browser_blocking(repo_path: String, branch: String, port: u16) -> Re
     let _ = chrome_proc.wait();
 
     let _ = server.kill();
-    let _ = std::fs::remove_dir_all(&preview_dir);
     Ok(())
 }
 
@@ -1356,27 +1520,84 @@ fn uses_hash_routing(dir: &Path) -> bool {
     false
 }
 
-/// Blocking core: starts a dev server for `branch`, screenshots each `path` in
-/// sequence via the CDP script, and returns one base64 data URL per path.
-/// Empty strings indicate that a particular screenshot failed.
-fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option<String>, port: u16, paths: Vec<String>) -> Result<Vec<String>, String> {
-    use std::process::Stdio;
-    use std::time::{Duration, Instant};
-    use base64::Engine;
+/// The directory a dev server runs in for a preview. Dropping it undoes
+/// whatever the preview did to it, on every exit path: an extracted copy is
+/// removed, a reused worktree only loses the node_modules link and the .env
+/// files added to it.
+struct PreviewDir {
+    path: std::path::PathBuf,
+    /// A worktree the branch was already checked out in
+    reused: bool,
+    linked_modules: bool,
+    /// .env files copied into a reused worktree
+    added_env: Vec<std::path::PathBuf>,
+}
 
-    let repo = Path::new(&repo_path);
+impl PreviewDir {
+    /// A branch already checked out in a worktree is previewed in place, so
+    /// uncommitted work shows up too; anything else is extracted to a temp dir.
+    /// Either way the repo's .env files are there. `suppress_overlay` hides
+    /// Vite's error overlay in an extracted copy, for screenshots.
+    fn prepare(
+        repo: &Path,
+        branch: &str,
+        fallback_sha: Option<&str>,
+        slug: &str,
+        port: u16,
+        suppress_overlay: bool,
+    ) -> Result<PreviewDir, String> {
+        // Failing here rather than extracting a copy, which would be missing
+        // the uncommitted work of a worktree we couldn't see
+        let worktree = git::worktree_for_branch(repo, branch)
+            .map_err(|e| format!("Failed to list worktrees: {e}"))?
+            .filter(|w| !w.bare);
+        if let Some(worktree) = worktree {
+            let path: std::path::PathBuf = worktree.path.into();
+            // The worktree's own .env files win
+            let added_env = copy_env_files(repo, &path, false);
+            return Ok(PreviewDir { path, reused: true, linked_modules: false, added_env });
+        }
+        // Include port in the dir name so concurrent calls for the same branch
+        // (e.g. from React StrictMode double-effect) use separate directories.
+        let path = std::env::temp_dir().join(format!("git-viz-preview-{slug}-{port}"));
+        extract_preview(repo, branch, fallback_sha, &path, port)?;
+        if suppress_overlay {
+            suppress_error_overlay(&path);
+        }
+        Ok(PreviewDir { path, reused: false, linked_modules: false, added_env: Vec::new() })
+    }
 
-    // Sanitise branch name for the temp directory name
-    let slug: String = branch.chars()
-        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
-        .collect();
-    // Include port in the dir name so concurrent calls for the same branch
-    // (e.g. from React StrictMode double-effect) use separate directories.
-    let preview_dir = std::env::temp_dir().join(format!("git-viz-preview-{slug}-{port}"));
+    /// Symlink node_modules from the live repo checkout to skip install.
+    fn link_modules(&mut self, repo: &Path) {
+        let main_modules = repo.join("node_modules");
+        let link = self.path.join("node_modules");
+        if main_modules.exists() && !link.exists() {
+            self.linked_modules = std::os::unix::fs::symlink(&main_modules, &link).is_ok();
+        }
+    }
+}
+
+impl Drop for PreviewDir {
+    fn drop(&mut self) {
+        if !self.reused {
+            let _ = std::fs::remove_dir_all(&self.path);
+        } else {
+            if self.linked_modules {
+                let _ = std::fs::remove_file(self.path.join("node_modules"));
+            }
+            for file in &self.added_env {
+                let _ = std::fs::remove_file(file);
+            }
+        }
+    }
+}
 
+/// Extract `branch` (or `fallback_sha`) into a fresh `preview_dir`, with the
+/// repo's .env files added.
+fn extract_preview(repo: &Path, branch: &str, fallback_sha: Option<&str>, preview_dir: &Path, port: u16) -> Result<(), String> {
     // Always start clean
-    let _ = std::fs::remove_dir_all(&preview_dir);
-    std::fs::create_dir_all(&preview_dir)
+    let _ = std::fs::remove_dir_all(preview_dir);
+    std::fs::create_dir_all(preview_dir)
         .map_err(|e| format!("Failed to create preview dir: {e}"))?;
 
     // ── Extract branch files via git archive ─────────────────────────────────
@@ -1386,38 +1607,32 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
     let archive_path = std::env::temp_dir().join(format!("git-viz-archive-{port}.tar"));
     let _ = std::fs::remove_file(&archive_path);
 
-    let primary = std::process::Command::new("git")
-        .args(["-C", &repo_path, "archive", "--format=tar", &branch])
-        .output()
-        .map_err(|e| format!("git archive failed to start: {e}"))?;
-
-    let arch_out = if primary.status.success() {
-        primary
-    } else if let Some(ref sha) = fallback_sha {
-        let fallback = std::process::Command::new("git")
-            .args(["-C", &repo_path, "archive", "--format=tar", sha])
-            .output()
-            .map_err(|e| format!("git archive failed to start: {e}"))?;
-        if fallback.status.success() {
-            fallback
-        } else {
-            let _ = std::fs::remove_dir_all(&preview_dir);
+    let primary = git::backend().archive(repo, branch);
+
+    let tarball = match (primary, fallback_sha) {
+        (Ok(bytes), _) => bytes,
+        (Err(primary_err), Some(sha)) => match git::backend().archive(repo, sha) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                let _ = std::fs::remove_dir_all(preview_dir);
+                return Err(format!(
+                    "git archive failed for branch '{}': {}",
+                    branch,
+                    primary_err.to_string().trim()
+                ));
+            }
+        },
+        (Err(primary_err), None) => {
+            let _ = std::fs::remove_dir_all(preview_dir);
             return Err(format!(
                 "git archive failed for branch '{}': {}",
                 branch,
-                String::from_utf8_lossy(&primary.stderr).trim()
+                primary_err.to_string().trim()
             ));
         }
-    } else {
-        let _ = std::fs::remove_dir_all(&preview_dir);
-        return Err(format!(
-            "git archive failed for branch '{}': {}",
-            branch,
-            String::from_utf8_lossy(&primary.stderr).trim()
-        ));
     };
 
-    std::fs::write(&archive_path, &arch_out.stdout)
+    std::fs::write(&archive_path, &tarball)
         .map_err(|e| format!("Failed to write archive: {e}"))?;
 
     let tar_out = std::process::Command::new("tar")
@@ -1428,23 +1643,25 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
     let _ = std::fs::remove_file(&archive_path);
 
     if !tar_out.status.success() {
-        let _ = std::fs::remove_dir_all(&preview_dir);
+        let _ = std::fs::remove_dir_all(preview_dir);
         return Err(format!(
             "tar extraction failed: {}",
             String::from_utf8_lossy(&tar_out.stderr).trim()
         ));
     }
 
-    // Must be a Node.js project
-    if !preview_dir.join("package.json").exists() {
-        let _ = std::fs::remove_dir_all(&preview_dir);
-        return Err("No package.json — not a Node.js project".to_string());
-    }
-
-    // Copy .env files from the real repo into the temp dir.
-    // git archive excludes them (they're gitignored) but the app needs them
-    // to authenticate and load data — without them the app shows loading
+    // git archive excludes .env files (they're gitignored) but the app needs
+    // them to authenticate and load data — without them the app shows loading
     // skeletons or blank pages indefinitely.
+    copy_env_files(repo, preview_dir, true);
+
+    Ok(())
+}
+
+/// Copy the repo's .env files into `dir`, keeping any `dir` already has
+/// unless `overwrite`. Returns the files written.
+fn copy_env_files(repo: &Path, dir: &Path, overwrite: bool) -> Vec<std::path::PathBuf> {
+    let mut copied = Vec::new();
     for name in &[
         ".env",
         ".env.local",
@@ -1454,14 +1671,18 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
         ".env.production.local",
     ] {
         let src = repo.join(name);
-        if src.exists() {
-            let _ = std::fs::copy(&src, preview_dir.join(name));
+        let dest = dir.join(name);
+        if src.exists() && (overwrite || !dest.exists()) && std::fs::copy(&src, &dest).is_ok() {
+            copied.push(dest);
         }
     }
+    copied
+}
 
-    // Inject a MutationObserver script into index.html that removes the
-    // Vite error overlay element before the screenshot is taken.
-    // We're modifying a temp copy so the real repo is untouched.
+/// Inject a MutationObserver script into index.html that removes the Vite
+/// error overlay element before a screenshot is taken. Only for extracted
+/// copies, so the real repo is untouched.
+fn suppress_error_overlay(preview_dir: &Path) {
     let index_html_path = preview_dir.join("index.html");
     if index_html_path.exists() {
         if let Ok(html) = std::fs::read_to_string(&index_html_path) {
@@ -1486,6 +1707,30 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
             let _ = std::fs::write(&index_html_path, patched);
         }
     }
+}
+
+/// Blocking core: starts a dev server for `branch`, screenshots each `path` in
+/// sequence via the CDP script, and returns one base64 data URL per path.
+/// Empty strings indicate that a particular screenshot failed.
+fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option<String>, port: u16, paths: Vec<String>) -> Result<Vec<String>, String> {
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+    use base64::Engine;
+
+    let repo = Path::new(&repo_path);
+
+    // Sanitise branch name for the temp directory name
+    let slug: String = branch.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let mut preview = PreviewDir::prepare(repo, &branch, fallback_sha.as_deref(), &slug, port, true)?;
+    let preview_dir = preview.path.clone();
+    let reused = preview.reused;
+
+    // Must be a Node.js project
+    if !preview_dir.join("package.json").exists() {
+        return Err("No package.json — not a Node.js project".to_string());
+    }
 
     // Detect package manager from lockfile
     let pm_name = if preview_dir.join("bun.lockb").exists() { "bun" }
@@ -1494,14 +1739,7 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
         else { "npm" };
     let pm = resolve_bin(pm_name);
 
-    // Symlink node_modules from the live repo checkout to skip install
-    let main_modules = repo.join("node_modules");
-    if main_modules.exists() {
-        let link = preview_dir.join("node_modules");
-        if !link.exists() {
-            let _ = std::os::unix::fs::symlink(&main_modules, &link);
-        }
-    }
+    preview.link_modules(repo);
 
     // Restore persisted .next cache for warm server startup.
     // The cache is keyed by branch slug so different branches don't share state.
@@ -1509,7 +1747,7 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
     // into it, keeping it up to date without any post-run copy step.
     let next_cache = std::env::temp_dir().join(format!("git-viz-next-cache-{slug}"));
     let next_link = preview_dir.join(".next");
-    if next_cache.exists() {
+    if !reused && next_cache.exists() {
         let _ = std::os::unix::fs::symlink(&next_cache, &next_link);
     }
 
@@ -1543,10 +1781,7 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
         .stdout(stdout_sink)
         .stderr(stderr_sink)
         .spawn()
-        .map_err(|e| {
-            let _ = std::fs::remove_dir_all(&preview_dir);
-            format!("Failed to start dev server ({pm_name}): {e}")
-        })?;
+        .map_err(|e| format!("Failed to start dev server ({pm_name}): {e}"))?;
 
     // Poll until the server responds.  We try two URLs:
     // 1. The port we asked for (via --port flag).
@@ -1563,7 +1798,6 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
             let tail = log.lines().rev().take(15)
                 .collect::<Vec<_>>().into_iter().rev().collect::<Vec<_>>().join("\n");
             let _ = std::fs::remove_file(&log_path);
-            let _ = std::fs::remove_dir_all(&preview_dir);
             return Err(format!("Dev server crashed.\nLog:\n{tail}"));
         }
 
@@ -1592,7 +1826,6 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
             let tail = log.lines().rev().take(15)
                 .collect::<Vec<_>>().into_iter().rev().collect::<Vec<_>>().join("\n");
             let _ = std::fs::remove_file(&log_path);
-            let _ = std::fs::remove_dir_all(&preview_dir);
             return Err(if tail.is_empty() {
                 format!("Dev server did not respond within 90s (tried port {port})")
             } else {
@@ -1606,7 +1839,6 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
     let chrome = "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome";
     if !Path::new(chrome).exists() {
         let _ = server.kill();
-        let _ = std::fs::remove_dir_all(&preview_dir);
         return Err("Google Chrome not found — install Chrome to generate previews".to_string());
     }
 
@@ -1635,7 +1867,6 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
 
     if node_bin.is_none() {
         let _ = server.kill();
-        let _ = std::fs::remove_dir_all(&preview_dir);
         return Err("Node.js not found — install Node.js to generate previews".to_string());
     }
     let node_bin = node_bin.unwrap();
@@ -1712,12 +1943,13 @@ fn run_previews_blocking(repo_path: String, branch: String, fallback_sha: Option
     // Persist the .next cache for warm startup on subsequent runs.
     // Only move if it's a real directory (first run); if it's already a symlink
     // pointing at next_cache, the cache was updated in-place — nothing to do.
-    if next_link.exists() && !next_link.is_symlink() {
+    // A reused worktree keeps its own .next directory.
+    if !reused && next_link.exists() && !next_link.is_symlink() {
         let _ = std::fs::remove_dir_all(&next_cache);
         let _ = std::fs::rename(&next_link, &next_cache);
     }
 
-    let _ = std::fs::remove_dir_all(&preview_dir);
+    drop(preview);
 
     match node_out {
         Ok(out) => {
@@ -1834,13 +2066,39 @@ pub fn run() {
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             get_branches,
+            get_equivalent_merges,
+            get_remotes,
+            get_remote_branches,
+            get_staleness_rules,
+            predict_merge,
+            predict_merges,
+            get_conflict_matrix,
+            delete_local_branches,
+            delete_remote_branches,
+            prune_remote_refs,
+            get_undo_journal,
+            undo_deletion,
+            get_commit_graph,
             get_merge_nodes,
+            get_tags,
+            get_pr_releases,
+            get_release_notes,
+            get_branch_signatures,
+            get_worktrees,
             get_default_branch,
             get_repo_info,
+            get_repo_status,
+            get_stashes,
+            get_stash_diff,
+            get_submodules,
+            open_submodule,
             get_github_info,
             get_merged_prs,
+            get_merged_prs_page,
             get_open_prs,
             get_pr_commits,
+            get_pr_details,
+            get_github_rate_limit,
             list_directory,
             search_directories,
             get_home_dir,
@@ -1856,6 +2114,8 @@ pub fn run() {
             open_preview_browser,
             get_changed_routes,
             debug_diff_files,
+            get_git_backend,
+            set_git_backend,
         ])
         .run(tauri::generate_context!())
         .expect("error running tauri application");
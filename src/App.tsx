@@ -3,7 +3,7 @@ import { invoke } from '@tauri-apps/api/core';
 import BranchMapView from '../components/BranchMapView';
 import DiffViewer from '../components/DiffViewer';
 import FolderPickerModal from './FolderPickerModal';
-import type { Branch, DirectCommit, MergeNode, MergedPR, OpenPR, GitHubInfo } from '../types';
+import type { Branch, DirectCommit, MergeNode, MergedPR, MergedPRPage, OpenPR, GitHubError, GitHubInfo } from '../types';
 
 type View = 'landing' | 'map' | 'diff';
 
@@ -14,6 +14,7 @@ function App() {
   const [mergeNodes, setMergeNodes] = useState<MergeNode[]>([]);
   const [directCommits, setDirectCommits] = useState<DirectCommit[]>([]);
   const [mergedPRs, setMergedPRs] = useState<MergedPR[]>([]);
+  const [mergedPRCursor, setMergedPRCursor] = useState<string | null>(null);
   const [openPRs, setOpenPRs] = useState<OpenPR[]>([]);
   const [defaultBranch, setDefaultBranch] = useState<string>('main');
   const [loading, setLoading] = useState(false);       // button spinner in landing
@@ -32,6 +33,8 @@ function App() {
   const [githubAvailable, setGithubAvailable] = useState(false);
   const [githubOwner, setGithubOwner] = useState<string | null>(null);
   const [githubRepo, setGithubRepo] = useState<string | null>(null);
+  // When GitHub's rate limit lifts, while it is exhausted
+  const [githubLimitedUntil, setGithubLimitedUntil] = useState<Date | null>(null);
 
   // Pre-warm: screenshot main branch at '/' as soon as active branches load,
   // so DiffViewer can skip the main-side server start for the common case.
@@ -94,33 +97,53 @@ function App() {
     }
   }
 
+  /** Note a GitHub rate limit; other GitHub failures only get logged. */
+  function handleGitHubError(e: unknown, context: string) {
+    const err = e as GitHubError;
+    if (err?.kind === 'rateLimited') {
+      setGithubLimitedUntil(new Date(err.resetAt));
+    } else {
+      console.log(context, e);
+    }
+  }
+
   async function fetchGitHubData(path: string, baseBranch: string) {
     try {
       const ghInfo = await invoke<GitHubInfo>('get_github_info', { repoPath: path });
 
-      if (ghInfo.ghAvailable) {
+      if (ghInfo.tokenAvailable || ghInfo.ghAvailable) {
         setGithubAvailable(true);
         setGithubOwner(ghInfo.owner);
         setGithubRepo(ghInfo.repo);
         // Fetch merged PRs and open PRs in parallel
-        const [prs, open] = await Promise.all([
-          invoke<MergedPR[]>('get_merged_prs', {
+        const [page, open] = await Promise.all([
+          invoke<MergedPRPage>('get_merged_prs_page', {
             owner: ghInfo.owner,
             repo: ghInfo.repo,
             baseBranch,
-            limit: 50,
           }),
           invoke<OpenPR[]>('get_open_prs', {
             owner: ghInfo.owner,
             repo: ghInfo.repo,
           }),
         ]);
-        setMergedPRs(prs);
+        setGithubLimitedUntil(null);
+        setMergedPRs(page.prs);
+        setMergedPRCursor(page.nextCursor);
         setOpenPRs(open);
+        // GitHub knows whether a branch with an open PR merges cleanly
+        const mergeableByBranch = new Map(open.map((pr) => [pr.branchName, pr.mergeable]));
+        setBranches((prev) =>
+          prev.map((b) => {
+            const state = mergeableByBranch.get(b.name);
+            if (state === undefined) return b;
+            return { ...b, mergeable: state === 'unknown' ? null : state === 'mergeable' };
+          })
+        );
       }
     } catch (e) {
-      // GitHub data is optional, don't show error to user
-      console.log('GitHub data not available:', e);
+      // GitHub data is optional; only a rate limit is worth telling the user about
+      handleGitHubError(e, 'GitHub data not available:');
     }
   }
 
@@ -138,6 +161,25 @@ function App() {
     } catch (e) {
       console.error('Failed to load more nodes:', e);
     }
+    // Older merge nodes need older PRs to match against
+    if (mergedPRCursor && githubOwner && githubRepo) {
+      try {
+        const page = await invoke<MergedPRPage>('get_merged_prs_page', {
+          owner: githubOwner,
+          repo: githubRepo,
+          baseBranch: defaultBranch,
+          cursor: mergedPRCursor,
+        });
+        // A PR can show up on two pages if the list changed in between
+        setMergedPRs((prev) => {
+          const seen = new Set(prev.map((pr) => pr.number));
+          return [...prev, ...page.prs.filter((pr) => !seen.has(pr.number))];
+        });
+        setMergedPRCursor(page.nextCursor);
+      } catch (e) {
+        handleGitHubError(e, 'Older PRs not available:');
+      }
+    }
   }
 
   const openPRBranchNames = new Set(openPRs.map((p) => p.branchName));
@@ -323,9 +365,11 @@ function App() {
   function handleBackToLanding() {
     setRepoPath(null);
     setMergedPRs([]);
+    setMergedPRCursor(null);
     setOpenPRs([]);
     setDirectCommits([]);
     setGithubAvailable(false);
+    setGithubLimitedUntil(null);
     setView('landing');
   }
 
@@ -371,6 +415,15 @@ function App() {
                   </button>
                 </div>
               )}
+              {githubLimitedUntil && (
+                <span
+                  title="GitHub API rate limit exhausted; PR data may be incomplete"
+                  className="text-xs text-muted-foreground border border-border/50 rounded-full px-3 py-1"
+                >
+                  GitHub rate limited until{' '}
+                  {githubLimitedUntil.toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' })}
+                </span>
+              )}
               {(previewIsAuthGated || authSetupLoading) && (
                 <button
                   onClick={handleAuthSetup}
@@ -1,11 +1,13 @@
 import { useState, useEffect, useRef, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import type { RepoKind } from '../types';
 
 interface DirEntry {
   name: string;
   path: string;
   isDir: boolean;
   isRepo: boolean;
+  repoKind: RepoKind | null;
 }
 
 interface FolderPickerModalProps {
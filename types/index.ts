@@ -12,29 +12,88 @@ export interface Branch {
   headSha: string;
   divergedFromSha?: string;
   divergedFromDate?: string;
+  isRemote: boolean;
+  remote?: string;
+  upstream?: string;
+  upstreamAhead?: number;
+  upstreamBehind?: number;
+  /** Merge commits always; squashes and cherry-picks once `get_equivalent_merges` has found them */
+  mergedBy?: MergedBy;
+  /** Set when a local branch is checked out in a working tree */
+  worktree?: BranchWorktree | null;
 }
 
-export interface Commit {
-  sha: string;
-  message: string;
-  author: string;
-  date: string;
+export interface BranchWorktree {
+  path: string;
+  isMain: boolean;
+  dirty: boolean;
+}
+
+export interface Worktree {
+  path: string;
+  headSha: string | null;
+  branch: string | null;
+  isMain: boolean;
+  bare: boolean;
+  dirty: boolean;
 }
 
-export interface DirectCommit {
+export type MergedBy =
+  | { kind: 'merge'; sha: string }
+  | { kind: 'squash'; sha: string }
+  | { kind: 'cherryPick'; shas: string[] };
+
+export interface Commit {
   fullSha: string;
   sha: string;
   message: string;
+  body: string;
   author: string;
+  authorEmail: string;
   date: string;
+  committer: string;
+  committerEmail: string;
+  committerDate: string;
+  parents: string[];
+  tree: string;
+  conventional: ConventionalHeader | null;
+  trailers: Trailers;
+  /** Only set when signatures were verified */
+  signature: Signature | null;
+}
+
+export interface ConventionalHeader {
+  type: string;
+  scope: string | null;
+  breaking: boolean;
+  description: string;
+}
+
+export interface Person {
+  name: string;
+  email: string | null;
 }
 
+export interface Trailers {
+  coAuthors: Person[];
+  signedOffBy: Person[];
+  reviewedBy: Person[];
+  fixes: number[];
+  all: { key: string; value: string }[];
+}
+
+export type DirectCommit = Commit;
+
+export type MergeStrategy = 'merge' | 'squash' | 'rebase';
+
 export interface MergeNode {
   sha: string;
   fullSha: string;
   prNumber: number | null;
   prTitle: string | null;
   date: string;
+  strategy: MergeStrategy;
+  commitCount: number;
 }
 
 export interface MergedPR {
@@ -47,17 +106,79 @@ export interface MergedPR {
   mergedAt: string;
   mergeCommitSha: string;
   commitCount: number;
+  labels: string[];
+}
+
+export interface MergedPRPage {
+  prs: MergedPR[];
+  nextCursor: string | null;
+}
+
+export type PrState = 'open' | 'closed' | 'merged';
+export type ReviewDecision = 'approved' | 'changes-requested' | 'review-required';
+export type MergeableState = 'mergeable' | 'conflicting' | 'unknown';
+export type CheckStatus = 'success' | 'pending' | 'expected' | 'failure' | 'error';
+
+export interface PullRequestDetails {
+  number: number;
+  title: string;
+  state: PrState;
+  draft: boolean;
+  branchName: string;
+  baseBranch: string;
+  authorLogin: string;
+  authorAvatar: string;
+  createdAt: string;
+  mergedAt: string | null;
+  mergeCommitSha: string | null;
+  additions: number;
+  deletions: number;
+  commitCount: number;
+  commits: string[];
+  labels: string[];
+  requestedReviewers: string[];
+  reviewDecision: ReviewDecision | null;
+  mergeable: MergeableState;
+  checks: CheckStatus | null;
 }
 
 export interface OpenPR {
   number: number;
   branchName: string;
+  title: string;
+  authorLogin: string;
+  authorAvatar: string;
+  draft: boolean;
+  requestedReviewers: string[];
+  reviewDecision: ReviewDecision | null;
+  checks: CheckStatus | null;
+  labels: string[];
+  mergeable: MergeableState;
+}
+
+export interface Quota {
+  limit: number;
+  remaining: number;
+  used: number;
+  resetAt: string;
 }
 
+export interface RateLimits {
+  core: Quota;
+  graphql: Quota;
+}
+
+/** What GitHub commands reject with */
+export type GitHubError =
+  | { kind: 'rateLimited'; resetAt: string }
+  | { kind: 'api'; status: number; message: string }
+  | { kind: 'graphQL' | 'network' | 'ghUnavailable' | 'gh' | 'parse' | 'invalid'; message: string };
+
 export interface GitHubInfo {
   owner: string;
   repo: string;
   ghAvailable: boolean;
+  tokenAvailable: boolean;
 }
 
 export interface ChangedFile {
@@ -74,3 +195,211 @@ export interface ComponentGroup {
   deletions: number;
   files: ChangedFile[];
 }
+
+export type ConflictKind = 'content' | 'add-add' | 'modify-delete' | 'other';
+
+export interface MergeConflict {
+  path: string;
+  kind: ConflictKind;
+}
+
+export interface MergePrediction {
+  branch: string;
+  headSha: string;
+  baseSha: string;
+  clean: boolean;
+  conflicts: MergeConflict[];
+}
+
+export interface BranchPair {
+  left: string;
+  right: string;
+  overlappingFiles: string[];
+  conflicts: MergeConflict[];
+}
+
+export interface ConflictMatrix {
+  branches: string[];
+  pairs: BranchPair[];
+}
+
+export type RefKind = 'head' | 'branch' | 'remote' | 'tag';
+
+export interface RefLabel {
+  name: string;
+  kind: RefKind;
+}
+
+export interface GraphNode extends Commit {
+  refs: RefLabel[];
+  column: number;
+}
+
+export interface CommitGraph {
+  nodes: GraphNode[];
+  width: number;
+  hasMore: boolean;
+}
+
+export type DeletionKind = 'local-branch' | 'remote-branch' | 'tracking-ref';
+
+export interface Deletion {
+  /** Undo journal entry; null on a dry run */
+  id: string | null;
+  kind: DeletionKind;
+  branch: string;
+  remote: string | null;
+  sha: string;
+  deletedAt: string | null;
+}
+
+export interface Refusal {
+  branch: string;
+  reason: string;
+}
+
+export interface CleanupReport {
+  dryRun: boolean;
+  deleted: Deletion[];
+  refused: Refusal[];
+  /** Set when the deletions happened but couldn't be journaled for undo */
+  journalError: string | null;
+}
+
+export interface Version {
+  major: number;
+  minor: number;
+  patch: number;
+  pre: string | null;
+}
+
+export interface Tag {
+  name: string;
+  targetSha: string;
+  annotated: boolean;
+  taggerName: string | null;
+  taggerEmail: string | null;
+  date: string;
+  message: string | null;
+  /** Set when the tag name is a semantic version */
+  version: Version | null;
+}
+
+export type ReleaseGroupBy = 'type' | 'label';
+
+export interface ReleaseNote {
+  prNumber: number | null;
+  title: string;
+  type: string | null;
+  scope: string | null;
+  breaking: boolean;
+  labels: string[];
+  authorLogin: string | null;
+  authors: string[];
+  fullSha: string;
+  strategy: MergeStrategy;
+}
+
+export interface ReleaseSection {
+  title: string;
+  notes: ReleaseNote[];
+}
+
+export interface ReleaseNotes {
+  from: string;
+  to: string;
+  sections: ReleaseSection[];
+  markdown: string;
+}
+
+export type SignatureStatus =
+  | 'good'
+  | 'bad'
+  | 'unknown-validity'
+  | 'expired-signature'
+  | 'expired-key'
+  | 'revoked-key'
+  | 'cannot-check'
+  | 'unsigned';
+
+export interface Signature {
+  status: SignatureStatus;
+  signer: string | null;
+  key: string | null;
+}
+
+export type SignatureSummary = 'all-signed' | 'some-unsigned' | 'bad-signature';
+
+export interface BranchSignatures {
+  branch: string;
+  summary: SignatureSummary;
+  signed: number;
+  unsigned: number;
+  bad: number;
+  unverified: number;
+  commits: Commit[];
+}
+
+export type FileChange = 'added' | 'modified' | 'deleted' | 'renamed' | 'copied' | 'type-changed';
+
+/** A file changed in the working tree or index */
+export interface WorkingFileChange {
+  path: string;
+  oldPath: string | null;
+  change: FileChange;
+  /** null for binary files */
+  additions: number | null;
+  deletions: number | null;
+}
+
+export interface StashEntry {
+  index: number;
+  sha: string;
+  message: string;
+  date: string;
+}
+
+export interface Stash extends StashEntry {
+  branch: string | null;
+  baseSha: string;
+  description: string;
+  files: string[];
+  untracked: string[];
+}
+
+export type OperationKind = 'merge' | 'rebase' | 'cherry-pick' | 'revert';
+
+export interface Operation {
+  kind: OperationKind;
+  sha: string | null;
+  branch: string | null;
+  step: number | null;
+  total: number | null;
+}
+
+export interface RepoStatus {
+  branch: string | null;
+  headSha: string | null;
+  staged: WorkingFileChange[];
+  unstaged: WorkingFileChange[];
+  untracked: WorkingFileChange[];
+  conflicted: MergeConflict[];
+  stashes: StashEntry[];
+  operation: Operation | null;
+}
+
+export interface Submodule {
+  name: string;
+  path: string;
+  repoPath: string;
+  url: string | null;
+  initialized: boolean;
+  pinnedSha: string | null;
+  checkedOutSha: string | null;
+  branch: string | null;
+  branchSha: string | null;
+  behind: number | null;
+  ahead: number | null;
+}
+
+export type RepoKind = 'normal' | 'bare' | 'worktree' | 'submodule';